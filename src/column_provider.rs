@@ -0,0 +1,137 @@
+use crate::process::Process;
+use crate::tree::Node;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+// Computes an additional column that gets shown after the built-in ones.
+pub(crate) trait ColumnProvider: fmt::Debug {
+    fn header(&self) -> &str;
+
+    fn value(&self, process: &Process) -> String;
+
+    // Called with all processes after every refresh, for providers that
+    // keep track of how processes change over time.
+    fn tick(&mut self, _processes: &[Process]) {}
+}
+
+// How much cpu each thread of a process uses on average, to tell a single
+// busy thread apart from many mostly idle ones.
+#[derive(Debug)]
+pub(crate) struct CpuPerThread;
+
+impl ColumnProvider for CpuPerThread {
+    fn header(&self) -> &str {
+        "cpu/thread"
+    }
+
+    fn value(&self, process: &Process) -> String {
+        format!(
+            "{:.1}%",
+            process.own_cpu() / process.own_threads().max(1) as f32
+        )
+    }
+}
+
+// How many ticks `CpuHistory` looks back.
+const CPU_HISTORY_LENGTH: usize = 8;
+
+const SPARKLINE_BLOCKS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// A sparkline of the cpu usage of every process over the last ticks, oldest
+// sample first.
+#[derive(Debug, Default)]
+pub(crate) struct CpuHistory {
+    samples: HashMap<sysinfo::Pid, VecDeque<f32>>,
+}
+
+impl ColumnProvider for CpuHistory {
+    fn header(&self) -> &str {
+        "history"
+    }
+
+    fn value(&self, process: &Process) -> String {
+        let Some(samples) = self.samples.get(&process.id()) else {
+            return String::new();
+        };
+        samples
+            .iter()
+            .map(|cpu| {
+                let level = (cpu.clamp(0.0, 100.0) / 100.0 * 8.0).ceil() as usize;
+                SPARKLINE_BLOCKS[level]
+            })
+            .collect()
+    }
+
+    fn tick(&mut self, processes: &[Process]) {
+        let mut samples = HashMap::new();
+        for process in processes {
+            let mut history = self.samples.remove(&process.id()).unwrap_or_default();
+            if history.len() == CPU_HISTORY_LENGTH {
+                history.pop_front();
+            }
+            history.push_back(process.own_cpu());
+            samples.insert(process.id(), history);
+        }
+        // Processes that are gone aren't carried over.
+        self.samples = samples;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::process::ProcessWatcher;
+
+    #[test]
+    fn divides_cpu_by_the_number_of_threads() {
+        assert_eq!(
+            CpuPerThread.value(&Process::fake(1, 50.0, None).with_threads(4)),
+            "12.5%"
+        );
+    }
+
+    #[test]
+    fn divides_own_cpu_by_own_threads_for_parents() {
+        let forest = ProcessWatcher::fake(vec![
+            Process::fake(1, 50.0, None).with_threads(4),
+            Process::fake(2, 30.0, Some(1)).with_threads(2),
+        ])
+        .get_forest();
+        assert_eq!(
+            forest
+                .iter()
+                .map(|process| CpuPerThread.value(process))
+                .collect::<Vec<_>>(),
+            vec!["12.5%", "15.0%"]
+        );
+    }
+
+    #[test]
+    fn cpu_history_shows_the_trend_of_the_last_ticks() {
+        let mut history = CpuHistory::default();
+        for cpu in [0.0, 10.0, 30.0, 60.0, 100.0] {
+            history.tick(&[Process::fake(1, cpu, None)]);
+        }
+        assert_eq!(history.value(&Process::fake(1, 0.0, None)), " ▁▃▅█");
+    }
+
+    #[test]
+    fn cpu_history_keeps_only_the_last_ticks() {
+        let mut history = CpuHistory::default();
+        history.tick(&[Process::fake(1, 100.0, None)]);
+        for _ in 0..CPU_HISTORY_LENGTH {
+            history.tick(&[Process::fake(1, 50.0, None)]);
+        }
+        assert_eq!(history.value(&Process::fake(1, 0.0, None)), "▄".repeat(8));
+    }
+
+    #[test]
+    fn cpu_history_forgets_processes_that_are_gone() {
+        let mut history = CpuHistory::default();
+        history.tick(&[Process::fake(1, 100.0, None)]);
+        history.tick(&[Process::fake(2, 100.0, None)]);
+        assert_eq!(history.value(&Process::fake(1, 0.0, None)), "");
+        history.tick(&[Process::fake(1, 100.0, None)]);
+        assert_eq!(history.value(&Process::fake(1, 0.0, None)), "█");
+    }
+}
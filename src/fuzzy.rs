@@ -0,0 +1,78 @@
+// Scores how well `pattern` matches `text` as a case-insensitive subsequence,
+// or returns `None` if it doesn't match at all. Higher scores are better
+// matches. Consecutive characters and characters at the start of words get
+// bonuses, gaps between matched characters cost points.
+pub(crate) fn score(pattern: &str, text: &str) -> Option<i64> {
+    const MATCH: i64 = 1;
+    const CONSECUTIVE: i64 = 5;
+    const WORD_START: i64 = 3;
+    const MAX_GAP_PENALTY: usize = 3;
+
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    if pattern.is_empty() {
+        return Some(0);
+    }
+    let word_start = |j: usize| j == 0 || !text[j - 1].is_alphanumeric();
+    // best[j] is the best score for the pattern so far, with its last
+    // character matched at text[j].
+    let mut best: Vec<Option<i64>> = text
+        .iter()
+        .enumerate()
+        .map(|(j, &c)| {
+            (c == pattern[0]).then(|| MATCH + if word_start(j) { WORD_START } else { 0 })
+        })
+        .collect();
+    for &p in &pattern[1..] {
+        // All gaps of at least `MAX_GAP_PENALTY` cost the same, so only the
+        // best match that far back matters. It's kept as a running maximum.
+        let mut far_back: Option<i64> = None;
+        best = (0..text.len())
+            .map(|j| {
+                if j > MAX_GAP_PENALTY {
+                    far_back = far_back.max(best[j - MAX_GAP_PENALTY - 1]);
+                }
+                if text[j] != p {
+                    return None;
+                }
+                let bonus = MATCH + if word_start(j) { WORD_START } else { 0 };
+                let near = (j.saturating_sub(MAX_GAP_PENALTY)..j).filter_map(|k| {
+                    let gap = j - k - 1;
+                    Some(if gap == 0 {
+                        best[k]? + bonus + CONSECUTIVE
+                    } else {
+                        best[k]? + bonus - gap as i64
+                    })
+                });
+                let far = far_back.map(|previous| previous + bonus - MAX_GAP_PENALTY as i64);
+                near.chain(far).max()
+            })
+            .collect();
+    }
+    best.into_iter().flatten().max()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn matches_subsequences() {
+        assert!(score("ffx", "firefox").is_some());
+        assert!(score("FOX", "firefox").is_some());
+        assert_eq!(score("xf", "firefox"), None);
+        assert_eq!(score("", "firefox"), Some(0));
+    }
+
+    #[test]
+    fn prefers_consecutive_characters() {
+        assert!(score("ff", "fluff-finder") > score("ff", "firefox"));
+        assert!(score("fox", "firefox") > score("fox", "f-o-x"));
+    }
+
+    #[test]
+    fn prefers_word_starts() {
+        assert!(score("fb", "foo-bar") > score("fb", "foobar"));
+    }
+}
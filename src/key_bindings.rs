@@ -0,0 +1,607 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mode {
+    Normal,
+    EditingPattern,
+    ProcessSelected,
+    EnteringSignal,
+    ConfirmingSignal,
+    EnteringPresetName,
+    ConfirmingQuit,
+    EditingFilters,
+    ChoosingFilterField,
+    EnteringFilterValue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Action {
+    Quit,
+    RequestQuit,
+    ConfirmQuit,
+    ClearPattern,
+    ScrollUp,
+    PageUp,
+    ScrollDown,
+    PageDown,
+    PreviousRoot,
+    NextRoot,
+    StopEditingPattern,
+    NextSearchScope,
+    ToggleFixedStrings,
+    ToggleIgnoreCase,
+    SendTypedSignal,
+    SelectProcess,
+    EditPattern,
+    EditFilters,
+    RemoveFilter(usize),
+    BuildFilter,
+    ChooseFilterField(usize),
+    AddFilter,
+    DumpProcesses,
+    NamePreset,
+    SavePreset,
+    NextPreset,
+    NextSortColumn,
+    SortByColumn(usize),
+    ReverseSortDirection,
+    ShrinkColumn,
+    GrowColumn,
+    ToggleCompact,
+    ToggleStartTimeFormat,
+    ToggleSelf,
+    ToggleArguments,
+    ToggleWrap,
+    ToggleDepthColors,
+    NextNameSource,
+    TogglePidLabels,
+    ToggleOnlyLeaves,
+    NextGrouping,
+    ToggleTop,
+    ToggleRamPercent,
+    TogglePerCpu,
+    TogglePinSelected,
+    ToggleInverted,
+    ToggleGlobalSort,
+    CollapseAll,
+    ExpandAll,
+    CancelSignal,
+    ConfirmSignal,
+    BackToNormal,
+    SendSigterm,
+    TerminateAndWait,
+    SendSigkill,
+    SendSigusr1,
+    SendSigusr2,
+    TypeSignal,
+    ToggleBreakdown,
+    FocusBreakdown,
+    ToggleSiblings,
+    ToggleMark,
+    InvertMarks,
+    SignalMarked,
+}
+
+impl Action {
+    pub(crate) fn sends_signal(self) -> bool {
+        matches!(
+            self,
+            Action::SendTypedSignal
+                | Action::ConfirmSignal
+                | Action::SendSigterm
+                | Action::TerminateAndWait
+                | Action::SendSigkill
+                | Action::SendSigusr1
+                | Action::SendSigusr2
+                | Action::TypeSignal
+                | Action::SignalMarked
+        )
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::RequestQuit => "quit, asking first if processes are marked",
+            Action::ConfirmQuit => "quit and discard the marks",
+            Action::ClearPattern => "clear search pattern",
+            Action::ScrollUp => "scroll up",
+            Action::PageUp => "scroll up one page",
+            Action::ScrollDown => "scroll down",
+            Action::PageDown => "scroll down one page",
+            Action::PreviousRoot => "jump to the previous top-level process",
+            Action::NextRoot => "jump to the next top-level process",
+            Action::StopEditingPattern => "stop editing search pattern",
+            Action::NextSearchScope => "search in name, command line, user or all",
+            Action::ToggleFixedStrings => "match search patterns literally or as regexes",
+            Action::ToggleIgnoreCase => "match search patterns with or without regard to case",
+            Action::SendTypedSignal => "send typed signal",
+            Action::SelectProcess => "select process",
+            Action::EditPattern => "filter processes",
+            Action::EditFilters => "show the active filters to remove them",
+            Action::RemoveFilter(_) => "remove the filter with that number",
+            Action::BuildFilter => "add a filter on cpu, ram, pid or user",
+            Action::ChooseFilterField(_) => "filter on the field with that number",
+            Action::AddFilter => "add filter",
+            Action::DumpProcesses => "write the shown processes to a JSON file",
+            Action::NamePreset => "save search patterns and heat map as a preset",
+            Action::SavePreset => "save preset",
+            Action::NextPreset => "load the next saved preset",
+            Action::NextSortColumn => "sort by next column",
+            Action::SortByColumn(_) => "sort by the column with that number",
+            Action::ReverseSortDirection => "reverse sort direction",
+            Action::ShrinkColumn => "make the sorted column narrower",
+            Action::GrowColumn => "make the sorted column wider",
+            Action::ToggleCompact => "toggle compact columns",
+            Action::ToggleStartTimeFormat => "toggle between run time and start time",
+            Action::ToggleSelf => "show or hide treetop itself",
+            Action::ToggleArguments => "show or hide command line arguments",
+            Action::ToggleWrap => "wrap the highlighted command line instead of cutting it off",
+            Action::ToggleDepthColors => "color the tree lines by depth",
+            Action::NextNameSource => "name processes by argv[0], executable or kernel name",
+            Action::TogglePidLabels => "label processes by pid or by name",
+            Action::ToggleOnlyLeaves => "show only processes without children",
+            Action::NextGrouping => "show totals per executable or per user instead of the tree",
+            Action::ToggleTop => "show only the top-level processes using the most cpu",
+            Action::ToggleRamPercent => "show ram in MB or as a percentage of total memory",
+            Action::TogglePerCpu => "show cpu per process or divided by the number of cpus",
+            Action::TogglePinSelected => "keep the selected row at the top",
+            Action::ToggleInverted => "show children above their parents",
+            Action::ToggleGlobalSort => "sort all processes together instead of within siblings",
+            Action::CollapseAll => "collapse all processes",
+            Action::ExpandAll => "expand all processes",
+            Action::CancelSignal => "cancel sending signal",
+            Action::ConfirmSignal => "send signal",
+            Action::BackToNormal => "back to normal mode",
+            Action::SendSigterm => "send SIGTERM to process",
+            Action::TerminateAndWait => "send SIGTERM and report whether the process exits",
+            Action::SendSigkill => "send SIGKILL to process",
+            Action::SendSigusr1 => "send SIGUSR1 to process, often used to reopen log files",
+            Action::SendSigusr2 => "send SIGUSR2 to process, meaning depends on the program",
+            Action::TypeSignal => "type signal to send",
+            Action::ToggleBreakdown => "show own and children's cpu and ram",
+            Action::FocusBreakdown => "scroll the breakdown instead of the process list",
+            Action::ToggleSiblings => "show or hide the siblings hidden by the search pattern",
+            Action::ToggleMark => "mark or unmark process",
+            Action::InvertMarks => "mark all unmarked visible processes and unmark the others",
+            Action::SignalMarked => "send SIGTERM to all marked processes",
+        }
+    }
+}
+
+struct KeyBinding {
+    modes: &'static [Mode],
+    modifiers: KeyModifiers,
+    code: KeyCode,
+    action: Action,
+}
+
+const fn key(
+    modes: &'static [Mode],
+    modifiers: KeyModifiers,
+    code: KeyCode,
+    action: Action,
+) -> KeyBinding {
+    KeyBinding {
+        modes,
+        modifiers,
+        code,
+        action,
+    }
+}
+
+const ALL: &[Mode] = &[];
+const NORMAL: &[Mode] = &[Mode::Normal];
+const EDITING_PATTERN: &[Mode] = &[Mode::EditingPattern];
+const PROCESS_SELECTED: &[Mode] = &[Mode::ProcessSelected];
+const ENTERING_SIGNAL: &[Mode] = &[Mode::EnteringSignal];
+const CONFIRMING_SIGNAL: &[Mode] = &[Mode::ConfirmingSignal];
+const ENTERING_PRESET_NAME: &[Mode] = &[Mode::EnteringPresetName];
+const CONFIRMING_QUIT: &[Mode] = &[Mode::ConfirmingQuit];
+const EDITING_FILTERS: &[Mode] = &[Mode::EditingFilters];
+const CHOOSING_FILTER_FIELD: &[Mode] = &[Mode::ChoosingFilterField];
+const ENTERING_FILTER_VALUE: &[Mode] = &[Mode::EnteringFilterValue];
+const NONE: KeyModifiers = KeyModifiers::NONE;
+const CONTROL: KeyModifiers = KeyModifiers::CONTROL;
+
+// Bindings are matched in order, so earlier entries take precedence.
+// Keys that don't match any binding are used for text input.
+const KEY_BINDINGS: &[KeyBinding] = &[
+    key(ALL, CONTROL, KeyCode::Char('c'), Action::Quit),
+    // 'q' only quits in normal mode. With a selected process it unselects,
+    // and while typing a pattern or signal it's just text (e.g. SIGQUIT).
+    key(NORMAL, NONE, KeyCode::Char('q'), Action::RequestQuit),
+    key(
+        CONFIRMING_QUIT,
+        NONE,
+        KeyCode::Char('y'),
+        Action::ConfirmQuit,
+    ),
+    key(
+        PROCESS_SELECTED,
+        NONE,
+        KeyCode::Char('q'),
+        Action::BackToNormal,
+    ),
+    key(ALL, CONTROL, KeyCode::Char('u'), Action::ClearPattern),
+    key(ALL, NONE, KeyCode::Up, Action::ScrollUp),
+    key(ALL, NONE, KeyCode::PageUp, Action::PageUp),
+    key(ALL, NONE, KeyCode::Down, Action::ScrollDown),
+    key(ALL, NONE, KeyCode::PageDown, Action::PageDown),
+    key(
+        EDITING_PATTERN,
+        NONE,
+        KeyCode::Enter,
+        Action::StopEditingPattern,
+    ),
+    key(
+        EDITING_PATTERN,
+        CONTROL,
+        KeyCode::Char('s'),
+        Action::NextSearchScope,
+    ),
+    key(
+        EDITING_PATTERN,
+        CONTROL,
+        KeyCode::Char('f'),
+        Action::ToggleFixedStrings,
+    ),
+    key(
+        &[Mode::Normal, Mode::EditingPattern],
+        CONTROL,
+        KeyCode::Char('a'),
+        Action::ToggleIgnoreCase,
+    ),
+    key(
+        ENTERING_SIGNAL,
+        NONE,
+        KeyCode::Enter,
+        Action::SendTypedSignal,
+    ),
+    key(
+        ENTERING_PRESET_NAME,
+        NONE,
+        KeyCode::Enter,
+        Action::SavePreset,
+    ),
+    key(
+        ENTERING_FILTER_VALUE,
+        NONE,
+        KeyCode::Enter,
+        Action::AddFilter,
+    ),
+    key(ALL, NONE, KeyCode::Enter, Action::SelectProcess),
+    key(
+        &[Mode::Normal, Mode::EditingPattern, Mode::ProcessSelected],
+        NONE,
+        KeyCode::Char('/'),
+        Action::EditPattern,
+    ),
+    key(NORMAL, NONE, KeyCode::Char('-'), Action::EditFilters),
+    key(
+        EDITING_FILTERS,
+        NONE,
+        KeyCode::Char('1'),
+        Action::RemoveFilter(0),
+    ),
+    key(
+        EDITING_FILTERS,
+        NONE,
+        KeyCode::Char('2'),
+        Action::RemoveFilter(1),
+    ),
+    key(
+        EDITING_FILTERS,
+        NONE,
+        KeyCode::Char('3'),
+        Action::RemoveFilter(2),
+    ),
+    key(
+        EDITING_FILTERS,
+        NONE,
+        KeyCode::Char('4'),
+        Action::RemoveFilter(3),
+    ),
+    key(
+        EDITING_FILTERS,
+        NONE,
+        KeyCode::Char('5'),
+        Action::RemoveFilter(4),
+    ),
+    key(
+        EDITING_FILTERS,
+        NONE,
+        KeyCode::Char('6'),
+        Action::RemoveFilter(5),
+    ),
+    key(
+        EDITING_FILTERS,
+        NONE,
+        KeyCode::Char('7'),
+        Action::RemoveFilter(6),
+    ),
+    key(
+        EDITING_FILTERS,
+        NONE,
+        KeyCode::Char('8'),
+        Action::RemoveFilter(7),
+    ),
+    key(
+        EDITING_FILTERS,
+        NONE,
+        KeyCode::Char('9'),
+        Action::RemoveFilter(8),
+    ),
+    key(NORMAL, NONE, KeyCode::Char('#'), Action::BuildFilter),
+    key(
+        CHOOSING_FILTER_FIELD,
+        NONE,
+        KeyCode::Char('1'),
+        Action::ChooseFilterField(0),
+    ),
+    key(
+        CHOOSING_FILTER_FIELD,
+        NONE,
+        KeyCode::Char('2'),
+        Action::ChooseFilterField(1),
+    ),
+    key(
+        CHOOSING_FILTER_FIELD,
+        NONE,
+        KeyCode::Char('3'),
+        Action::ChooseFilterField(2),
+    ),
+    key(
+        CHOOSING_FILTER_FIELD,
+        NONE,
+        KeyCode::Char('4'),
+        Action::ChooseFilterField(3),
+    ),
+    key(ALL, NONE, KeyCode::Tab, Action::NextSortColumn),
+    key(NORMAL, NONE, KeyCode::Char('1'), Action::SortByColumn(0)),
+    key(NORMAL, NONE, KeyCode::Char('2'), Action::SortByColumn(1)),
+    key(NORMAL, NONE, KeyCode::Char('3'), Action::SortByColumn(2)),
+    key(NORMAL, NONE, KeyCode::Char('4'), Action::SortByColumn(3)),
+    key(NORMAL, NONE, KeyCode::Char('5'), Action::SortByColumn(4)),
+    key(NORMAL, NONE, KeyCode::Char('6'), Action::SortByColumn(5)),
+    key(NORMAL, NONE, KeyCode::Char('7'), Action::SortByColumn(6)),
+    key(NORMAL, NONE, KeyCode::Char('8'), Action::SortByColumn(7)),
+    key(NORMAL, NONE, KeyCode::Char('9'), Action::SortByColumn(8)),
+    key(
+        NORMAL,
+        NONE,
+        KeyCode::Char('r'),
+        Action::ReverseSortDirection,
+    ),
+    key(NORMAL, NONE, KeyCode::Char('<'), Action::ShrinkColumn),
+    key(NORMAL, NONE, KeyCode::Char('>'), Action::GrowColumn),
+    key(NORMAL, NONE, KeyCode::Char('c'), Action::ToggleCompact),
+    key(
+        NORMAL,
+        NONE,
+        KeyCode::Char('a'),
+        Action::ToggleStartTimeFormat,
+    ),
+    key(NORMAL, NONE, KeyCode::Char('h'), Action::ToggleSelf),
+    key(NORMAL, NONE, KeyCode::Char('e'), Action::ToggleArguments),
+    key(NORMAL, NONE, KeyCode::Char('d'), Action::ToggleWrap),
+    key(NORMAL, NONE, KeyCode::Char('y'), Action::ToggleDepthColors),
+    key(NORMAL, NONE, KeyCode::Char('n'), Action::NextNameSource),
+    key(NORMAL, NONE, KeyCode::Char('p'), Action::TogglePidLabels),
+    key(NORMAL, NONE, KeyCode::Char('l'), Action::ToggleOnlyLeaves),
+    key(NORMAL, NONE, KeyCode::Char('g'), Action::NextGrouping),
+    key(NORMAL, NONE, KeyCode::Char('x'), Action::ToggleTop),
+    key(
+        &[Mode::Normal, Mode::ProcessSelected],
+        NONE,
+        KeyCode::Char('z'),
+        Action::ToggleSiblings,
+    ),
+    key(NORMAL, NONE, KeyCode::Char('m'), Action::ToggleRamPercent),
+    key(NORMAL, NONE, KeyCode::Char('%'), Action::TogglePerCpu),
+    key(NORMAL, NONE, KeyCode::Char('f'), Action::TogglePinSelected),
+    key(NORMAL, NONE, KeyCode::Char('i'), Action::ToggleInverted),
+    key(NORMAL, NONE, KeyCode::Char('v'), Action::ToggleGlobalSort),
+    key(NORMAL, NONE, KeyCode::Char('s'), Action::NamePreset),
+    key(NORMAL, CONTROL, KeyCode::Char('s'), Action::DumpProcesses),
+    key(NORMAL, NONE, KeyCode::Char('o'), Action::NextPreset),
+    key(NORMAL, NONE, KeyCode::Char('*'), Action::CollapseAll),
+    key(NORMAL, NONE, KeyCode::Char(' '), Action::ToggleMark),
+    key(NORMAL, NONE, KeyCode::Char('!'), Action::InvertMarks),
+    key(NORMAL, CONTROL, KeyCode::Char('t'), Action::SignalMarked),
+    key(NORMAL, NONE, KeyCode::Char('_'), Action::ExpandAll),
+    key(NORMAL, NONE, KeyCode::Char('{'), Action::PreviousRoot),
+    key(NORMAL, NONE, KeyCode::Char('}'), Action::NextRoot),
+    key(
+        &[Mode::EnteringSignal, Mode::ConfirmingSignal],
+        NONE,
+        KeyCode::Esc,
+        Action::CancelSignal,
+    ),
+    key(
+        CONFIRMING_SIGNAL,
+        NONE,
+        KeyCode::Char('y'),
+        Action::ConfirmSignal,
+    ),
+    key(
+        CONFIRMING_SIGNAL,
+        NONE,
+        KeyCode::Char('n'),
+        Action::CancelSignal,
+    ),
+    key(
+        &[
+            Mode::EditingPattern,
+            Mode::ProcessSelected,
+            Mode::EnteringPresetName,
+            Mode::ConfirmingQuit,
+            Mode::EditingFilters,
+            Mode::ChoosingFilterField,
+            Mode::EnteringFilterValue,
+        ],
+        NONE,
+        KeyCode::Esc,
+        Action::BackToNormal,
+    ),
+    key(
+        PROCESS_SELECTED,
+        NONE,
+        KeyCode::Char('t'),
+        Action::SendSigterm,
+    ),
+    // Only with --quick-signals, to signal the highlighted row.
+    key(NORMAL, NONE, KeyCode::Char('t'), Action::SendSigterm),
+    key(NORMAL, NONE, KeyCode::Char('k'), Action::SendSigkill),
+    key(
+        PROCESS_SELECTED,
+        NONE,
+        KeyCode::Char('w'),
+        Action::TerminateAndWait,
+    ),
+    key(
+        PROCESS_SELECTED,
+        NONE,
+        KeyCode::Char('k'),
+        Action::SendSigkill,
+    ),
+    key(
+        PROCESS_SELECTED,
+        NONE,
+        KeyCode::Char('1'),
+        Action::SendSigusr1,
+    ),
+    key(
+        PROCESS_SELECTED,
+        NONE,
+        KeyCode::Char('2'),
+        Action::SendSigusr2,
+    ),
+    key(
+        PROCESS_SELECTED,
+        NONE,
+        KeyCode::Char(':'),
+        Action::TypeSignal,
+    ),
+    key(
+        PROCESS_SELECTED,
+        NONE,
+        KeyCode::Char('b'),
+        Action::ToggleBreakdown,
+    ),
+    key(
+        PROCESS_SELECTED,
+        NONE,
+        KeyCode::Char('f'),
+        Action::FocusBreakdown,
+    ),
+];
+
+pub(crate) fn lookup(mode: Mode, event: KeyEvent) -> Option<Action> {
+    KEY_BINDINGS
+        .iter()
+        .find(|binding| {
+            (binding.modes.is_empty() || binding.modes.contains(&mode))
+                && binding.modifiers == event.modifiers
+                && binding.code == event.code
+        })
+        .map(|binding| binding.action)
+}
+
+fn render_key(modifiers: KeyModifiers, code: KeyCode) -> String {
+    let key = match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(char) => char.to_string(),
+        code => format!("{:?}", code),
+    };
+    if modifiers.contains(KeyModifiers::CONTROL) {
+        format!("Ctrl+{}", key)
+    } else {
+        key
+    }
+}
+
+pub(crate) fn cheat_sheet() -> String {
+    let mut result = String::new();
+    for binding in KEY_BINDINGS {
+        let modes = if binding.modes.is_empty() {
+            "all modes".to_string()
+        } else {
+            binding
+                .modes
+                .iter()
+                .map(|mode| format!("{:?}", mode))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        result.push_str(&format!(
+            "{:<10} {:<40} {}\n",
+            render_key(binding.modifiers, binding.code),
+            modes,
+            binding.action.description()
+        ));
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cheat_sheet_lists_core_bindings() {
+        let cheat_sheet = cheat_sheet();
+        let lines: Vec<Vec<&str>> = cheat_sheet
+            .lines()
+            .map(|line| line.split_whitespace().collect())
+            .collect();
+        assert!(lines.contains(&vec!["Ctrl+c", "all", "modes", "quit"]));
+        assert!(lines.contains(&vec![
+            "q",
+            "Normal",
+            "quit,",
+            "asking",
+            "first",
+            "if",
+            "processes",
+            "are",
+            "marked"
+        ]));
+        assert!(lines.contains(&vec![
+            "/",
+            "Normal,",
+            "EditingPattern,",
+            "ProcessSelected",
+            "filter",
+            "processes"
+        ]));
+    }
+
+    #[test]
+    fn earlier_bindings_take_precedence() {
+        assert_eq!(
+            lookup(
+                Mode::EditingPattern,
+                KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)
+            ),
+            Some(Action::StopEditingPattern)
+        );
+        assert_eq!(
+            lookup(
+                Mode::Normal,
+                KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)
+            ),
+            Some(Action::SelectProcess)
+        );
+    }
+
+    #[test]
+    fn unbound_keys_are_not_found() {
+        assert_eq!(
+            lookup(
+                Mode::EditingPattern,
+                KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE)
+            ),
+            None
+        );
+    }
+}
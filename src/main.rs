@@ -1,12 +1,28 @@
+use crate::process::Backend;
+use crate::process::NameSource;
 use crate::process::ProcessWatcher;
-use crate::regex::Regex;
-use crate::treetop_app::TreetopApp;
+use crate::process::{SortBy, SortDirection};
+use crate::signal::parse_signal;
+use crate::tree::PrefixStyle;
+use crate::treetop_app::{ExitStatus, FilterDisplay, Highlight, Options, TreetopApp};
 use clap::Parser;
+use nix::sys::signal::Signal;
+use num_format::Locale;
+use ratatui::style::Color;
 use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode, Stdio};
+use std::str::FromStr;
+use std::time::Duration;
 use sysinfo::System;
 
+mod column_provider;
+mod fuzzy;
+mod key_bindings;
+mod preset;
 mod process;
 mod regex;
+mod signal;
 mod tree;
 mod treetop_app;
 mod tui_app;
@@ -16,16 +32,540 @@ type R<A> = Result<A, Box<dyn Error>>;
 
 #[derive(Parser, Debug)]
 struct Args {
-    #[arg(help = "search pattern for filtering the process tree")]
-    pattern: Option<String>,
+    #[arg(long, help = "print all key bindings and exit")]
+    list_keys: bool,
+    #[arg(
+        long,
+        help = "print the process tree once and exit, with exit code 1 if no process matches"
+    )]
+    once: bool,
+    #[arg(
+        long,
+        value_name = "PID",
+        help = "exit once the process with this pid is gone, with exit code 2 when quit before"
+    )]
+    wait: Option<u32>,
+    #[arg(
+        long,
+        num_args = 1..,
+        allow_hyphen_values = true,
+        value_name = "COMMAND",
+        help = "run a command and only show it and its descendants, has to be the last option"
+    )]
+    follow: Vec<String>,
+    #[arg(
+        long,
+        requires = "follow",
+        help = "exit once the followed command exits"
+    )]
+    exit_with_command: bool,
+    #[arg(
+        value_name = "PATTERN",
+        help = "search pattern for filtering the process tree"
+    )]
+    positional_pattern: Option<String>,
+    #[arg(
+        short,
+        long,
+        help = "search pattern for filtering the process tree, can be given multiple times, use env:PATTERN to match environment variables"
+    )]
+    pattern: Vec<String>,
+    #[arg(
+        long,
+        help = "match search patterns fuzzily instead of as regexes, best matches first"
+    )]
+    fuzzy: bool,
+    #[arg(
+        short = 'F',
+        long,
+        help = "match search patterns literally instead of as regexes, toggle with Ctrl+F"
+    )]
+    fixed_strings: bool,
+    #[arg(
+        long,
+        help = "match search patterns regardless of case, toggle with Ctrl+A"
+    )]
+    ignore_case: bool,
+    #[arg(
+        long,
+        value_parser = FilterDisplay::parse,
+        default_value = "subtrees",
+        value_name = "subtrees|paths",
+        help = "show matching processes with all their children or only the paths from the roots to the matches, with the matches collapsed until '_' expands all processes"
+    )]
+    filter_display: FilterDisplay,
+    #[arg(
+        long,
+        conflicts_with = "filter_display",
+        help = "hide the children of matching processes unless they match themselves"
+    )]
+    no_subtrees: bool,
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "start with a saved filter preset, save presets with 's' in the ui"
+    )]
+    preset: Option<String>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "where presets are stored, defaults to ~/.config/treetop/presets.json"
+    )]
+    presets_file: Option<PathBuf>,
+    #[arg(long, help = "only show executables without their arguments")]
+    hide_arguments: bool,
+    #[arg(
+        long,
+        help = "wrap the command line of the highlighted process onto further lines"
+    )]
+    wrap: bool,
+    #[arg(
+        long,
+        default_value = "▶",
+        value_name = "MARKER",
+        help = "the marker in front of the highlighted row"
+    )]
+    selection_marker: String,
+    #[arg(
+        long,
+        value_name = "COLOR",
+        help = "the color of the selection marker, e.g. 'red' or '#ff8800'"
+    )]
+    selection_color: Option<Color>,
+    #[arg(
+        long,
+        value_parser = Highlight::parse,
+        default_value = "marker",
+        value_name = "marker|row",
+        help = "highlight the selected row with just the marker or by showing the whole row reversed"
+    )]
+    highlight: Highlight,
+    #[arg(
+        long,
+        help = "show the full command line of the process under the mouse cursor"
+    )]
+    tooltips: bool,
+    #[arg(long, help = "color each level of the tree lines differently")]
+    depth_colors: bool,
+    #[arg(
+        long,
+        value_parser = NameSource::parse,
+        default_value = "argv0",
+        value_name = "argv0|exe|comm",
+        help = "show and filter processes by the first argument, the executable file or the kernel's name"
+    )]
+    name_source: NameSource,
+    #[arg(
+        long,
+        help = "remember collapsed processes by name, so they stay collapsed when restarted"
+    )]
+    collapse_by_name: bool,
+    #[arg(long, help = "show children above their parents")]
+    inverted: bool,
+    #[arg(
+        long,
+        help = "sort all processes together instead of within siblings, keeping their indentation"
+    )]
+    global_sort: bool,
+    #[arg(long, help = "only show processes without children, as a flat list")]
+    leaves: bool,
+    #[arg(
+        long,
+        help = "show one row per executable with the totals of all its processes"
+    )]
+    group: bool,
+    #[arg(
+        long,
+        conflicts_with = "group",
+        help = "show one row per user with the totals of all their processes"
+    )]
+    group_by_user: bool,
+    #[arg(
+        long,
+        value_name = "N",
+        help = "only show the N top-level processes using the most cpu, toggle with 'x'"
+    )]
+    top: Option<usize>,
+    #[arg(long, help = "never send signals to processes")]
+    read_only: bool,
+    #[arg(
+        long,
+        value_parser = parse_signal,
+        value_delimiter = ',',
+        num_args = 0..,
+        default_value = "SIGKILL",
+        value_name = "SIGNALS",
+        help = "signals that need to be confirmed before they're sent, pass no signals to never ask"
+    )]
+    confirm_signals: Vec<Signal>,
+    #[arg(
+        long,
+        help = "let 't' and 'k' signal the highlighted process without selecting it first"
+    )]
+    quick_signals: bool,
+    #[arg(long, help = "hide treetop itself and its children")]
+    hide_self: bool,
+    #[arg(long, help = "use narrower columns with minimal padding")]
+    compact: bool,
+    #[arg(long, help = "show the number of descendants per process")]
+    descendants: bool,
+    #[arg(
+        long,
+        help = "show own and accumulated cpu and ram side by side on wide terminals"
+    )]
+    wide: bool,
+    #[arg(
+        long,
+        help = "show cpu per process instead of accumulated over children"
+    )]
+    own_cpu: bool,
+    #[arg(
+        long,
+        help = "show ram per process instead of accumulated over children"
+    )]
+    own_ram: bool,
+    #[arg(
+        long,
+        conflicts_with_all = ["own_cpu", "own_ram"],
+        help = "show cpu and ram accumulated over children only for collapsed processes"
+    )]
+    accumulate_collapsed: bool,
+    #[arg(long, help = "show ram as a percentage of total memory")]
+    ram_percent: bool,
+    #[arg(
+        long,
+        help = "divide cpu by the number of cpus, so that using all of them is 100%"
+    )]
+    per_cpu: bool,
+    #[arg(
+        long,
+        default_value = "en",
+        help = "locale for the thousands separators in numbers, e.g. 'de' or 'fr'"
+    )]
+    locale: Locale,
+    #[arg(
+        long,
+        help = "show processes started in the last minute in bold and ones older than an hour dimmed"
+    )]
+    dim_by_age: bool,
+    #[arg(long, help = "color cpu and ram cells from green to red")]
+    heat_map: bool,
+    #[arg(
+        long,
+        value_parser = parse_thresholds::<f32>,
+        default_value = "50,90",
+        value_name = "YELLOW,RED",
+        help = "cpu percentages at which the heat map turns yellow and red"
+    )]
+    cpu_thresholds: [f32; 2],
+    #[arg(
+        long,
+        value_parser = parse_thresholds::<u64>,
+        default_value = "1024,4096",
+        value_name = "YELLOW,RED",
+        help = "ram usage in MB at which the heat map turns yellow and red"
+    )]
+    ram_thresholds: [u64; 2],
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "briefly highlight processes whose cpu usage jumped by at least this much"
+    )]
+    spike_threshold: Option<f32>,
+    #[arg(
+        long,
+        value_name = "TICKS",
+        help = "hide processes started after treetop until they've been seen for this many ticks, to reduce flicker"
+    )]
+    settle: Option<usize>,
+    #[arg(long, help = "mark processes that were started after treetop")]
+    differences: bool,
+    #[arg(long, help = "show the depth of each process in the tree")]
+    generation: bool,
+    #[arg(
+        long,
+        help = "show the cgroup of each process, filter with cgroup:PATTERN"
+    )]
+    cgroup: bool,
+    #[arg(
+        long,
+        help = "show the controlling terminal of each process, filter with tty:PATTERN"
+    )]
+    tty: bool,
+    #[arg(
+        long,
+        help = "show how likely processes are to be killed when out of memory"
+    )]
+    oom_score: bool,
+    #[arg(long, help = "show how much of each process is swapped out")]
+    swap: bool,
+    #[arg(
+        long,
+        help = "show how much cpu each thread of a process uses on average"
+    )]
+    cpu_per_thread: bool,
+    #[arg(long, help = "show a sparkline of the cpu usage of the last ticks")]
+    cpu_history: bool,
+    #[arg(long, help = "show the number of threads per process")]
+    threads: bool,
+    #[arg(
+        long,
+        value_parser = SortBy::parse,
+        value_delimiter = ',',
+        value_name = "COLUMNS",
+        help = "columns to show in this order, e.g. cpu,pid,ram, overrides the other column flags"
+    )]
+    columns: Vec<SortBy>,
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        help = "status bar template, supports {commands}, {pattern}, {count} and {sort}"
+    )]
+    status_bar: Option<String>,
+    #[arg(
+        long,
+        value_parser = SortBy::parse,
+        default_value = "pid",
+        value_name = "COLUMN",
+        help = "column to sort by on startup"
+    )]
+    sort: SortBy,
+    #[arg(
+        long,
+        value_parser = SortDirection::parse,
+        value_name = "asc|desc",
+        help = "sort direction on startup, defaults to the natural direction of the column"
+    )]
+    sort_dir: Option<SortDirection>,
+    #[arg(long, help = "show bytes read from and written to disk per process")]
+    disk_io: bool,
+    #[arg(long, help = "show how long processes have been running")]
+    run_time: bool,
+    #[arg(long, help = "show when processes were started")]
+    start_time: bool,
+    #[arg(
+        long,
+        value_parser = parse_prefix_style,
+        default_value = "thin",
+        value_name = "thin|rounded|thick",
+        help = "glyphs used to draw the process tree"
+    )]
+    tree_style: PrefixStyle,
+    #[arg(
+        long,
+        value_parser = parse_idle_interval,
+        value_name = "SECONDS",
+        help = "refresh only this often while unfocused or after a minute without input"
+    )]
+    idle_interval: Option<Duration>,
+    #[arg(long, help = "keep the column widths computed on startup")]
+    freeze_widths: bool,
+    #[arg(long, help = "show the processes from a recorded snapshot file")]
+    replay: Option<PathBuf>,
+    #[arg(
+        long,
+        value_parser = Backend::parse,
+        default_value = "sysinfo",
+        conflicts_with_all = ["replay", "ps_command"],
+        value_name = "sysinfo|proc",
+        help = "where to read processes from, proc reads /proc directly on Linux"
+    )]
+    backend: Backend,
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        conflicts_with = "replay",
+        help = "read processes from a command printing `ps -eo pid,ppid,pcpu,rss,comm` output"
+    )]
+    ps_command: Option<String>,
+    #[arg(
+        long,
+        help = "append a snapshot of all processes to a file on every tick"
+    )]
+    record: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "where Ctrl+S writes the shown processes to, defaults to the current directory"
+    )]
+    dump_dir: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "write key events, tick timings and errors to a file for debugging"
+    )]
+    log: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "write a json line with pid, signal and result for every signal sent"
+    )]
+    signal_log: Option<PathBuf>,
 }
 
-fn main() -> R<()> {
-    let args = Args::parse();
-    TreetopApp::run(TreetopApp::new(
-        ProcessWatcher::new(System::new()),
-        args.pattern
-            .map(|pattern| ::regex::Regex::new(&pattern).map(crate::Regex::new))
-            .transpose()?,
-    )?)
+impl Args {
+    fn options(&self) -> Options {
+        Options {
+            once: self.once,
+            wait: self.wait,
+            exit_with_command: self.exit_with_command,
+            patterns: self
+                .positional_pattern
+                .iter()
+                .chain(&self.pattern)
+                .cloned()
+                .collect(),
+            fuzzy: self.fuzzy,
+            fixed_strings: self.fixed_strings,
+            ignore_case: self.ignore_case,
+            filter_display: self.filter_display,
+            no_subtrees: self.no_subtrees,
+            preset: self.preset.clone(),
+            presets_file: self.presets_file.clone(),
+            hide_arguments: self.hide_arguments,
+            wrap: self.wrap,
+            selection_marker: self.selection_marker.clone(),
+            selection_color: self.selection_color,
+            highlight: self.highlight,
+            tooltips: self.tooltips,
+            depth_colors: self.depth_colors,
+            name_source: self.name_source,
+            collapse_by_name: self.collapse_by_name,
+            inverted: self.inverted,
+            global_sort: self.global_sort,
+            leaves: self.leaves,
+            group: self.group,
+            group_by_user: self.group_by_user,
+            top: self.top,
+            read_only: self.read_only,
+            confirm_signals: self.confirm_signals.clone(),
+            quick_signals: self.quick_signals,
+            hide_self: self.hide_self,
+            compact: self.compact,
+            descendants: self.descendants,
+            wide: self.wide,
+            own_cpu: self.own_cpu,
+            own_ram: self.own_ram,
+            accumulate_collapsed: self.accumulate_collapsed,
+            ram_percent: self.ram_percent,
+            per_cpu: self.per_cpu,
+            locale: self.locale,
+            dim_by_age: self.dim_by_age,
+            heat_map: self.heat_map,
+            cpu_thresholds: self.cpu_thresholds,
+            ram_thresholds: self.ram_thresholds,
+            spike_threshold: self.spike_threshold,
+            settle: self.settle,
+            differences: self.differences,
+            generation: self.generation,
+            cgroup: self.cgroup,
+            tty: self.tty,
+            oom_score: self.oom_score,
+            swap: self.swap,
+            cpu_per_thread: self.cpu_per_thread,
+            cpu_history: self.cpu_history,
+            threads: self.threads,
+            columns: self.columns.clone(),
+            status_bar: self.status_bar.clone(),
+            sort: self.sort,
+            sort_dir: self.sort_dir,
+            disk_io: self.disk_io,
+            run_time: self.run_time,
+            start_time: self.start_time,
+            tree_style: self.tree_style.clone(),
+            idle_interval: self.idle_interval,
+            freeze_widths: self.freeze_widths,
+            record: self.record.clone(),
+            dump_dir: self.dump_dir.clone(),
+            log: self.log.clone(),
+            signal_log: self.signal_log.clone(),
+        }
+    }
+}
+
+fn parse_thresholds<T: FromStr>(thresholds: &str) -> Result<[T; 2], String> {
+    match thresholds.split_once(',') {
+        Some((yellow, red)) => match (yellow.trim().parse(), red.trim().parse()) {
+            (Ok(yellow), Ok(red)) => Ok([yellow, red]),
+            _ => Err(format!("invalid thresholds: {}", thresholds)),
+        },
+        None => Err("expected two comma-separated thresholds".to_string()),
+    }
+}
+
+fn parse_idle_interval(seconds: &str) -> Result<Duration, String> {
+    let interval = seconds
+        .parse()
+        .map(Duration::from_secs)
+        .map_err(|_| format!("invalid number of seconds: {}", seconds))?;
+    if interval < tui_app::TICK_LENGTH {
+        return Err(format!(
+            "must be at least one tick ({}s)",
+            tui_app::TICK_LENGTH.as_secs()
+        ));
+    }
+    Ok(interval)
+}
+
+fn parse_prefix_style(style: &str) -> Result<PrefixStyle, String> {
+    match style {
+        "thin" => Ok(PrefixStyle::THIN),
+        "rounded" => Ok(PrefixStyle::ROUNDED),
+        "thick" => Ok(PrefixStyle::THICK),
+        _ => Err(format!("unknown tree style: {}", style)),
+    }
+}
+
+// Errors get their own exit code, so they can't be mistaken for the
+// statuses `--once`, `--wait` and `--follow` report.
+fn main() -> ExitCode {
+    let args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(error) => {
+            let _ = error.print();
+            return if error.use_stderr() {
+                ExitCode::from(ExitStatus::Error.code())
+            } else {
+                ExitCode::SUCCESS
+            };
+        }
+    };
+    match run(&args) {
+        Ok(exit_status) => ExitCode::from(exit_status.code()),
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            ExitCode::from(ExitStatus::Error.code())
+        }
+    }
+}
+
+fn run(args: &Args) -> R<ExitStatus> {
+    if args.list_keys {
+        print!("{}", key_bindings::cheat_sheet());
+        return Ok(ExitStatus::Success);
+    }
+    let mut app = TreetopApp::new(
+        match (&args.replay, &args.ps_command) {
+            (Some(snapshot), _) => ProcessWatcher::replay(snapshot)?,
+            (None, Some(command)) => ProcessWatcher::command(command),
+            (None, None) => match args.backend {
+                Backend::Sysinfo => ProcessWatcher::new(System::new()),
+                Backend::Proc => ProcessWatcher::proc(Path::new("/proc")),
+            },
+        },
+        &args.options(),
+    )?;
+    if let Some((command, arguments)) = args.follow.split_first() {
+        // The command's output would garble the ui.
+        app.follow(
+            Command::new(command)
+                .args(arguments)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .map_err(|error| format!("couldn't run {}: {}", command, error))?,
+        );
+    }
+    app.run()
 }
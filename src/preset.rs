@@ -0,0 +1,122 @@
+use crate::process::HeatMap;
+use crate::treetop_app::SearchScope;
+use crate::R;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Preset {
+    pub(crate) patterns: Vec<String>,
+    pub(crate) search_scope: SearchScope,
+    pub(crate) heat_map: Option<HeatMap>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Presets {
+    path: Option<PathBuf>,
+    presets: BTreeMap<String, Preset>,
+}
+
+impl Presets {
+    pub(crate) fn default_path() -> Option<PathBuf> {
+        let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(config_dir) if !config_dir.is_empty() => PathBuf::from(config_dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(config_dir.join("treetop").join("presets.json"))
+    }
+
+    pub(crate) fn load(path: Option<&Path>) -> R<Presets> {
+        let presets = match path {
+            Some(path) if path.exists() => serde_json::from_str(&std::fs::read_to_string(path)?)
+                .map_err(|error| format!("invalid presets in {}: {}", path.display(), error))?,
+            _ => BTreeMap::new(),
+        };
+        Ok(Presets {
+            path: path.map(Path::to_path_buf),
+            presets,
+        })
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&Preset> {
+        self.presets.get(name)
+    }
+
+    // Presets are cycled through in alphabetical order.
+    pub(crate) fn next(&self, name: Option<&str>) -> Option<(&str, &Preset)> {
+        let mut presets = self.presets.iter();
+        let next = match name {
+            Some(name) => presets
+                .clone()
+                .find(|(other, _)| other.as_str() > name)
+                .or_else(|| presets.next()),
+            None => presets.next(),
+        };
+        next.map(|(name, preset)| (name.as_str(), preset))
+    }
+
+    pub(crate) fn save(&mut self, name: &str, preset: Preset) -> R<()> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or("no config directory for presets")?;
+        self.presets.insert(name.to_string(), preset);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&self.presets)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::test::TempDir;
+    use pretty_assertions::assert_eq;
+
+    fn preset(pattern: &str) -> Preset {
+        Preset {
+            patterns: vec![pattern.to_string()],
+            search_scope: SearchScope::Cmdline,
+            heat_map: Some(HeatMap {
+                cpu: [10.0, 20.0],
+                ram_in_mb: [100, 200],
+            }),
+        }
+    }
+
+    #[test]
+    fn presets_survive_saving_and_loading() -> R<()> {
+        let temp = TempDir::new("presets-test")?;
+        let path = temp.join("presets.json");
+        let mut presets = Presets::load(Some(&path))?;
+        assert_eq!(presets.get("services"), None);
+        presets.save("services", preset("nginx|postgres"))?;
+        presets.save("browsers", preset("firefox"))?;
+        let loaded = Presets::load(Some(&path))?;
+        assert_eq!(loaded.get("services"), Some(&preset("nginx|postgres")));
+        assert_eq!(loaded.get("browsers"), Some(&preset("firefox")));
+        Ok(())
+    }
+
+    #[test]
+    fn saving_without_a_path_fails() -> R<()> {
+        let mut presets = Presets::load(None)?;
+        assert!(presets.save("services", preset("nginx")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn cycles_through_presets_alphabetically() -> R<()> {
+        let mut presets = Presets::load(None)?;
+        presets.presets.insert("b".to_string(), preset("b"));
+        presets.presets.insert("a".to_string(), preset("a"));
+        let names = |name| presets.next(name).map(|(name, _)| name);
+        assert_eq!(names(None), Some("a"));
+        assert_eq!(names(Some("a")), Some("b"));
+        assert_eq!(names(Some("b")), Some("a"));
+        Ok(())
+    }
+}
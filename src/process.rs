@@ -1,5 +1,10 @@
+use crate::column_provider::ColumnProvider;
 pub(crate) use crate::tree::Forest;
 use crate::tree::Node;
+use crate::R;
+use chrono::Local;
+use chrono::{LocalResult, TimeZone};
+use nix::unistd::{sysconf, SysconfVar};
 use num_format::Locale;
 use num_format::ToFormattedString;
 use ratatui::buffer::Buffer;
@@ -9,36 +14,269 @@ use ratatui::style::Modifier;
 use ratatui::style::Style;
 use ratatui::text::Line;
 use ratatui::text::Span;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
 use std::path::Path;
+use std::path::PathBuf;
+use std::time::Instant;
 use sysinfo::Pid;
 use sysinfo::ProcessRefreshKind;
 use sysinfo::ThreadKind;
 use sysinfo::UpdateKind;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct Process {
+    #[serde(with = "serde_pid")]
     pid: Pid,
     pub(crate) name: String,
+    #[serde(default)]
+    comm: String,
     arguments: Vec<String>,
+    #[serde(with = "serde_parent")]
     parent: Option<Pid>,
     cpu: f32,
     ram: u64,
+    #[serde(skip)]
+    descendants_cpu: f32,
+    #[serde(skip)]
+    descendants_ram: u64,
+    #[serde(skip)]
+    descendants_threads: usize,
+    #[serde(skip)]
+    descendants_read_bytes: u64,
+    #[serde(skip)]
+    descendants_written_bytes: u64,
+    #[serde(skip)]
+    descendants_swap: u64,
+    #[serde(default)]
+    threads: usize,
+    #[serde(default)]
+    start_time: u64,
+    #[serde(default)]
+    run_time: u64,
+    #[serde(default)]
+    read_bytes: u64,
+    #[serde(default)]
+    written_bytes: u64,
+    #[serde(default)]
+    swap: u64,
+    #[serde(default)]
+    user: Option<String>,
+    #[serde(default)]
+    oom_score: Option<i32>,
+    #[serde(default)]
+    cgroup: Option<String>,
+    #[serde(default)]
+    tty: Option<String>,
+    // Only filled in when requested with `ProcessWatcher::set_details`.
+    // Never written to recordings or dumps, since it often contains secrets.
+    #[serde(skip)]
+    environment: Vec<String>,
+    #[serde(skip)]
+    descendants: usize,
+    // The number of processes combined into this one by `group`, 0
+    // for ordinary processes.
+    #[serde(skip)]
+    group_size: usize,
+}
+
+mod serde_pid {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use sysinfo::Pid;
+
+    pub(super) fn serialize<S: Serializer>(pid: &Pid, serializer: S) -> Result<S::Ok, S::Error> {
+        pid.as_u32().serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pid, D::Error> {
+        Ok(Pid::from_u32(u32::deserialize(deserializer)?))
+    }
+}
+
+mod serde_parent {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use sysinfo::Pid;
+
+    pub(super) fn serialize<S: Serializer>(
+        parent: &Option<Pid>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        parent.map(|pid| pid.as_u32()).serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Pid>, D::Error> {
+        Ok(Option::<u32>::deserialize(deserializer)?.map(Pid::from_u32))
+    }
 }
 
 impl fmt::Display for Process {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.arguments.first() {
-            Some(executable) => match Path::new(&executable).file_name() {
-                Some(file_name) => write!(f, "{}", file_name.to_string_lossy())?,
-                None => write!(f, "{}", executable)?,
-            },
-            None => write!(f, "{}", self.name)?,
+        write!(f, "{}", self.command_line(NameSource::Argv0))
+    }
+}
+
+// The name of a process can come from different places, which disagree e.g.
+// for processes that rename themselves or are started through symlinks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NameSource {
+    Argv0,
+    Exe,
+    Comm,
+}
+
+impl NameSource {
+    pub(crate) fn parse(source: &str) -> Result<NameSource, String> {
+        match source {
+            "argv0" => Ok(NameSource::Argv0),
+            "exe" => Ok(NameSource::Exe),
+            "comm" => Ok(NameSource::Comm),
+            _ => Err(format!("unknown name source: {}", source)),
         }
-        for argument in self.arguments.iter().skip(1) {
-            write!(f, " {}", argument)?;
+    }
+
+    pub(crate) fn next(self) -> NameSource {
+        match self {
+            NameSource::Argv0 => NameSource::Exe,
+            NameSource::Exe => NameSource::Comm,
+            NameSource::Comm => NameSource::Argv0,
+        }
+    }
+}
+
+// What processes get combined into one row by, instead of showing the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Grouping {
+    Executable,
+    User,
+}
+
+impl Grouping {
+    pub(crate) fn next(grouping: Option<Grouping>) -> Option<Grouping> {
+        match grouping {
+            None => Some(Grouping::Executable),
+            Some(Grouping::Executable) => Some(Grouping::User),
+            Some(Grouping::User) => None,
+        }
+    }
+
+    fn key(self, process: &Process) -> String {
+        match self {
+            Grouping::Executable => process.name.clone(),
+            Grouping::User => process
+                .user
+                .clone()
+                .unwrap_or_else(|| "[unknown]".to_string()),
+        }
+    }
+}
+
+// The fields that the filter builder can compose conditions on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ConditionField {
+    Cpu,
+    Ram,
+    Pid,
+    User,
+}
+
+impl ConditionField {
+    pub(crate) const ALL: [ConditionField; 4] = [
+        ConditionField::Cpu,
+        ConditionField::Ram,
+        ConditionField::Pid,
+        ConditionField::User,
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            ConditionField::Cpu => "cpu",
+            ConditionField::Ram => "ram",
+            ConditionField::Pid => "pid",
+            ConditionField::User => "user",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Less,
+    Equal,
+    Greater,
+}
+
+// A filter on a single field, like `cpu > 2`. Ram is compared in MB.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Condition {
+    field: ConditionField,
+    comparison: Comparison,
+    value: String,
+}
+
+impl Condition {
+    // Parses input like `> 2`, `<100` or `root`, which compares for
+    // equality.
+    pub(crate) fn parse(field: ConditionField, input: &str) -> Result<Condition, String> {
+        let input = input.trim();
+        let (comparison, value) = match input.chars().next() {
+            Some('<') => (Comparison::Less, &input[1..]),
+            Some('>') => (Comparison::Greater, &input[1..]),
+            Some('=') => (Comparison::Equal, &input[1..]),
+            _ => (Comparison::Equal, input),
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(format!("missing value for {}", field.name()));
+        }
+        match field {
+            ConditionField::User if comparison != Comparison::Equal => {
+                return Err("users can only be compared with =".to_string());
+            }
+            ConditionField::User => {}
+            ConditionField::Cpu | ConditionField::Ram | ConditionField::Pid => {
+                value
+                    .parse::<f64>()
+                    .map_err(|_| format!("not a number: {}", value))?;
+            }
+        }
+        Ok(Condition {
+            field,
+            comparison,
+            value: value.to_string(),
+        })
+    }
+
+    pub(crate) fn matches(&self, process: &Process, accumulation: Accumulation) -> bool {
+        let actual = match self.field {
+            ConditionField::User => return process.user() == Some(self.value.as_str()),
+            ConditionField::Cpu => f64::from(process.cpu(accumulation)),
+            ConditionField::Ram => process.ram(accumulation) as f64 / (1024.0 * 1024.0),
+            ConditionField::Pid => f64::from(process.pid.as_u32()),
+        };
+        let expected = self.value.parse::<f64>().unwrap_or(f64::NAN);
+        match self.comparison {
+            Comparison::Less => actual < expected,
+            Comparison::Equal => actual == expected,
+            Comparison::Greater => actual > expected,
         }
-        Ok(())
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let comparison = match self.comparison {
+            Comparison::Less => "<",
+            Comparison::Equal => "=",
+            Comparison::Greater => ">",
+        };
+        write!(f, "{} {} {}", self.field.name(), comparison, self.value)
     }
 }
 
@@ -56,11 +294,101 @@ impl Node for Process {
     fn accumulate_from(&mut self, other: &Self) {
         self.cpu += other.cpu;
         self.ram += other.ram;
+        self.descendants_cpu += other.cpu;
+        self.descendants_ram += other.ram;
+        self.threads += other.threads;
+        self.descendants_threads += other.threads;
+        self.read_bytes += other.read_bytes;
+        self.written_bytes += other.written_bytes;
+        self.descendants_read_bytes += other.read_bytes;
+        self.descendants_written_bytes += other.written_bytes;
+        self.swap += other.swap;
+        self.descendants_swap += other.swap;
+        self.descendants += other.descendants + 1;
     }
 }
 
 impl Process {
-    fn from_sysinfo_process(process: &sysinfo::Process) -> Self {
+    // Combines all processes with the same name or user into one, summing up
+    // their own cpu and ram usage.
+    pub(crate) fn group<'a>(
+        grouping: Grouping,
+        processes: impl Iterator<Item = &'a Process>,
+    ) -> Vec<Process> {
+        let mut groups: BTreeMap<String, Process> = BTreeMap::new();
+        for process in processes {
+            let key = grouping.key(process);
+            let group = groups.entry(key.clone()).or_insert_with(|| Process {
+                name: key.clone(),
+                comm: match grouping {
+                    Grouping::Executable => process.comm.clone(),
+                    Grouping::User => key,
+                },
+                arguments: Vec::new(),
+                parent: None,
+                cpu: 0.0,
+                ram: 0,
+                descendants_cpu: 0.0,
+                descendants_ram: 0,
+                descendants_threads: 0,
+                descendants_read_bytes: 0,
+                descendants_written_bytes: 0,
+                descendants_swap: 0,
+                threads: 0,
+                read_bytes: 0,
+                written_bytes: 0,
+                swap: 0,
+                descendants: 0,
+                group_size: 0,
+                ..process.clone()
+            });
+            group.pid = group.pid.min(process.pid);
+            group.cpu += process.cpu - process.descendants_cpu;
+            group.ram += process.ram - process.descendants_ram;
+            group.threads += process.own_threads();
+            group.read_bytes += process.read_bytes - process.descendants_read_bytes;
+            group.written_bytes += process.written_bytes - process.descendants_written_bytes;
+            group.swap += process.swap - process.descendants_swap;
+            group.start_time = group.start_time.min(process.start_time);
+            group.run_time = group.run_time.max(process.run_time);
+            group.group_size += 1;
+        }
+        groups.into_values().collect()
+    }
+
+    pub(crate) fn run_time(&self) -> u64 {
+        self.run_time
+    }
+
+    pub(crate) fn own_threads(&self) -> usize {
+        self.threads - self.descendants_threads
+    }
+
+    pub(crate) fn own_cpu(&self) -> f32 {
+        self.cpu - self.descendants_cpu
+    }
+
+    pub(crate) fn group_size(&self) -> usize {
+        self.group_size
+    }
+
+    pub(crate) fn executable(&self) -> String {
+        match self.arguments.first() {
+            Some(executable) => match Path::new(&executable).file_name() {
+                Some(file_name) => file_name.to_string_lossy().to_string(),
+                None => executable.clone(),
+            },
+            None => self.name.clone(),
+        }
+    }
+
+    fn from_sysinfo_process(
+        process: &sysinfo::Process,
+        users: &sysinfo::Users,
+        details: Details,
+    ) -> Self {
+        let proc_dir =
+            cfg!(target_os = "linux").then(|| Path::new("/proc").join(process.pid().to_string()));
         Process {
             pid: process.pid(),
             name: match process.exe() {
@@ -70,18 +398,156 @@ impl Process {
                 },
                 None => process.name().to_string(),
             },
+            comm: process.name().to_string(),
             arguments: process.cmd().to_vec(),
             parent: process.parent(),
             cpu: process.cpu_usage(),
             ram: process.memory(),
+            descendants_cpu: 0.0,
+            descendants_ram: 0,
+            descendants_threads: 0,
+            descendants_read_bytes: 0,
+            descendants_written_bytes: 0,
+            descendants_swap: 0,
+            threads: process.tasks().map_or(1, |tasks| tasks.len() + 1),
+            start_time: process.start_time(),
+            run_time: process.run_time(),
+            read_bytes: process.disk_usage().read_bytes,
+            written_bytes: process.disk_usage().written_bytes,
+            swap: proc_dir
+                .as_deref()
+                .filter(|_| details.swap)
+                .and_then(read_swap)
+                .unwrap_or(0),
+            user: process
+                .user_id()
+                .and_then(|user_id| users.get_user_by_id(user_id))
+                .map(|user| user.name().to_string()),
+            oom_score: proc_dir
+                .as_deref()
+                .filter(|_| details.oom_score)
+                .and_then(read_oom_score),
+            cgroup: proc_dir
+                .as_deref()
+                .filter(|_| details.cgroup)
+                .and_then(read_cgroup),
+            tty: proc_dir
+                .as_deref()
+                .filter(|_| details.tty)
+                .and_then(read_tty),
+            environment: if details.environment {
+                process.environ().to_vec()
+            } else {
+                Vec::new()
+            },
+            descendants: 0,
+            group_size: 0,
+        }
+    }
+
+    pub(crate) fn display_name(&self, source: NameSource) -> String {
+        let name = match source {
+            NameSource::Argv0 => self.executable(),
+            NameSource::Exe => self.name.clone(),
+            NameSource::Comm => self.comm.clone(),
+        };
+        // The executable and arguments of other users' processes can be
+        // unreadable, and snapshots recorded before `comm` existed don't have
+        // it. Rows should never be blank, so fall back to anything we know.
+        [name, self.name.clone(), self.comm.clone()]
+            .into_iter()
+            .find(|name| !name.is_empty())
+            .unwrap_or_else(|| format!("[{}]", self.pid))
+    }
+
+    pub(crate) fn command_line(&self, source: NameSource) -> String {
+        let mut result = self.display_name(source);
+        for argument in self.arguments.iter().skip(1) {
+            result.push(' ');
+            result.push_str(argument);
+        }
+        result
+    }
+
+    pub(crate) fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    pub(crate) fn environment(&self) -> &[String] {
+        &self.environment
+    }
+
+    pub(crate) fn cgroup(&self) -> Option<&str> {
+        self.cgroup.as_deref()
+    }
+
+    pub(crate) fn tty(&self) -> Option<&str> {
+        self.tty.as_deref()
+    }
+
+    fn cpu(&self, accumulation: Accumulation) -> f32 {
+        if accumulation.cpu {
+            self.cpu
+        } else {
+            self.cpu - self.descendants_cpu
         }
     }
 
-    pub(crate) fn compare(&self, other: &Process, sort_by: SortBy) -> std::cmp::Ordering {
+    fn ram(&self, accumulation: Accumulation) -> u64 {
+        if accumulation.ram {
+            self.ram
+        } else {
+            self.ram - self.descendants_ram
+        }
+    }
+
+    pub(crate) fn compare(
+        &self,
+        other: &Process,
+        sort_by: SortBy,
+        direction: SortDirection,
+        accumulation: Accumulation,
+    ) -> std::cmp::Ordering {
+        self.compare_with(other, sort_by, direction, [accumulation, accumulation])
+    }
+
+    // Like `compare`, but with separate accumulations for `self` and `other`,
+    // for rows that show their values accumulated differently.
+    pub(crate) fn compare_with(
+        &self,
+        other: &Process,
+        sort_by: SortBy,
+        direction: SortDirection,
+        [accumulation, other_accumulation]: [Accumulation; 2],
+    ) -> std::cmp::Ordering {
+        if sort_by == SortBy::Name && self.name.is_empty() != other.name.is_empty() {
+            // Processes without a name don't tell anything apart, so they go
+            // to the bottom in either direction.
+            return self.name.is_empty().cmp(&other.name.is_empty());
+        }
         let ordering = match sort_by {
             SortBy::Pid => self.id().partial_cmp(&other.id()),
-            SortBy::Cpu => other.cpu.partial_cmp(&self.cpu),
-            SortBy::Ram => other.ram.partial_cmp(&self.ram),
+            SortBy::Cpu => self
+                .cpu(accumulation)
+                .partial_cmp(&other.cpu(other_accumulation)),
+            SortBy::Ram => self
+                .ram(accumulation)
+                .partial_cmp(&other.ram(other_accumulation)),
+            SortBy::Descendants => self.descendants.partial_cmp(&other.descendants),
+            SortBy::Threads => self.threads.partial_cmp(&other.threads),
+            SortBy::RunTime => self.run_time.partial_cmp(&other.run_time),
+            SortBy::Generation => Some(std::cmp::Ordering::Equal),
+            SortBy::Cgroup => self.cgroup.partial_cmp(&other.cgroup),
+            SortBy::Tty => self.tty.partial_cmp(&other.tty),
+            SortBy::Name => self.name.partial_cmp(&other.name),
+            SortBy::OomScore => self.oom_score.partial_cmp(&other.oom_score),
+            SortBy::Swap => self.swap.partial_cmp(&other.swap),
+            SortBy::DiskIo => (self.read_bytes + self.written_bytes)
+                .partial_cmp(&(other.read_bytes + other.written_bytes)),
+        };
+        let ordering = match direction {
+            SortDirection::Ascending => ordering,
+            SortDirection::Descending => ordering.map(std::cmp::Ordering::reverse),
         };
         match ordering {
             Some(std::cmp::Ordering::Equal) => self.pid.cmp(&other.pid),
@@ -90,18 +556,23 @@ impl Process {
         }
     }
 
-    pub(crate) fn render_header(area: Rect, sort_by: SortBy, buffer: &mut Buffer) -> u16 {
+    pub(crate) fn render_header(
+        area: Rect,
+        sort_by: SortBy,
+        layout: &TableLayout,
+        providers: &[Box<dyn ColumnProvider>],
+        buffer: &mut Buffer,
+    ) -> u16 {
         let table_header = {
             let mut line = Line::default();
-            for column in SortBy::all() {
-                let leading_spaces = match column {
-                    SortBy::Pid => 5,
-                    SortBy::Cpu => 3,
-                    SortBy::Ram => 7,
-                };
-                line.push_span(" ".repeat(leading_spaces));
+            for (i, &(column, width)) in layout.widths.iter().enumerate() {
+                let name = column.header(layout.format);
+                if i > 0 {
+                    line.push_span(" ");
+                }
+                line.push_span(" ".repeat(width.saturating_sub(name.len())));
                 line.push_span(Span::styled(
-                    format!("{:?}", column).to_lowercase(),
+                    name,
                     if column == sort_by {
                         Style::new().add_modifier(Modifier::REVERSED)
                     } else {
@@ -109,6 +580,12 @@ impl Process {
                     },
                 ));
             }
+            for (provider, &width) in providers.iter().zip(&layout.provider_widths) {
+                if !line.spans.is_empty() {
+                    line.push_span(" ");
+                }
+                line.push_span(format!("{:>1$}", provider.header(), width));
+            }
             line.push_span(" ");
             line
         };
@@ -138,21 +615,358 @@ impl Process {
         2
     }
 
-    pub(crate) fn table_data(&self) -> String {
+    pub(crate) fn includes_descendants(&self) -> bool {
+        self.descendants > 0
+    }
+
+    pub(crate) fn table_data(
+        &self,
+        layout: &TableLayout,
+        providers: &[Box<dyn ColumnProvider>],
+        heat_map: Option<&HeatMap>,
+        depth: usize,
+    ) -> Vec<Span<'static>> {
+        let mut spans = Vec::new();
+        for (i, &(column, width)) in layout.widths.iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::raw(" "));
+            }
+            spans.push(Span::styled(
+                format!("{:>1$}", self.cell(column, layout.format, depth), width),
+                heat_map.map_or(Style::new(), |heat_map| {
+                    heat_map.style(self, column, layout.format.accumulation)
+                }),
+            ));
+        }
+        for (provider, &width) in providers.iter().zip(&layout.provider_widths) {
+            if !spans.is_empty() {
+                spans.push(Span::raw(" "));
+            }
+            spans.push(Span::raw(format!("{:>1$}", provider.value(self), width)));
+        }
+        spans
+    }
+
+    pub(crate) fn usage(&self, accumulation: Accumulation) -> String {
+        let format = CellFormat {
+            accumulation,
+            ..CellFormat::default()
+        };
         format!(
-            "{:>8} {:>4.0}% {:>7}MB",
-            self.pid.as_u32(),
-            self.cpu,
-            (self.ram / 2_u64.pow(20)).to_formatted_string(&Locale::en)
+            "cpu {}, ram {}",
+            self.cell(SortBy::Cpu, format, 0),
+            self.cell(SortBy::Ram, format, 0)
         )
     }
+
+    fn cell(&self, column: SortBy, format: CellFormat, depth: usize) -> String {
+        let per_cpu = |cpu: f32| match format.cpu_count {
+            Some(cpu_count) if cpu_count > 0 => cpu / cpu_count as f32,
+            _ => cpu,
+        };
+        let render_ram = |ram: u64| {
+            format!(
+                "{}{}",
+                (ram / 2_u64.pow(20)).to_formatted_string(&format.locale),
+                if format.compact { "M" } else { "MB" }
+            )
+        };
+        let render_ram_usage = |ram: u64| match format.total_memory {
+            Some(total_memory) if total_memory > 0 => {
+                format!("{}%", (ram as f64 / total_memory as f64 * 100.0).round())
+            }
+            _ => render_ram(ram),
+        };
+        match column {
+            SortBy::Pid => self.pid.as_u32().to_string(),
+            SortBy::Cpu if format.wide => format!(
+                "{}/{}",
+                format_cpu(per_cpu(self.cpu - self.descendants_cpu), format.compact),
+                format_cpu(per_cpu(self.cpu), format.compact)
+            ),
+            SortBy::Cpu => format_cpu(per_cpu(self.cpu(format.accumulation)), format.compact),
+            SortBy::Ram if format.wide => format!(
+                "{}/{}",
+                render_ram_usage(self.ram - self.descendants_ram),
+                render_ram_usage(self.ram)
+            ),
+            SortBy::Ram => render_ram_usage(self.ram(format.accumulation)),
+            SortBy::Swap => render_ram(self.swap),
+            SortBy::Descendants => self.descendants.to_string(),
+            SortBy::Generation => depth.to_string(),
+            SortBy::OomScore => match self.oom_score {
+                Some(oom_score) => oom_score.to_string(),
+                None => "-".to_string(),
+            },
+            SortBy::Cgroup => match &self.cgroup {
+                Some(cgroup) => cgroup.rsplit('/').next().unwrap_or(cgroup).to_string(),
+                None => "-".to_string(),
+            },
+            SortBy::Tty => self.tty.clone().unwrap_or_else(|| "?".to_string()),
+            SortBy::Name => self.name.clone(),
+            SortBy::Threads => self.threads.to_string(),
+            SortBy::RunTime if format.absolute_start_time => {
+                format_start_time(self.start_time, self.run_time, &Local)
+            }
+            SortBy::RunTime => format_run_time(self.run_time),
+            SortBy::DiskIo => format!(
+                "{}/{}",
+                format_bytes(self.read_bytes),
+                format_bytes(self.written_bytes)
+            ),
+        }
+    }
+}
+
+// Accumulated cpu usage easily exceeds 999% on machines with many cores. To
+// keep the column narrow, those values are shown as multiples of a core.
+fn format_cpu(cpu: f32, compact: bool) -> String {
+    let cores = cpu / 100.0;
+    if cpu < 999.5 {
+        format!("{:.0}%", cpu)
+    } else if cores < 99.95 && !compact {
+        format!("{:.1}x", cores)
+    } else {
+        format!("{:.0}x", cores)
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    let mut value = bytes as f64;
+    for unit in ["B", "K", "M", "G"] {
+        if value < 1024.0 {
+            return if unit == "B" || value >= 10.0 {
+                format!("{:.0}{}", value, unit)
+            } else {
+                format!("{:.1}{}", value, unit)
+            };
+        }
+        value /= 1024.0;
+    }
+    format!("{:.0}T", value)
+}
+
+fn format_run_time(run_time: u64) -> String {
+    let days = run_time / (24 * 60 * 60);
+    let hours = run_time / (60 * 60) % 24;
+    let minutes = run_time / 60 % 60;
+    let seconds = run_time % 60;
+    if days > 0 {
+        format!("{}d {:02}:{:02}", days, hours, minutes)
+    } else {
+        format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
+    }
+}
+
+fn format_start_time<Tz: TimeZone>(start_time: u64, run_time: u64, timezone: &Tz) -> String
+where
+    Tz::Offset: fmt::Display,
+{
+    match timezone.timestamp_opt(start_time.try_into().unwrap_or(i64::MAX), 0) {
+        LocalResult::Single(start_time) | LocalResult::Ambiguous(start_time, _) => {
+            if run_time < 24 * 60 * 60 {
+                start_time.format("%H:%M:%S").to_string()
+            } else {
+                start_time.format("%b %d %H:%M").to_string()
+            }
+        }
+        LocalResult::None => "?".to_string(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct HeatMap {
+    pub(crate) cpu: [f32; 2],
+    pub(crate) ram_in_mb: [u64; 2],
+}
+
+impl HeatMap {
+    fn style(&self, process: &Process, column: SortBy, accumulation: Accumulation) -> Style {
+        let level = match column {
+            SortBy::Cpu => self
+                .cpu
+                .iter()
+                .filter(|&&threshold| process.cpu(accumulation) >= threshold)
+                .count(),
+            SortBy::Ram => self
+                .ram_in_mb
+                .iter()
+                .filter(|&&threshold| process.ram(accumulation) / 2_u64.pow(20) >= threshold)
+                .count(),
+            _ => return Style::new(),
+        };
+        match level {
+            0 => Style::new().green(),
+            1 => Style::new().yellow(),
+            _ => Style::new().red(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CellFormat {
+    pub(crate) compact: bool,
+    pub(crate) wide: bool,
+    pub(crate) absolute_start_time: bool,
+    pub(crate) accumulation: Accumulation,
+    // Renders ram as a percentage of this total instead of in MB.
+    pub(crate) total_memory: Option<u64>,
+    // Used for the thousands separators of ram.
+    pub(crate) locale: Locale,
+    // Divides cpu by this number of cpus, so that fully using all of them
+    // is 100%.
+    pub(crate) cpu_count: Option<usize>,
+}
+
+impl Default for CellFormat {
+    fn default() -> CellFormat {
+        CellFormat {
+            compact: false,
+            wide: false,
+            absolute_start_time: false,
+            accumulation: Accumulation::default(),
+            total_memory: None,
+            locale: Locale::en,
+            cpu_count: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Accumulation {
+    pub(crate) cpu: bool,
+    pub(crate) ram: bool,
+}
+
+impl Default for Accumulation {
+    fn default() -> Accumulation {
+        Accumulation {
+            cpu: true,
+            ram: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TableLayout {
+    format: CellFormat,
+    widths: Vec<(SortBy, usize)>,
+    // The widths of the columns added with `ColumnProvider`s, in order.
+    provider_widths: Vec<usize>,
+}
+
+impl TableLayout {
+    // How far columns can be widened or narrowed with `adjustments`.
+    pub(crate) const MAX_ADJUSTMENT: isize = 20;
+
+    pub(crate) fn new<'a>(
+        columns: &[SortBy],
+        format: CellFormat,
+        processes: impl Iterator<Item = (usize, &'a Process)>,
+        adjustments: &HashMap<SortBy, isize>,
+        providers: &[Box<dyn ColumnProvider>],
+    ) -> TableLayout {
+        // Columns can't be narrower than their header and cells. Widths are
+        // counted in chars, like `format!` pads them.
+        let mut content_widths: Vec<usize> = columns
+            .iter()
+            .map(|column| column.header(format).chars().count())
+            .collect();
+        let mut provider_widths: Vec<usize> = providers
+            .iter()
+            .map(|provider| provider.header().chars().count())
+            .collect();
+        for (depth, process) in processes {
+            for (width, column) in content_widths.iter_mut().zip(columns) {
+                *width = (*width).max(process.cell(*column, format, depth).chars().count());
+            }
+            for (width, provider) in provider_widths.iter_mut().zip(providers) {
+                *width = (*width).max(provider.value(process).chars().count());
+            }
+        }
+        let widths = columns
+            .iter()
+            .zip(content_widths)
+            .map(|(&column, content_width)| {
+                let width = column.min_width(format.compact).max(content_width);
+                let adjustment = adjustments.get(&column).copied().unwrap_or(0);
+                let adjusted = width.saturating_add_signed(adjustment);
+                (column, adjusted.max(content_width))
+            })
+            .collect();
+        TableLayout {
+            format,
+            widths,
+            provider_widths,
+        }
+    }
+
+    // The same layout, but with cells showing values accumulated according
+    // to `accumulation`.
+    pub(crate) fn with_accumulation(&self, accumulation: Accumulation) -> TableLayout {
+        TableLayout {
+            format: CellFormat {
+                accumulation,
+                ..self.format
+            },
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn is_for(&self, columns: &[SortBy], format: CellFormat) -> bool {
+        self.format == format && self.widths.iter().map(|(column, _)| column).eq(columns)
+    }
+
+    pub(crate) fn column_at(&self, x: u16) -> Option<SortBy> {
+        let mut offset = 0;
+        for &(column, width) in self.widths.iter() {
+            if (offset..offset + width).contains(&usize::from(x)) {
+                return Some(column);
+            }
+            offset += width + 1;
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub(crate) enum SortBy {
     Pid,
     Cpu,
     Ram,
+    Swap,
+    Descendants,
+    Generation,
+    Threads,
+    RunTime,
+    DiskIo,
+    OomScore,
+    Cgroup,
+    Tty,
+    Name,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+impl SortDirection {
+    pub(crate) fn parse(direction: &str) -> Result<SortDirection, String> {
+        match direction {
+            "asc" => Ok(SortDirection::Ascending),
+            "desc" => Ok(SortDirection::Descending),
+            _ => Err(format!("invalid sort direction: {}", direction)),
+        }
+    }
+
+    pub(crate) fn reverse(self) -> SortDirection {
+        match self {
+            SortDirection::Ascending => SortDirection::Descending,
+            SortDirection::Descending => SortDirection::Ascending,
+        }
+    }
 }
 
 #[allow(clippy::derivable_impls)]
@@ -163,89 +977,1303 @@ impl Default for SortBy {
 }
 
 impl SortBy {
-    pub(crate) fn next(self) -> SortBy {
+    pub(crate) fn parse(column: &str) -> Result<SortBy, String> {
+        SortBy::all()
+            .find(|sort_by| format!("{:?}", sort_by).eq_ignore_ascii_case(column))
+            .ok_or_else(|| {
+                format!(
+                    "unknown column: {}, expected one of: {}",
+                    column,
+                    SortBy::all()
+                        .map(|sort_by| format!("{:?}", sort_by).to_lowercase())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+
+    pub(crate) fn default_direction(self) -> SortDirection {
         match self {
-            SortBy::Pid => SortBy::Cpu,
-            SortBy::Cpu => SortBy::Ram,
-            SortBy::Ram => SortBy::Pid,
+            SortBy::Pid | SortBy::Cgroup | SortBy::Tty | SortBy::Name => SortDirection::Ascending,
+            _ => SortDirection::Descending,
         }
     }
 
-    fn all() -> impl Iterator<Item = SortBy> {
-        vec![SortBy::Pid, SortBy::Cpu, SortBy::Ram].into_iter()
+    fn header(self, format: CellFormat) -> String {
+        match self {
+            SortBy::Cpu | SortBy::Ram if format.wide => {
+                format!("{:?} own/total", self).to_lowercase()
+            }
+            SortBy::RunTime if format.absolute_start_time => "started".to_string(),
+            SortBy::RunTime => "runtime".to_string(),
+            SortBy::Generation => "gen".to_string(),
+            SortBy::OomScore => "oom".to_string(),
+            SortBy::DiskIo => "disk r/w".to_string(),
+            _ => format!("{:?}", self).to_lowercase(),
+        }
+    }
+
+    fn min_width(self, compact: bool) -> usize {
+        match (self, compact) {
+            (SortBy::Pid, false) => 8,
+            (SortBy::Pid, true) => 7,
+            (SortBy::Cpu, false) => 5,
+            (SortBy::Cpu, true) => 4,
+            (SortBy::Ram, false) => 9,
+            (SortBy::Ram, true) => 6,
+            (SortBy::Swap, false) => 9,
+            (SortBy::Swap, true) => 6,
+            (SortBy::Descendants, _) => 11,
+            (SortBy::Generation, _) => 3,
+            (SortBy::Threads, _) => 7,
+            (SortBy::RunTime, _) => 8,
+            (SortBy::DiskIo, _) => 11,
+            (SortBy::OomScore, _) => 4,
+            (SortBy::Cgroup, _) => 6,
+            (SortBy::Tty, _) => 5,
+            (SortBy::Name, _) => 4,
+        }
+    }
+
+    pub(crate) fn all() -> impl Iterator<Item = SortBy> {
+        vec![
+            SortBy::Pid,
+            SortBy::Cpu,
+            SortBy::Ram,
+            SortBy::Swap,
+            SortBy::Descendants,
+            SortBy::Generation,
+            SortBy::Threads,
+            SortBy::RunTime,
+            SortBy::DiskIo,
+            SortBy::OomScore,
+            SortBy::Cgroup,
+            SortBy::Tty,
+            SortBy::Name,
+        ]
+        .into_iter()
     }
 }
 
+// Fields that take an extra file read per process, so they're only read
+// while a column, sort or filter needs them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Details {
+    pub(crate) environment: bool,
+    pub(crate) swap: bool,
+    pub(crate) oom_score: bool,
+    pub(crate) cgroup: bool,
+    pub(crate) tty: bool,
+}
+
 #[derive(Debug)]
 pub(crate) struct ProcessWatcher(ProcessWatcherInner);
 
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 enum ProcessWatcherInner {
     Production {
         system: sysinfo::System,
+        users: sysinfo::Users,
+        details: Details,
+    },
+    Replay {
+        snapshots: VecDeque<Vec<Process>>,
+        processes: Vec<Process>,
+    },
+    Command {
+        command: String,
+        processes: Vec<Process>,
+    },
+    Proc {
+        root: PathBuf,
+        users: sysinfo::Users,
+        processes: Vec<Process>,
+        cpu_ticks: HashMap<Pid, u64>,
+        last_refresh: Option<Instant>,
+        details: Details,
+        total_memory: Option<u64>,
+        cpu_count: Option<usize>,
     },
     #[cfg(test)]
     TestWatcher {
         processes: Vec<Process>,
+        total_memory: Option<u64>,
+        cpu_count: Option<usize>,
     },
 }
 
 impl ProcessWatcher {
-    pub(crate) fn new(system: sysinfo::System) -> ProcessWatcher {
-        ProcessWatcher(ProcessWatcherInner::Production { system })
+    pub(crate) fn new(mut system: sysinfo::System) -> ProcessWatcher {
+        // Only to know how many cpus there are.
+        system.refresh_cpu();
+        ProcessWatcher(ProcessWatcherInner::Production {
+            system,
+            users: sysinfo::Users::new_with_refreshed_list(),
+            details: Details::default(),
+        })
+    }
+
+    pub(crate) fn replay(snapshot: &Path) -> R<ProcessWatcher> {
+        let snapshots =
+            serde_json::Deserializer::from_reader(BufReader::new(File::open(snapshot)?))
+                .into_iter()
+                .collect::<Result<VecDeque<Vec<Process>>, _>>()?;
+        Ok(ProcessWatcher(ProcessWatcherInner::Replay {
+            snapshots,
+            processes: Vec::new(),
+        }))
     }
 
-    pub(crate) fn refresh(&mut self) {
+    pub(crate) fn command(command: &str) -> ProcessWatcher {
+        ProcessWatcher(ProcessWatcherInner::Command {
+            command: command.to_string(),
+            processes: Vec::new(),
+        })
+    }
+
+    pub(crate) fn proc(root: &Path) -> ProcessWatcher {
+        ProcessWatcher(ProcessWatcherInner::Proc {
+            root: root.to_path_buf(),
+            users: sysinfo::Users::new_with_refreshed_list(),
+            processes: Vec::new(),
+            cpu_ticks: HashMap::new(),
+            last_refresh: None,
+            details: Details::default(),
+            total_memory: None,
+            cpu_count: None,
+        })
+    }
+
+    pub(crate) fn set_details(&mut self, value: Details) {
         match self {
-            ProcessWatcher(ProcessWatcherInner::Production { system }) => system
-                .refresh_processes_specifics(
-                    ProcessRefreshKind::new()
-                        .with_memory()
-                        .with_cpu()
-                        .with_cmd(UpdateKind::OnlyIfNotSet),
-                ),
+            ProcessWatcher(ProcessWatcherInner::Production { details, .. })
+            | ProcessWatcher(ProcessWatcherInner::Proc { details, .. }) => *details = value,
+            _ => {}
+        }
+    }
+
+    pub(crate) fn refresh(&mut self) -> R<()> {
+        match self {
+            ProcessWatcher(ProcessWatcherInner::Production {
+                system, details, ..
+            }) => {
+                let refresh_kind = ProcessRefreshKind::new()
+                    .with_memory()
+                    .with_cpu()
+                    .with_disk_usage()
+                    .with_cmd(UpdateKind::OnlyIfNotSet);
+                system.refresh_processes_specifics(if details.environment {
+                    refresh_kind.with_environ(UpdateKind::OnlyIfNotSet)
+                } else {
+                    refresh_kind
+                });
+                system.refresh_memory();
+            }
+            ProcessWatcher(ProcessWatcherInner::Replay {
+                snapshots,
+                processes,
+            }) => {
+                if let Some(snapshot) = snapshots.pop_front() {
+                    *processes = snapshot;
+                }
+            }
+            ProcessWatcher(ProcessWatcherInner::Command { command, processes }) => {
+                let output = std::process::Command::new("sh")
+                    .arg("-c")
+                    .arg(&*command)
+                    .output()?;
+                if !output.status.success() {
+                    return Err(format!(
+                        "{} failed: {}",
+                        command,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )
+                    .into());
+                }
+                *processes = parse_ps_output(&String::from_utf8_lossy(&output.stdout))?;
+            }
+            ProcessWatcher(ProcessWatcherInner::Proc {
+                root,
+                users,
+                processes,
+                cpu_ticks,
+                last_refresh,
+                details,
+                total_memory,
+                cpu_count,
+            }) => {
+                *total_memory = read_total_memory(root);
+                *cpu_count = read_cpu_count(root);
+                let now = Instant::now();
+                let elapsed = last_refresh.map(|last_refresh| (now - last_refresh).as_secs_f32());
+                let boot_time = read_boot_time(root)?;
+                let mut new_cpu_ticks = HashMap::new();
+                processes.clear();
+                for entry in std::fs::read_dir(&*root)? {
+                    let entry = entry?;
+                    if entry.file_name().to_string_lossy().parse::<u32>().is_err() {
+                        continue;
+                    }
+                    // Processes can exit while we're reading their files.
+                    let Ok((mut process, ticks)) =
+                        read_proc_process(&entry.path(), boot_time, users, *details)
+                    else {
+                        continue;
+                    };
+                    if let (Some(elapsed), Some(previous)) = (elapsed, cpu_ticks.get(&process.pid))
+                    {
+                        process.cpu =
+                            ticks.saturating_sub(*previous) as f32 / clock_ticks() / elapsed
+                                * 100.0;
+                    }
+                    new_cpu_ticks.insert(process.pid, ticks);
+                    processes.push(process);
+                }
+                *cpu_ticks = new_cpu_ticks;
+                *last_refresh = Some(now);
+            }
             #[cfg(test)]
             ProcessWatcher(ProcessWatcherInner::TestWatcher { .. }) => {}
         }
+        Ok(())
     }
 
-    pub(crate) fn get_forest(&self) -> Forest<Process> {
+    pub(crate) fn processes(&self) -> Vec<Process> {
+        let mut processes = match self {
+            ProcessWatcher(ProcessWatcherInner::Production {
+                system,
+                users,
+                details,
+            }) => system
+                .processes()
+                .values()
+                .filter(|process| process.thread_kind() != Some(ThreadKind::Userland))
+                .map(|process| Process::from_sysinfo_process(process, users, *details))
+                .collect(),
+            ProcessWatcher(ProcessWatcherInner::Replay { processes, .. })
+            | ProcessWatcher(ProcessWatcherInner::Command { processes, .. })
+            | ProcessWatcher(ProcessWatcherInner::Proc { processes, .. }) => processes.clone(),
+            #[cfg(test)]
+            ProcessWatcher(ProcessWatcherInner::TestWatcher { processes, .. }) => processes.clone(),
+        };
+        // sysinfo occasionally reports NaN cpu usage, which would otherwise
+        // poison the accumulated values of all ancestors.
+        for process in &mut processes {
+            if process.cpu.is_nan() || process.cpu < 0.0 {
+                process.cpu = 0.0;
+            }
+        }
+        processes
+    }
+
+    // The physical memory of the system, if known.
+    pub(crate) fn total_memory(&self) -> Option<u64> {
         match self {
-            ProcessWatcher(ProcessWatcherInner::Production { system }) => Forest::new_forest(
-                system
-                    .processes()
-                    .values()
-                    .filter(|process| process.thread_kind() != Some(ThreadKind::Userland))
-                    .map(Process::from_sysinfo_process),
-            ),
+            ProcessWatcher(ProcessWatcherInner::Production { system, .. }) => {
+                Some(system.total_memory()).filter(|total_memory| *total_memory > 0)
+            }
+            ProcessWatcher(ProcessWatcherInner::Proc { total_memory, .. }) => *total_memory,
+            ProcessWatcher(ProcessWatcherInner::Replay { .. })
+            | ProcessWatcher(ProcessWatcherInner::Command { .. }) => None,
             #[cfg(test)]
-            ProcessWatcher(ProcessWatcherInner::TestWatcher { processes }) => {
-                Forest::new_forest(processes.iter().cloned())
+            ProcessWatcher(ProcessWatcherInner::TestWatcher { total_memory, .. }) => *total_memory,
+        }
+    }
+
+    pub(crate) fn cpu_count(&self) -> Option<usize> {
+        match self {
+            ProcessWatcher(ProcessWatcherInner::Production { system, .. }) => {
+                Some(system.cpus().len()).filter(|cpu_count| *cpu_count > 0)
             }
+            ProcessWatcher(ProcessWatcherInner::Proc { cpu_count, .. }) => *cpu_count,
+            ProcessWatcher(ProcessWatcherInner::Replay { .. })
+            | ProcessWatcher(ProcessWatcherInner::Command { .. }) => None,
+            #[cfg(test)]
+            ProcessWatcher(ProcessWatcherInner::TestWatcher { cpu_count, .. }) => *cpu_count,
+        }
+    }
+
+    pub(crate) fn get_forest(&self) -> Forest<Process> {
+        Forest::new_forest(self.processes().into_iter())
+    }
+}
+
+// `/proc` reports times in clock ticks and rss in pages. The fallbacks are
+// the values on practically all Linux systems.
+fn clock_ticks() -> f32 {
+    match sysconf(SysconfVar::CLK_TCK) {
+        Ok(Some(ticks)) => ticks as f32,
+        _ => 100.0,
+    }
+}
+
+fn page_size() -> u64 {
+    match sysconf(SysconfVar::PAGE_SIZE) {
+        Ok(Some(size)) => size as u64,
+        _ => 4096,
+    }
+}
+
+fn read_total_memory(root: &Path) -> Option<u64> {
+    let kilobytes: u64 = std::fs::read_to_string(root.join("meminfo"))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("MemTotal:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+// `/proc/stat` has a line for every cpu, after the one summing them all up.
+fn read_cpu_count(root: &Path) -> Option<usize> {
+    let cpu_count = std::fs::read_to_string(root.join("stat"))
+        .ok()?
+        .lines()
+        .filter(|line| {
+            line.strip_prefix("cpu")
+                .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+        })
+        .count();
+    Some(cpu_count).filter(|cpu_count| *cpu_count > 0)
+}
+
+fn read_boot_time(root: &Path) -> R<u64> {
+    std::fs::read_to_string(root.join("stat"))?
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))
+        .ok_or("no btime in /proc/stat")?
+        .trim()
+        .parse()
+        .map_err(Into::into)
+}
+
+fn read_null_separated(path: &Path) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .split('\0')
+        .filter(|entry| !entry.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn read_proc_process(
+    dir: &Path,
+    boot_time: u64,
+    users: &sysinfo::Users,
+    details: Details,
+) -> R<(Process, u64)> {
+    let stat = std::fs::read_to_string(dir.join("stat"))?;
+    let parse_error = || format!("unexpected stat format: {}", stat);
+    let (pid, rest) = stat.split_once(" (").ok_or_else(parse_error)?;
+    let (name, rest) = rest.rsplit_once(") ").ok_or_else(parse_error)?;
+    // Fields after the name, starting with the state (field 3 in proc(5)).
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let field = |number: usize| -> R<u64> {
+        Ok(fields
+            .get(number - 3)
+            .ok_or_else(parse_error)?
+            .parse()
+            .map_err(|_| parse_error())?)
+    };
+    let pid = Pid::from_u32(pid.trim().parse()?);
+    let parent = field(4)?;
+    let start_time = boot_time + field(22)? / clock_ticks() as u64;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+    let arguments = read_null_separated(&dir.join("cmdline"));
+    let process = Process {
+        pid,
+        name: std::fs::read_link(dir.join("exe"))
+            .ok()
+            .and_then(|exe| Some(exe.file_name()?.to_string_lossy().to_string()))
+            .unwrap_or_else(|| name.to_string()),
+        comm: name.to_string(),
+        arguments,
+        parent: (parent != 0).then_some(Pid::from_u32(parent.try_into()?)),
+        cpu: 0.0,
+        ram: field(24)? * page_size(),
+        descendants_cpu: 0.0,
+        descendants_ram: 0,
+        descendants_threads: 0,
+        descendants_read_bytes: 0,
+        descendants_written_bytes: 0,
+        descendants_swap: 0,
+        threads: field(20)?.try_into()?,
+        start_time,
+        run_time: now.saturating_sub(start_time),
+        read_bytes: 0,
+        written_bytes: 0,
+        swap: details.swap.then(|| read_swap(dir)).flatten().unwrap_or(0),
+        user: read_user_id(dir)
+            .and_then(|user_id| users.get_user_by_id(&user_id))
+            .map(|user| user.name().to_string()),
+        oom_score: details.oom_score.then(|| read_oom_score(dir)).flatten(),
+        cgroup: details.cgroup.then(|| read_cgroup(dir)).flatten(),
+        tty: tty_name(field(7)?),
+        environment: if details.environment {
+            read_null_separated(&dir.join("environ"))
+        } else {
+            Vec::new()
+        },
+        descendants: 0,
+        group_size: 0,
+    };
+    Ok((process, field(14)? + field(15)?))
+}
+
+fn read_user_id(dir: &Path) -> Option<sysinfo::Uid> {
+    std::fs::read_to_string(dir.join("status"))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("Uid:"))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+fn read_oom_score(dir: &Path) -> Option<i32> {
+    std::fs::read_to_string(dir.join("oom_score"))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn read_swap(dir: &Path) -> Option<u64> {
+    let kilobytes: u64 = std::fs::read_to_string(dir.join("status"))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("VmSwap:"))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+fn read_cgroup(dir: &Path) -> Option<String> {
+    std::fs::read_to_string(dir.join("cgroup"))
+        .ok()?
+        .lines()
+        .last()?
+        .splitn(3, ':')
+        .nth(2)
+        .map(ToString::to_string)
+}
+
+// The controlling terminal, from the tty_nr field of /proc/[pid]/stat.
+fn read_tty(dir: &Path) -> Option<String> {
+    let stat = std::fs::read_to_string(dir.join("stat")).ok()?;
+    let (_, rest) = stat.rsplit_once(") ")?;
+    tty_name(rest.split_whitespace().nth(4)?.parse().ok()?)
+}
+
+// Names the common kinds of terminals by their device number, see
+// Documentation/admin-guide/devices.txt in the kernel.
+fn tty_name(tty_nr: u64) -> Option<String> {
+    if tty_nr == 0 {
+        return None;
+    }
+    let major = (tty_nr >> 8) & 0xfff;
+    let minor = (tty_nr & 0xff) | ((tty_nr >> 12) & 0xfff00);
+    Some(match major {
+        4 if minor < 64 => format!("tty{}", minor),
+        4 => format!("ttyS{}", minor - 64),
+        136..=143 => format!("pts/{}", (major - 136) * 256 + minor),
+        _ => format!("{}:{}", major, minor),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Backend {
+    Sysinfo,
+    Proc,
+}
+
+impl Backend {
+    pub(crate) fn parse(backend: &str) -> Result<Backend, String> {
+        match backend {
+            "sysinfo" => Ok(Backend::Sysinfo),
+            "proc" => Ok(Backend::Proc),
+            _ => Err(format!("unknown backend: {}", backend)),
+        }
+    }
+}
+
+// Parses the output of `ps -eo pid,ppid,pcpu,rss,comm`.
+fn parse_ps_output(output: &str) -> R<Vec<Process>> {
+    let mut processes = Vec::new();
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() || fields[0] == "PID" {
+            continue;
+        }
+        let parse_error = || format!("unexpected ps output: {}", line);
+        if fields.len() < 5 {
+            return Err(parse_error().into());
         }
+        let pid: u32 = fields[0].parse().map_err(|_| parse_error())?;
+        let parent: u32 = fields[1].parse().map_err(|_| parse_error())?;
+        let name = fields[4..].join(" ");
+        processes.push(Process {
+            pid: Pid::from_u32(pid),
+            name: name.clone(),
+            comm: name.clone(),
+            arguments: vec![name],
+            parent: (parent != 0 && parent != pid).then_some(Pid::from_u32(parent)),
+            cpu: fields[2].parse().map_err(|_| parse_error())?,
+            ram: fields[3].parse::<u64>().map_err(|_| parse_error())? * 1024,
+            descendants_cpu: 0.0,
+            descendants_ram: 0,
+            descendants_threads: 0,
+            descendants_read_bytes: 0,
+            descendants_written_bytes: 0,
+            descendants_swap: 0,
+            threads: 1,
+            start_time: 0,
+            run_time: 0,
+            read_bytes: 0,
+            written_bytes: 0,
+            swap: 0,
+            user: None,
+            oom_score: None,
+            cgroup: None,
+            tty: None,
+            environment: Vec::new(),
+            descendants: 0,
+            group_size: 0,
+        });
     }
+    Ok(processes)
 }
 
 #[cfg(test)]
 pub(crate) mod test {
     use super::*;
+    use crate::tree::PrefixStyle;
+    use crate::utils::test::TempDir;
+
+    impl TableLayout {
+        pub(crate) fn widths(&self) -> &[(SortBy, usize)] {
+            &self.widths
+        }
+    }
 
     impl Process {
         pub(crate) fn fake(pid: usize, cpu: f32, parent: Option<usize>) -> Process {
             Process {
                 pid: pid.into(),
                 name: crate::utils::test::render_number(pid).to_string(),
+                comm: crate::utils::test::render_number(pid).to_string(),
                 arguments: Vec::new(),
                 parent: parent.map(From::from),
                 cpu,
                 ram: 0,
+                descendants_cpu: 0.0,
+                descendants_ram: 0,
+                descendants_threads: 0,
+                descendants_read_bytes: 0,
+                descendants_written_bytes: 0,
+                descendants_swap: 0,
+                threads: 1,
+                start_time: 0,
+                run_time: 0,
+                read_bytes: 0,
+                written_bytes: 0,
+                swap: 0,
+                user: None,
+                oom_score: None,
+                cgroup: None,
+                tty: None,
+                environment: Vec::new(),
+                descendants: 0,
+                group_size: 0,
             }
         }
+
+        // For pids that `fake` can't name.
+        pub(crate) fn with_pid(mut self, pid: u32) -> Process {
+            self.pid = Pid::from_u32(pid);
+            self
+        }
+
+        pub(crate) fn with_threads(mut self, threads: usize) -> Process {
+            self.threads = threads;
+            self
+        }
+
+        pub(crate) fn with_comm(mut self, comm: &str) -> Process {
+            self.comm = comm.to_string();
+            self
+        }
+
+        pub(crate) fn with_user(mut self, user: &str) -> Process {
+            self.user = Some(user.to_string());
+            self
+        }
+
+        pub(crate) fn with_oom_score(mut self, oom_score: i32) -> Process {
+            self.oom_score = Some(oom_score);
+            self
+        }
+
+        pub(crate) fn with_environment(mut self, environment: &[&str]) -> Process {
+            self.environment = environment.iter().map(ToString::to_string).collect();
+            self
+        }
+
+        pub(crate) fn with_tty(mut self, tty: &str) -> Process {
+            self.tty = Some(tty.to_string());
+            self
+        }
+
+        pub(crate) fn with_cgroup(mut self, cgroup: &str) -> Process {
+            self.cgroup = Some(cgroup.to_string());
+            self
+        }
+
+        pub(crate) fn with_ram(mut self, ram: u64) -> Process {
+            self.ram = ram;
+            self
+        }
+
+        pub(crate) fn with_run_time(mut self, run_time: u64) -> Process {
+            self.run_time = run_time;
+            self
+        }
+
+        pub(crate) fn with_arguments(mut self, arguments: &[&str]) -> Process {
+            self.arguments = arguments.iter().map(ToString::to_string).collect();
+            self
+        }
+
+        pub(crate) fn with_swap(mut self, swap: u64) -> Process {
+            self.swap = swap;
+            self
+        }
+
+        pub(crate) fn with_disk_io(mut self, read_bytes: u64, written_bytes: u64) -> Process {
+            self.read_bytes = read_bytes;
+            self.written_bytes = written_bytes;
+            self
+        }
     }
 
     impl ProcessWatcher {
         pub(crate) fn fake(processes: Vec<Process>) -> ProcessWatcher {
-            ProcessWatcher(ProcessWatcherInner::TestWatcher { processes })
+            ProcessWatcher(ProcessWatcherInner::TestWatcher {
+                processes,
+                total_memory: None,
+                cpu_count: None,
+            })
+        }
+
+        pub(crate) fn with_total_memory(mut self, total: u64) -> ProcessWatcher {
+            if let ProcessWatcher(ProcessWatcherInner::TestWatcher { total_memory, .. }) = &mut self
+            {
+                *total_memory = Some(total);
+            }
+            self
+        }
+
+        pub(crate) fn with_cpu_count(mut self, count: usize) -> ProcessWatcher {
+            if let ProcessWatcher(ProcessWatcherInner::TestWatcher { cpu_count, .. }) = &mut self {
+                *cpu_count = Some(count);
+            }
+            self
+        }
+    }
+
+    #[test]
+    fn processes_without_readable_names_still_get_a_label() {
+        let mut process = Process::fake(7, 0.0, None);
+        process.name = String::new();
+        process.arguments = vec![String::new()];
+        assert_eq!(process.to_string(), "seven");
+        for source in [NameSource::Argv0, NameSource::Exe, NameSource::Comm] {
+            assert_eq!(process.display_name(source), "seven");
+        }
+        process.comm = String::new();
+        assert_eq!(process.to_string(), "[7]");
+        assert_eq!(process.display_name(NameSource::Exe), "[7]");
+    }
+
+    #[test]
+    fn names_terminals_by_device_number() {
+        assert_eq!(tty_name(0), None);
+        assert_eq!(tty_name(0x8803).as_deref(), Some("pts/3"));
+        assert_eq!(tty_name(0x0401).as_deref(), Some("tty1"));
+        assert_eq!(tty_name(0x0440).as_deref(), Some("ttyS0"));
+        assert_eq!(tty_name(0x0501).as_deref(), Some("5:1"));
+    }
+
+    #[test]
+    fn nan_and_negative_cpu_count_as_zero() {
+        let forest = ProcessWatcher::fake(vec![
+            Process::fake(1, 1.0, None),
+            Process::fake(2, f32::NAN, Some(1)),
+            Process::fake(3, -5.0, Some(2)),
+            Process::fake(4, 2.0, Some(2)),
+        ])
+        .get_forest();
+        assert_eq!(
+            forest
+                .iter()
+                .map(|process| (process.pid.as_u32(), process.cpu))
+                .collect::<Vec<_>>(),
+            vec![(1, 3.0), (2, 2.0), (3, 0.0), (4, 2.0)]
+        );
+    }
+
+    #[test]
+    fn replays_processes_from_a_snapshot_file() -> R<()> {
+        let temp = TempDir::new("replay-test")?;
+        let snapshot = temp.join("replay.json");
+        std::fs::write(
+            &snapshot,
+            r#"[
+                {"pid": 1, "name": "one", "arguments": [], "parent": null, "cpu": 1.0, "ram": 0},
+                {"pid": 2, "name": "two", "arguments": ["two", "--flag"], "parent": 1, "cpu": 2.0, "ram": 0}
+            ]"#,
+        )?;
+        let mut process_watcher = ProcessWatcher::replay(&snapshot)?;
+        process_watcher.refresh()?;
+        let forest = process_watcher.get_forest();
+        assert_eq!(
+            forest
+                .render_forest_prefixes(&PrefixStyle::THIN)
+                .into_iter()
+                .map(|(prefix, process)| format!("{}{} {}%", prefix, process, process.cpu))
+                .collect::<Vec<_>>(),
+            vec!["one 3%", "└── two --flag 2%"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn replays_recorded_snapshots_one_per_refresh() -> R<()> {
+        let temp = TempDir::new("replay-recording-test")?;
+        let snapshot = temp.join("replay-recording.json");
+        std::fs::write(
+            &snapshot,
+            [
+                r#"[{"pid": 1, "name": "one", "arguments": [], "parent": null, "cpu": 1.0, "ram": 0}]"#,
+                r#"[{"pid": 2, "name": "two", "arguments": [], "parent": null, "cpu": 1.0, "ram": 0}]"#,
+            ]
+            .join("\n"),
+        )?;
+        let mut process_watcher = ProcessWatcher::replay(&snapshot)?;
+        let mut pids = Vec::new();
+        for _ in 0..3 {
+            process_watcher.refresh()?;
+            pids.push(
+                process_watcher
+                    .get_forest()
+                    .iter()
+                    .map(Node::id)
+                    .collect::<Vec<_>>(),
+            );
+        }
+        assert_eq!(pids, vec![vec![1.into()], vec![2.into()], vec![2.into()]]);
+        Ok(())
+    }
+
+    #[test]
+    fn environments_are_not_serialized() -> R<()> {
+        let process = Process::fake(1, 0.0, None).with_environment(&["TOKEN=secret"]);
+        let json = serde_json::to_string(&process)?;
+        assert!(!json.contains("secret"), "{}", json);
+        Ok(())
+    }
+
+    #[test]
+    fn formats_run_times() {
+        assert_eq!(format_run_time(0), "00:00:00");
+        assert_eq!(format_run_time(2 * 60 * 60 + 3 * 60 + 4), "02:03:04");
+        assert_eq!(
+            format_run_time(3 * 24 * 60 * 60 + 4 * 60 * 60 + 12 * 60),
+            "3d 04:12"
+        );
+    }
+
+    #[test]
+    fn formats_start_times() {
+        let start_time = 1_741_000_000;
+        assert_eq!(format_start_time(start_time, 60, &chrono::Utc), "11:06:40");
+        assert_eq!(
+            format_start_time(start_time, 2 * 24 * 60 * 60, &chrono::Utc),
+            "Mar 03 11:06"
+        );
+    }
+
+    #[test]
+    fn accumulates_disk_io_across_subtrees() {
+        let process_watcher = ProcessWatcher::fake(vec![
+            Process::fake(1, 0.0, None).with_disk_io(1, 10),
+            Process::fake(2, 0.0, Some(1)).with_disk_io(2, 20),
+            Process::fake(3, 0.0, Some(2)).with_disk_io(4, 40),
+        ]);
+        let forest = process_watcher.get_forest();
+        assert_eq!(
+            forest
+                .iter()
+                .map(|process| (process.read_bytes, process.written_bytes))
+                .collect::<Vec<_>>(),
+            vec![(7, 70), (6, 60), (4, 40)]
+        );
+    }
+
+    #[test]
+    fn grouping_counts_the_disk_io_of_children_once() {
+        let forest = ProcessWatcher::fake(vec![
+            Process::fake(1, 0.0, None)
+                .with_user("alice")
+                .with_disk_io(1, 10),
+            Process::fake(2, 0.0, Some(1))
+                .with_user("alice")
+                .with_disk_io(2, 20),
+            Process::fake(3, 0.0, Some(1))
+                .with_user("bob")
+                .with_disk_io(4, 40),
+        ])
+        .get_forest();
+        assert_eq!(
+            Process::group(Grouping::User, forest.iter())
+                .iter()
+                .map(|group| (group.name.as_str(), group.read_bytes, group.written_bytes))
+                .collect::<Vec<_>>(),
+            vec![("alice", 3, 30), ("bob", 4, 40)]
+        );
+    }
+
+    #[test]
+    fn grouping_counts_the_swap_of_children_once() {
+        let forest = ProcessWatcher::fake(vec![
+            Process::fake(1, 0.0, None).with_user("alice").with_swap(1),
+            Process::fake(2, 0.0, Some(1))
+                .with_user("alice")
+                .with_swap(2),
+            Process::fake(3, 0.0, Some(1)).with_user("bob").with_swap(4),
+        ])
+        .get_forest();
+        assert_eq!(
+            Process::group(Grouping::User, forest.iter())
+                .iter()
+                .map(|group| (group.name.as_str(), group.swap))
+                .collect::<Vec<_>>(),
+            vec![("alice", 3), ("bob", 4)]
+        );
+    }
+
+    #[test]
+    fn accumulates_swap_across_subtrees() {
+        let process_watcher = ProcessWatcher::fake(vec![
+            Process::fake(1, 0.0, None).with_swap(1),
+            Process::fake(2, 0.0, Some(1)).with_swap(2),
+            Process::fake(3, 0.0, Some(2)).with_swap(4),
+        ]);
+        let forest = process_watcher.get_forest();
+        assert_eq!(
+            forest
+                .iter()
+                .map(|process| process.swap)
+                .collect::<Vec<_>>(),
+            vec![7, 6, 4]
+        );
+    }
+
+    #[test]
+    fn sorts_by_swap() {
+        let mut processes = [
+            Process::fake(1, 0.0, None).with_swap(2),
+            Process::fake(2, 0.0, None),
+            Process::fake(3, 0.0, None).with_swap(5),
+        ];
+        processes.sort_by(|a, b| {
+            a.compare(
+                b,
+                SortBy::Swap,
+                SortBy::Swap.default_direction(),
+                Accumulation::default(),
+            )
+        });
+        assert_eq!(
+            processes.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![3.into(), 1.into(), 2.into()]
+        );
+    }
+
+    #[test]
+    fn reads_swap_from_proc_status() -> R<()> {
+        let temp = TempDir::new("swap-test")?;
+        let dir = temp.path();
+        std::fs::write(dir.join("status"), "Name:\tbash\nVmSwap:\t    1234 kB\n")?;
+        let swap = read_swap(dir);
+        assert_eq!(swap, Some(1234 * 1024));
+        Ok(())
+    }
+
+    #[test]
+    fn sorts_by_combined_disk_io() {
+        let mut processes = [
+            Process::fake(1, 0.0, None).with_disk_io(5, 0),
+            Process::fake(2, 0.0, None).with_disk_io(3, 3),
+            Process::fake(3, 0.0, None).with_disk_io(0, 1),
+        ];
+        processes.sort_by(|a, b| {
+            a.compare(
+                b,
+                SortBy::DiskIo,
+                SortDirection::Descending,
+                Accumulation::default(),
+            )
+        });
+        assert_eq!(
+            processes.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![2.into(), 1.into(), 3.into()]
+        );
+    }
+
+    #[test]
+    fn sorts_by_name_with_empty_names_last() {
+        let named = |pid, name: &str| {
+            let mut process = Process::fake(pid, 0.0, None);
+            process.name = name.to_string();
+            process
+        };
+        let mut processes = [
+            named(1, ""),
+            named(2, "bash"),
+            named(3, ""),
+            named(4, "zsh"),
+            named(5, "fish"),
+        ];
+        let sorted = |processes: &mut [Process], direction| {
+            processes
+                .sort_by(|a, b| a.compare(b, SortBy::Name, direction, Accumulation::default()));
+            processes.iter().map(Node::id).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            sorted(&mut processes, SortDirection::Ascending),
+            vec![2.into(), 5.into(), 4.into(), 1.into(), 3.into()]
+        );
+        assert_eq!(
+            sorted(&mut processes, SortDirection::Descending),
+            vec![4.into(), 5.into(), 2.into(), 1.into(), 3.into()]
+        );
+    }
+
+    #[test]
+    fn sorts_likely_oom_kills_first() {
+        let mut processes = [
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, None).with_oom_score(10),
+            Process::fake(3, 0.0, None).with_oom_score(800),
+        ];
+        processes.sort_by(|a, b| {
+            a.compare(
+                b,
+                SortBy::OomScore,
+                SortBy::OomScore.default_direction(),
+                Accumulation::default(),
+            )
+        });
+        assert_eq!(
+            processes.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![3.into(), 2.into(), 1.into()]
+        );
+    }
+
+    #[test]
+    fn formats_large_cpu_values_as_multiples_of_a_core() {
+        assert_eq!(format_cpu(0.0, false), "0%");
+        assert_eq!(format_cpu(999.0, false), "999%");
+        assert_eq!(format_cpu(999.6, false), "10.0x");
+        assert_eq!(format_cpu(1600.0, false), "16.0x");
+        assert_eq!(format_cpu(1600.0, true), "16x");
+        assert_eq!(format_cpu(12345.0, false), "123x");
+        assert_eq!(format_cpu(123456.0, false), "1235x");
+        for cpu in [0.0, 999.0, 1600.0, 9999.0, 99_000.0] {
+            assert!(format_cpu(cpu, false).len() <= SortBy::Cpu.min_width(false));
+            assert!(format_cpu(cpu, true).len() <= SortBy::Cpu.min_width(true));
         }
     }
+
+    #[test]
+    fn formats_bytes_human_readably() {
+        assert_eq!(format_bytes(0), "0B");
+        assert_eq!(format_bytes(1023), "1023B");
+        assert_eq!(format_bytes(1536), "1.5K");
+        assert_eq!(format_bytes(20 * 1024 * 1024), "20M");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0G");
+    }
+
+    #[test]
+    fn accumulation_can_be_disabled_per_column() {
+        let process_watcher = ProcessWatcher::fake(vec![
+            Process::fake(1, 1.0, None).with_ram(1024),
+            Process::fake(2, 2.0, Some(1)).with_ram(2048),
+        ]);
+        let forest = process_watcher.get_forest();
+        let root = forest.iter().next().unwrap();
+        let accumulation = Accumulation {
+            cpu: true,
+            ram: false,
+        };
+        assert_eq!(root.cpu(accumulation), 3.0);
+        assert_eq!(root.ram(accumulation), 1024);
+        let format = CellFormat {
+            accumulation: Accumulation {
+                cpu: false,
+                ram: true,
+            },
+            ..CellFormat::default()
+        };
+        assert_eq!(root.cell(SortBy::Cpu, format, 0), "1%");
+        assert_eq!(root.ram(format.accumulation), 3072);
+    }
+
+    #[test]
+    fn sorts_by_own_values_when_accumulation_is_disabled() {
+        let process_watcher = ProcessWatcher::fake(vec![
+            Process::fake(1, 1.0, None),
+            Process::fake(2, 5.0, Some(1)),
+            Process::fake(3, 4.0, None),
+        ]);
+        let forest = process_watcher.get_forest();
+        let mut roots = forest
+            .iter()
+            .filter(|process| process.parent().is_none())
+            .collect::<Vec<_>>();
+        let own_cpu = Accumulation {
+            cpu: false,
+            ram: true,
+        };
+        roots.sort_by(|a, b| a.compare(b, SortBy::Cpu, SortDirection::Descending, own_cpu));
+        assert_eq!(
+            roots.iter().map(|process| process.id()).collect::<Vec<_>>(),
+            vec![3.into(), 1.into()]
+        );
+        roots.sort_by(|a, b| {
+            a.compare(
+                b,
+                SortBy::Cpu,
+                SortDirection::Descending,
+                Accumulation::default(),
+            )
+        });
+        assert_eq!(
+            roots.iter().map(|process| process.id()).collect::<Vec<_>>(),
+            vec![1.into(), 3.into()]
+        );
+    }
+
+    #[test]
+    fn parses_ps_output_into_a_forest() -> R<()> {
+        let processes = parse_ps_output(
+            "    PID    PPID %CPU   RSS COMMAND
+                   1       0  0.5  2048 init
+                  42       1 12.0  1024 sshd
+                  43      42  1.5   512 bash
+                  50       1  0.0   256 cron daemon
+            ",
+        )?;
+        let forest = Forest::new_forest(processes.into_iter());
+        assert_eq!(
+            forest
+                .render_forest_prefixes(&PrefixStyle::THIN)
+                .into_iter()
+                .map(|(prefix, process)| format!(
+                    "{}{} {}% {}",
+                    prefix,
+                    process,
+                    process.cpu,
+                    process.ram / 1024
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                "init 14% 3840",
+                "├─┬ sshd 13.5% 1536",
+                "│ └── bash 1.5% 512",
+                "└── cron daemon 0% 256"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_malformed_ps_output() {
+        assert!(parse_ps_output("1 0 abc 12 init").is_err());
+        assert!(parse_ps_output("1 0").is_err());
+    }
+
+    #[test]
+    fn reads_processes_from_a_command() -> R<()> {
+        let mut process_watcher =
+            ProcessWatcher::command("printf '1 0 2.0 4 one\\n2 1 1.0 4 two\\n'");
+        process_watcher.refresh()?;
+        assert_eq!(
+            process_watcher
+                .processes()
+                .iter()
+                .map(|process| (process.id(), process.parent()))
+                .collect::<Vec<_>>(),
+            vec![(1.into(), None), (2.into(), Some(1.into()))]
+        );
+        Ok(())
+    }
+
+    fn write_proc_fixture(root: &Path, pid: u32, stat: &str, cmdline: &str) -> R<()> {
+        let dir = root.join(pid.to_string());
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("stat"), stat)?;
+        std::fs::write(dir.join("cmdline"), cmdline)?;
+        std::fs::write(dir.join("oom_score"), "666\n")?;
+        std::fs::write(dir.join("cgroup"), "0::/user.slice/session-2.scope\n")?;
+        Ok(())
+    }
+
+    #[test]
+    fn reads_processes_from_proc() -> R<()> {
+        let temp = TempDir::new("proc-test")?;
+        let root = temp.path();
+        std::fs::create_dir_all(root.join("self"))?;
+        std::fs::write(
+            root.join("stat"),
+            "cpu  1 2 3 4\ncpu0 1 1 1 1\ncpu1 0 1 2 3\nbtime 1700000000\n",
+        )?;
+        std::fs::write(
+            root.join("meminfo"),
+            "MemTotal:       16318948 kB\nMemFree:         1234567 kB\n",
+        )?;
+        write_proc_fixture(
+            root,
+            1,
+            "1 (init) S 0 1 1 0 -1 4194560 0 0 0 0 10 20 0 0 20 0 1 0 500 1000 3 0",
+            "/sbin/init\0splash\0",
+        )?;
+        write_proc_fixture(
+            root,
+            42,
+            "42 (my (weird) proc) R 1 42 42 0 -1 4194560 0 0 0 0 5 5 0 0 20 0 4 0 1000 1000 256 0",
+            "",
+        )?;
+        let mut process_watcher = ProcessWatcher::proc(root);
+        process_watcher.set_details(Details {
+            oom_score: true,
+            cgroup: true,
+            ..Details::default()
+        });
+        process_watcher.refresh()?;
+        assert_eq!(process_watcher.total_memory(), Some(16318948 * 1024));
+        assert_eq!(process_watcher.cpu_count(), Some(2));
+        let mut processes = process_watcher.processes();
+        processes.sort_by_key(|process| process.pid);
+        assert_eq!(
+            processes
+                .iter()
+                .map(|process| (
+                    process.pid.as_u32(),
+                    process.to_string(),
+                    process.parent.map(|pid| pid.as_u32()),
+                    process.ram,
+                    process.threads,
+                    process.start_time,
+                    process.oom_score,
+                    process.cgroup.as_deref(),
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    1,
+                    "init splash".to_string(),
+                    None,
+                    3 * page_size(),
+                    1,
+                    1_700_000_005,
+                    Some(666),
+                    Some("/user.slice/session-2.scope"),
+                ),
+                (
+                    42,
+                    "my (weird) proc".to_string(),
+                    Some(1),
+                    256 * page_size(),
+                    4,
+                    1_700_000_010,
+                    Some(666),
+                    Some("/user.slice/session-2.scope"),
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reads_the_environment_from_proc_only_when_requested() -> R<()> {
+        let temp = TempDir::new("proc-environment-test")?;
+        let root = temp.path();
+        std::fs::write(root.join("stat"), "cpu  1 2 3 4\nbtime 1700000000\n")?;
+        write_proc_fixture(
+            root,
+            1,
+            "1 (init) S 0 1 1 0 -1 4194560 0 0 0 0 10 20 0 0 20 0 1 0 500 1000 3 0",
+            "/sbin/init\0",
+        )?;
+        std::fs::write(root.join("1").join("environ"), "HOME=/\0RUST_LOG=debug\0")?;
+        let mut process_watcher = ProcessWatcher::proc(root);
+        process_watcher.refresh()?;
+        let without = process_watcher.processes();
+        process_watcher.set_details(Details {
+            environment: true,
+            ..Details::default()
+        });
+        process_watcher.refresh()?;
+        let with = process_watcher.processes();
+        assert_eq!(without[0].environment(), &[] as &[String]);
+        assert_eq!(with[0].environment(), &["HOME=/", "RUST_LOG=debug"]);
+        Ok(())
+    }
+
+    #[test]
+    fn reads_optional_proc_files_only_when_requested() -> R<()> {
+        let temp = TempDir::new("proc-details-test")?;
+        let root = temp.path();
+        std::fs::write(root.join("stat"), "cpu  1 2 3 4\nbtime 1700000000\n")?;
+        write_proc_fixture(
+            root,
+            1,
+            "1 (init) S 0 1 1 0 -1 4194560 0 0 0 0 10 20 0 0 20 0 1 0 500 1000 3 0",
+            "/sbin/init\0",
+        )?;
+        std::fs::write(root.join("1").join("status"), "VmSwap:\t    12 kB\n")?;
+        let mut process_watcher = ProcessWatcher::proc(root);
+        process_watcher.refresh()?;
+        let without = process_watcher.processes();
+        process_watcher.set_details(Details {
+            swap: true,
+            oom_score: true,
+            cgroup: true,
+            ..Details::default()
+        });
+        process_watcher.refresh()?;
+        let with = process_watcher.processes();
+        let details = |process: &Process| (process.swap, process.oom_score, process.cgroup.clone());
+        assert_eq!(details(&without[0]), (0, None, None));
+        assert_eq!(
+            details(&with[0]),
+            (
+                12 * 1024,
+                Some(666),
+                Some("/user.slice/session-2.scope".to_string())
+            )
+        );
+        Ok(())
+    }
 }
@@ -1,40 +1,130 @@
 use crate::R;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Target {
+    Process,
+    Cgroup,
+    Environment,
+    Tty,
+}
+
+const CGROUP_PREFIX: &str = "cgroup:";
+const ENVIRONMENT_PREFIX: &str = "env:";
+const TTY_PREFIX: &str = "tty:";
+
 #[derive(Debug)]
 pub(crate) enum Regex {
-    Regex { regex: regex::Regex },
-    Invalid { regex: String },
+    Regex {
+        target: Target,
+        regex: regex::Regex,
+        source: String,
+        fixed: bool,
+        ignore_case: bool,
+    },
+    // Keeps the flags, so they still apply once the pattern is valid again.
+    Invalid {
+        regex: String,
+        fixed: bool,
+        ignore_case: bool,
+    },
 }
 
 impl Regex {
-    pub(crate) fn empty() -> R<Regex> {
-        Ok(Regex::new(::regex::Regex::new("")?))
+    pub(crate) fn empty(fixed: bool, ignore_case: bool) -> R<Regex> {
+        Regex::parse("", fixed, ignore_case)
     }
 
-    pub(crate) fn new(regex: ::regex::Regex) -> Regex {
-        Regex::Regex { regex }
+    // With `fixed`, the pattern after the target prefix is matched literally.
+    pub(crate) fn parse(source: &str, fixed: bool, ignore_case: bool) -> R<Regex> {
+        let (target, regex) = if let Some(regex) = source.strip_prefix(CGROUP_PREFIX) {
+            (Target::Cgroup, regex)
+        } else if let Some(regex) = source.strip_prefix(ENVIRONMENT_PREFIX) {
+            (Target::Environment, regex)
+        } else if let Some(regex) = source.strip_prefix(TTY_PREFIX) {
+            (Target::Tty, regex)
+        } else {
+            (Target::Process, source)
+        };
+        Ok(Regex::Regex {
+            target,
+            regex: if fixed {
+                regex::RegexBuilder::new(&regex::escape(regex))
+            } else {
+                regex::RegexBuilder::new(regex)
+            }
+            .case_insensitive(ignore_case)
+            .build()?,
+            source: source.to_string(),
+            fixed,
+            ignore_case,
+        })
+    }
+
+    pub(crate) fn target(&self) -> Target {
+        match self {
+            Regex::Regex { target, .. } => *target,
+            Regex::Invalid { .. } => Target::Process,
+        }
     }
 
     pub(crate) fn is_match(&self, s: &str) -> bool {
         match self {
-            Regex::Regex { regex } => regex.is_match(s),
+            Regex::Regex { regex, .. } => regex.is_match(s),
             Regex::Invalid { .. } => false,
         }
     }
 
+    // The pattern without the prefix selecting the target.
+    pub(crate) fn text(&self) -> &str {
+        match self.target() {
+            Target::Cgroup => self.as_str().strip_prefix(CGROUP_PREFIX).unwrap_or(""),
+            Target::Environment => self.as_str().strip_prefix(ENVIRONMENT_PREFIX).unwrap_or(""),
+            Target::Tty => self.as_str().strip_prefix(TTY_PREFIX).unwrap_or(""),
+            Target::Process => self.as_str(),
+        }
+    }
+
     pub(crate) fn as_str(&self) -> &str {
         match self {
-            Regex::Regex { regex } => regex.as_str(),
-            Regex::Invalid { regex } => regex.as_str(),
+            Regex::Regex { source, .. } => source.as_str(),
+            Regex::Invalid { regex, .. } => regex.as_str(),
+        }
+    }
+
+    fn fixed(&self) -> bool {
+        match self {
+            Regex::Regex { fixed, .. } | Regex::Invalid { fixed, .. } => *fixed,
+        }
+    }
+
+    fn ignore_case(&self) -> bool {
+        match self {
+            Regex::Regex { ignore_case, .. } | Regex::Invalid { ignore_case, .. } => *ignore_case,
+        }
+    }
+
+    fn reparse(regex: String, fixed: bool, ignore_case: bool) -> Regex {
+        match Regex::parse(&regex, fixed, ignore_case) {
+            Ok(parsed) => parsed,
+            Err(_) => Regex::Invalid {
+                regex,
+                fixed,
+                ignore_case,
+            },
         }
     }
 
     pub(crate) fn modify(&mut self, f: impl FnOnce(&mut String)) {
         let mut regex: String = self.as_str().to_string();
         f(&mut regex);
-        *self = match regex::Regex::new(&regex) {
-            Ok(regex) => Regex::Regex { regex },
-            Err(_) => Regex::Invalid { regex },
-        }
+        *self = Regex::reparse(regex, self.fixed(), self.ignore_case());
+    }
+
+    pub(crate) fn set_fixed(&mut self, fixed: bool) {
+        *self = Regex::reparse(self.as_str().to_string(), fixed, self.ignore_case());
+    }
+
+    pub(crate) fn set_ignore_case(&mut self, ignore_case: bool) {
+        *self = Regex::reparse(self.as_str().to_string(), self.fixed(), ignore_case);
     }
 }
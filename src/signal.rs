@@ -0,0 +1,135 @@
+use crate::R;
+use nix::sys::signal::Signal;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub(crate) struct SignalSender(SignalSenderInner);
+
+#[derive(Debug)]
+enum SignalSenderInner {
+    Production,
+    #[cfg(test)]
+    TestSender {
+        sent: Vec<(sysinfo::Pid, Signal)>,
+        failing: bool,
+    },
+}
+
+impl SignalSender {
+    pub(crate) fn new() -> SignalSender {
+        SignalSender(SignalSenderInner::Production)
+    }
+
+    pub(crate) fn send(&mut self, pid: sysinfo::Pid, signal: Signal) -> R<()> {
+        let raw_pid = i32::try_from(pid.as_u32()).map_err(|_| PidOutOfRange(pid.as_u32()))?;
+        match self {
+            SignalSender(SignalSenderInner::Production) => {
+                nix::sys::signal::kill(nix::unistd::Pid::from_raw(raw_pid), signal)?;
+            }
+            #[cfg(test)]
+            SignalSender(SignalSenderInner::TestSender { sent, failing }) => {
+                if *failing {
+                    Err(nix::errno::Errno::EPERM)?;
+                }
+                sent.push((pid, signal));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Pids are unsigned in sysinfo, but kill(2) takes signed ones, so pids beyond
+// `i32::MAX` can't be signaled.
+#[derive(Debug)]
+pub(crate) struct PidOutOfRange(u32);
+
+impl fmt::Display for PidOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pid {} is too large to be signaled", self.0)
+    }
+}
+
+impl std::error::Error for PidOutOfRange {}
+
+pub(crate) fn parse_signal(input: &str) -> Result<Signal, String> {
+    let input = input.trim();
+    let input = input.strip_prefix("kill ").unwrap_or(input).trim();
+    if let Ok(number) = input.parse::<i32>() {
+        return Signal::try_from(number).map_err(|_| format!("unknown signal number: {}", number));
+    }
+    let name = input.to_uppercase();
+    let name = if name.starts_with("SIG") {
+        name
+    } else {
+        format!("SIG{}", name)
+    };
+    Signal::from_str(&name).map_err(|_| format!("unknown signal: {}", input))
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    impl SignalSender {
+        pub(crate) fn fake() -> SignalSender {
+            SignalSender(SignalSenderInner::TestSender {
+                sent: Vec::new(),
+                failing: false,
+            })
+        }
+
+        // Fails every send as if the process belonged to another user.
+        pub(crate) fn failing() -> SignalSender {
+            SignalSender(SignalSenderInner::TestSender {
+                sent: Vec::new(),
+                failing: true,
+            })
+        }
+
+        pub(crate) fn sent(&self) -> &[(sysinfo::Pid, Signal)] {
+            match self {
+                SignalSender(SignalSenderInner::TestSender { sent, .. }) => sent,
+                SignalSender(SignalSenderInner::Production) => &[],
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_pids_beyond_the_range_of_kill() {
+        let mut sender = SignalSender::fake();
+        let pid = sysinfo::Pid::from_u32(u32::MAX);
+        let error = sender.send(pid, Signal::SIGTERM).unwrap_err();
+        assert!(error.is::<PidOutOfRange>());
+        assert_eq!(
+            error.to_string(),
+            "pid 4294967295 is too large to be signaled"
+        );
+        assert_eq!(sender.sent(), &[]);
+    }
+
+    #[test]
+    fn parses_signal_names() {
+        assert_eq!(parse_signal("SIGUSR1"), Ok(Signal::SIGUSR1));
+        assert_eq!(parse_signal("usr2"), Ok(Signal::SIGUSR2));
+        assert_eq!(parse_signal("kill SIGHUP"), Ok(Signal::SIGHUP));
+    }
+
+    #[test]
+    fn parses_signal_numbers() {
+        assert_eq!(parse_signal("9"), Ok(Signal::SIGKILL));
+        assert_eq!(parse_signal("kill 15"), Ok(Signal::SIGTERM));
+    }
+
+    #[test]
+    fn rejects_unknown_signals() {
+        assert_eq!(
+            parse_signal("SIGFOO"),
+            Err("unknown signal: SIGFOO".to_string())
+        );
+        assert_eq!(
+            parse_signal("1000"),
+            Err("unknown signal number: 1000".to_string())
+        );
+    }
+}
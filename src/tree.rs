@@ -15,6 +15,57 @@ pub(crate) trait Node {
     fn accumulate_from(&mut self, other: &Self);
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PrefixStyle {
+    pub(crate) branch: &'static str,
+    pub(crate) last_branch: &'static str,
+    pub(crate) with_children: &'static str,
+    pub(crate) without_children: &'static str,
+    pub(crate) continuation: &'static str,
+    pub(crate) blank: &'static str,
+    pub(crate) first_branch: &'static str,
+    pub(crate) with_children_above: &'static str,
+}
+
+impl PrefixStyle {
+    pub(crate) const THIN: PrefixStyle = PrefixStyle {
+        branch: "├─",
+        last_branch: "└─",
+        with_children: "┬ ",
+        without_children: "─ ",
+        continuation: "│ ",
+        blank: "  ",
+        first_branch: "┌─",
+        with_children_above: "┴ ",
+    };
+
+    pub(crate) const ROUNDED: PrefixStyle = PrefixStyle {
+        last_branch: "╰─",
+        first_branch: "╭─",
+        ..PrefixStyle::THIN
+    };
+
+    pub(crate) const THICK: PrefixStyle = PrefixStyle {
+        branch: "┣━",
+        last_branch: "┗━",
+        with_children: "┳ ",
+        without_children: "━ ",
+        continuation: "┃ ",
+        blank: "  ",
+        first_branch: "┏━",
+        with_children_above: "┻ ",
+    };
+
+    // Hints at the depth of a node that isn't shown below its parent.
+    pub(crate) fn indentation(&self, depth: usize) -> String {
+        if depth == 0 {
+            String::new()
+        } else {
+            self.blank.repeat(depth - 1) + self.last_branch + self.without_children
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct Tree<Node> {
     node: Node,
@@ -69,26 +120,58 @@ where
         result
     }
 
+    // Visits the nodes depth first in the order they're rendered, with roots
+    // in the same order as siblings. Callers pair up rendered rows with the
+    // results of `iter_with_depth`, so this has to stay in sync with
+    // `render_forest_prefixes`.
     pub(crate) fn iter(&self) -> impl Iterator<Item = &Node> {
-        struct Iter<'a, Node>(VecDeque<&'a Tree<Node>>);
+        self.iter_with_depth().map(|(_depth, node)| node)
+    }
+
+    pub(crate) fn iter_with_depth(&self) -> impl Iterator<Item = (usize, &Node)> {
+        struct Iter<'a, Node>(VecDeque<(usize, &'a Tree<Node>)>);
 
         impl<'a, Node> Iterator for Iter<'a, Node> {
-            type Item = &'a Node;
+            type Item = (usize, &'a Node);
 
-            fn next(&mut self) -> Option<&'a Node> {
+            fn next(&mut self) -> Option<(usize, &'a Node)> {
                 match self.0.pop_front() {
-                    Some(tree) => {
+                    Some((depth, tree)) => {
                         for child in tree.children.0.iter().rev() {
-                            self.0.push_front(child);
+                            self.0.push_front((depth + 1, child));
                         }
-                        Some(&tree.node)
+                        Some((depth, &tree.node))
                     }
                     None => None,
                 }
             }
         }
 
-        Iter(self.0.iter().rev().collect())
+        Iter(self.0.iter().map(|tree| (0, tree)).collect())
+    }
+
+    pub(crate) fn children_of(&self, id: Node::Id) -> Option<Vec<&Node>> {
+        self.0.iter().find_map(|tree| {
+            if tree.node.id() == id {
+                Some(tree.children.0.iter().map(|child| &child.node).collect())
+            } else {
+                tree.children.children_of(id)
+            }
+        })
+    }
+
+    // The nodes on the way from a root down to the node with `id`, starting
+    // with the root and excluding the node itself.
+    pub(crate) fn ancestors_of(&self, id: Node::Id) -> Option<Vec<&Node>> {
+        self.0.iter().find_map(|tree| {
+            if tree.node.id() == id {
+                Some(Vec::new())
+            } else {
+                let mut ancestors = tree.children.ancestors_of(id)?;
+                ancestors.insert(0, &tree.node);
+                Some(ancestors)
+            }
+        })
     }
 
     pub(crate) fn sort_by<F>(&mut self, compare: &F)
@@ -101,6 +184,55 @@ where
         }
     }
 
+    // All nodes sorted across the whole forest instead of within their
+    // siblings, together with their depth in the tree.
+    pub(crate) fn flatten_sorted<F>(&self, compare: &F) -> Vec<(usize, &Node)>
+    where
+        F: Fn(&Node, &Node) -> Ordering,
+    {
+        let mut nodes: Vec<(usize, &Node)> = self.iter_with_depth().collect();
+        nodes.sort_by(|(_, a), (_, b)| compare(a, b));
+        nodes
+    }
+
+    // Replaces the forest with the tree rooted at `id`, or with nothing if
+    // there's no such node.
+    pub(crate) fn reroot(&mut self, id: Node::Id) {
+        fn find<Node: crate::tree::Node>(forest: Forest<Node>, id: Node::Id) -> Option<Tree<Node>>
+        where
+            Node::Id: Eq + Copy,
+        {
+            for tree in forest.0 {
+                if tree.node.id() == id {
+                    return Some(tree);
+                }
+                if let Some(found) = find(tree.children, id) {
+                    return Some(found);
+                }
+            }
+            None
+        }
+
+        let forest = std::mem::replace(self, Forest(Vec::new()));
+        self.0 = find(forest, id).into_iter().collect();
+    }
+
+    // Keeps the `n` top-level trees that come first according to `compare`,
+    // without changing the order of the ones that are kept.
+    pub(crate) fn keep_first<F>(&mut self, n: usize, compare: &F)
+    where
+        F: Fn(&Node, &Node) -> Ordering,
+    {
+        let mut ranked: Vec<&Tree<Node>> = self.0.iter().collect();
+        ranked.sort_by(|a, b| compare(&a.node, &b.node));
+        let kept: Vec<Node::Id> = ranked
+            .into_iter()
+            .take(n)
+            .map(|tree| tree.node.id())
+            .collect();
+        self.0.retain(|tree| kept.contains(&tree.node.id()));
+    }
+
     fn compute_accumulate(&mut self) {
         for tree in self.0.iter_mut() {
             tree.children.compute_accumulate();
@@ -114,10 +246,22 @@ where
     where
         F: Fn(&Node) -> bool,
     {
-        self.filter_helper(&filter, false);
+        self.filter_helper(&filter, true, false);
     }
 
-    fn filter_helper<F>(&mut self, filter: &F, parent_included: bool) -> bool
+    pub(crate) fn filter_without_subtrees<F>(&mut self, filter: F)
+    where
+        F: Fn(&Node) -> bool,
+    {
+        self.filter_helper(&filter, false, false);
+    }
+
+    fn filter_helper<F>(
+        &mut self,
+        filter: &F,
+        include_subtrees: bool,
+        parent_included: bool,
+    ) -> bool
     where
         F: Fn(&Node) -> bool,
     {
@@ -126,10 +270,11 @@ where
         std::mem::swap(self, &mut old);
         for mut tree in old.0.into_iter() {
             if parent_included || filter(&tree.node) {
-                tree.children.filter_helper(filter, true);
+                tree.children
+                    .filter_helper(filter, include_subtrees, include_subtrees);
                 self.0.push(tree);
                 any_child_included = true
-            } else if tree.children.filter_helper(filter, false) {
+            } else if tree.children.filter_helper(filter, include_subtrees, false) {
                 self.0.push(tree);
                 any_child_included = true;
             }
@@ -137,16 +282,84 @@ where
         any_child_included
     }
 
-    pub(crate) fn render_forest_prefixes(&self) -> Vec<(String, &Node)> {
+    pub(crate) fn flatten(&mut self) {
+        fn collect<Node>(forest: Forest<Node>, nodes: &mut Vec<Tree<Node>>) {
+            for mut tree in forest.0 {
+                let children = std::mem::replace(&mut tree.children, Forest(Vec::new()));
+                nodes.push(tree);
+                collect(children, nodes);
+            }
+        }
+
+        let mut nodes = Vec::new();
+        collect(std::mem::replace(self, Forest(Vec::new())), &mut nodes);
+        self.0 = nodes;
+    }
+
+    pub(crate) fn only_leaves(&mut self) {
+        fn collect_leaves<Node>(forest: Forest<Node>, leaves: &mut Vec<Tree<Node>>) {
+            for tree in forest.0 {
+                if tree.children.0.is_empty() {
+                    leaves.push(tree);
+                } else {
+                    collect_leaves(tree.children, leaves);
+                }
+            }
+        }
+
+        let mut leaves = Vec::new();
+        collect_leaves(std::mem::replace(self, Forest(Vec::new())), &mut leaves);
+        self.0 = leaves;
+    }
+
+    pub(crate) fn remove<F>(&mut self, is_removed: &F)
+    where
+        F: Fn(&Node) -> bool,
+    {
+        self.0.retain(|tree| !is_removed(&tree.node));
+        for tree in self.0.iter_mut() {
+            tree.children.remove(is_removed);
+        }
+    }
+
+    pub(crate) fn collapse<F>(&mut self, is_collapsed: &F)
+    where
+        F: Fn(&Node) -> bool,
+    {
+        for tree in self.0.iter_mut() {
+            if is_collapsed(&tree.node) {
+                tree.children = Forest(Vec::new());
+            } else {
+                tree.children.collapse(is_collapsed);
+            }
+        }
+    }
+
+    pub(crate) fn render_forest_prefixes(&self, style: &PrefixStyle) -> Vec<(String, &Node)> {
         let mut acc = Vec::new();
-        self.render_forest_prefixes_helper(true, &mut Vec::new(), &mut acc);
+        self.render_forest_prefixes_helper(style, true, &mut Vec::new(), &mut acc);
         acc
     }
 
+    pub(crate) fn render_forest_prefixes_inverted(
+        &self,
+        style: &PrefixStyle,
+    ) -> Vec<(String, &Node)> {
+        let style = PrefixStyle {
+            last_branch: style.first_branch,
+            with_children: style.with_children_above,
+            ..style.clone()
+        };
+        let mut result = self.render_forest_prefixes(&style);
+        result.reverse();
+        result
+    }
+
     fn render_forest_prefixes_helper<'a>(
         &'a self,
+        style: &PrefixStyle,
         is_root: bool,
-        prefixes: &mut Vec<&str>,
+        prefixes: &mut Vec<&'static str>,
         acc: &mut Vec<(String, &'a Node)>,
     ) {
         for (i, child) in self.0.iter().enumerate() {
@@ -156,17 +369,29 @@ where
                 line += prefix;
             }
             if !is_root {
-                line += if is_last { "└─" } else { "├─" };
+                line += if is_last {
+                    style.last_branch
+                } else {
+                    style.branch
+                };
                 let has_children = !child.children.0.is_empty();
-                line += if has_children { "┬ " } else { "─ " };
+                line += if has_children {
+                    style.with_children
+                } else {
+                    style.without_children
+                };
             }
             acc.push((line, &child.node));
             if !(is_root) {
-                prefixes.push(if is_last { "  " } else { "│ " });
+                prefixes.push(if is_last {
+                    style.blank
+                } else {
+                    style.continuation
+                });
             }
             child
                 .children
-                .render_forest_prefixes_helper(false, prefixes, acc);
+                .render_forest_prefixes_helper(style, false, prefixes, acc);
             prefixes.pop();
         }
     }
@@ -186,7 +411,7 @@ mod test {
     {
         fn test_format(&self) -> String {
             let table: Vec<String> = self
-                .render_forest_prefixes()
+                .render_forest_prefixes(&PrefixStyle::THIN)
                 .into_iter()
                 .map(|x| format!("{}{}", x.0, x.1))
                 .collect();
@@ -477,6 +702,64 @@ mod test {
                 .unindent()
             );
         }
+
+        fn matched_parent() -> Forest<TestNode> {
+            Forest::new_forest(
+                vec![
+                    TestNode::new(1, None),
+                    TestNode::new(2, Some(1)),
+                    TestNode::new(3, Some(2)),
+                    TestNode::new(4, Some(2)),
+                    TestNode::new(5, Some(1)),
+                ]
+                .into_iter(),
+            )
+        }
+
+        #[test]
+        fn g_includes_subtrees_of_matched_parents() {
+            let mut tree = matched_parent();
+            tree.filter(|node| node.id == 2 || node.id == 4);
+            assert_eq!(
+                tree.test_format(),
+                "
+                    one
+                    └─┬ two
+                      ├── three
+                      └── four
+                "
+                .unindent()
+            );
+        }
+
+        #[test]
+        fn h_can_leave_out_subtrees_of_matched_parents() {
+            let mut tree = matched_parent();
+            tree.filter_without_subtrees(|node| node.id == 2);
+            assert_eq!(
+                tree.test_format(),
+                "
+                    one
+                    └── two
+                "
+                .unindent()
+            );
+        }
+
+        #[test]
+        fn i_keeps_matching_descendants_of_matched_parents() {
+            let mut tree = matched_parent();
+            tree.filter_without_subtrees(|node| node.id == 2 || node.id == 4);
+            assert_eq!(
+                tree.test_format(),
+                "
+                    one
+                    └─┬ two
+                      └── four
+                "
+                .unindent()
+            );
+        }
     }
 
     mod i_accumulation {
@@ -592,6 +875,29 @@ mod test {
         }
     }
 
+    #[test]
+    fn j_collapsing_hides_children() {
+        let mut tree = Forest::new_forest(
+            vec![
+                TestNode::new(1, None),
+                TestNode::new(2, Some(1)),
+                TestNode::new(3, Some(2)),
+                TestNode::new(4, Some(1)),
+            ]
+            .into_iter(),
+        );
+        tree.collapse(&|node| node.id == 2);
+        assert_eq!(
+            tree.test_format(),
+            "
+                one
+                ├── two
+                └── four
+            "
+            .unindent()
+        );
+    }
+
     mod k_iterators {
         use super::*;
         use pretty_assertions::assert_eq;
@@ -613,5 +919,278 @@ mod test {
                 vec![1, 2, 3, 4]
             );
         }
+
+        #[test]
+        fn b_iterates_through_multiple_roots_in_order() {
+            let tree = Forest::new_forest(
+                vec![
+                    TestNode::new(1, None),
+                    TestNode::new(2, Some(1)),
+                    TestNode::new(3, None),
+                    TestNode::new(4, Some(3)),
+                ]
+                .into_iter(),
+            );
+            assert_eq!(
+                tree.iter().map(Node::id).collect::<Vec<usize>>(),
+                vec![1, 2, 3, 4]
+            );
+        }
+
+        #[test]
+        fn c_iterates_with_depth() {
+            let tree = Forest::new_forest(
+                vec![
+                    TestNode::new(1, None),
+                    TestNode::new(2, Some(1)),
+                    TestNode::new(3, Some(2)),
+                    TestNode::new(4, Some(1)),
+                    TestNode::new(5, None),
+                ]
+                .into_iter(),
+            );
+            assert_eq!(
+                tree.iter_with_depth()
+                    .map(|(depth, node)| (depth, node.id()))
+                    .collect::<Vec<(usize, usize)>>(),
+                vec![(0, 1), (1, 2), (2, 3), (1, 4), (0, 5)]
+            );
+        }
+
+        #[test]
+        fn d_finds_the_children_of_a_node() {
+            let tree = Forest::new_forest(
+                vec![
+                    TestNode::new(1, None),
+                    TestNode::new(2, Some(1)),
+                    TestNode::new(3, Some(2)),
+                    TestNode::new(4, Some(2)),
+                    TestNode::new(5, None),
+                ]
+                .into_iter(),
+            );
+            let children = |id| {
+                tree.children_of(id)
+                    .map(|children| children.into_iter().map(Node::id).collect::<Vec<usize>>())
+            };
+            assert_eq!(children(2), Some(vec![3, 4]));
+            assert_eq!(children(5), Some(vec![]));
+            assert_eq!(children(6), None);
+        }
+
+        #[test]
+        fn e_finds_the_ancestors_of_a_node() {
+            let tree = Forest::new_forest(
+                vec![
+                    TestNode::new(1, None),
+                    TestNode::new(2, Some(1)),
+                    TestNode::new(3, Some(2)),
+                    TestNode::new(4, Some(1)),
+                ]
+                .into_iter(),
+            );
+            let ids = |id| {
+                tree.ancestors_of(id)
+                    .map(|ancestors| ancestors.into_iter().map(Node::id).collect::<Vec<_>>())
+            };
+            assert_eq!(ids(3), Some(vec![1, 2]));
+            assert_eq!(ids(4), Some(vec![1]));
+            assert_eq!(ids(1), Some(vec![]));
+            assert_eq!(ids(5), None);
+        }
+
+        #[test]
+        fn f_visits_roots_in_the_order_they_are_rendered() {
+            let mut tree = Forest::new_forest(
+                vec![
+                    TestNode::new(1, None),
+                    TestNode::new(2, Some(1)),
+                    TestNode::new(3, None),
+                    TestNode::new(4, None),
+                    TestNode::new(5, Some(4)),
+                ]
+                .into_iter(),
+            );
+            tree.sort_by(&|a: &TestNode, b: &TestNode| b.id.cmp(&a.id));
+            let rendered = tree
+                .render_forest_prefixes(&PrefixStyle::THIN)
+                .into_iter()
+                .map(|(_, node)| node.id)
+                .collect::<Vec<usize>>();
+            assert_eq!(rendered, vec![4, 5, 3, 1, 2]);
+            assert_eq!(tree.iter().map(Node::id).collect::<Vec<usize>>(), rendered);
+            assert_eq!(
+                tree.iter_with_depth()
+                    .map(|(_, node)| node.id)
+                    .collect::<Vec<usize>>(),
+                rendered
+            );
+        }
+    }
+
+    #[test]
+    fn l_removing_hides_subtrees() {
+        let mut tree = Forest::new_forest(
+            vec![
+                TestNode::new(1, None),
+                TestNode::new(2, Some(1)),
+                TestNode::new(3, Some(2)),
+                TestNode::new(4, Some(1)),
+            ]
+            .into_iter(),
+        );
+        tree.remove(&|node| node.id == 2);
+        assert_eq!(
+            tree.test_format(),
+            "
+                one
+                └── four
+            "
+            .unindent()
+        );
+    }
+
+    #[test]
+    fn m_only_leaves_flattens_the_forest() {
+        let mut tree = Forest::new_forest(
+            vec![
+                TestNode::new(1, None),
+                TestNode::new(2, Some(1)),
+                TestNode::new(3, Some(2)),
+                TestNode::new(4, Some(2)),
+                TestNode::new(5, Some(1)),
+                TestNode::new(6, None),
+            ]
+            .into_iter(),
+        );
+        tree.only_leaves();
+        assert_eq!(
+            tree.test_format(),
+            "
+                three
+                four
+                five
+                six
+            "
+            .unindent()
+        );
+    }
+
+    #[test]
+    fn n_flatten_turns_all_nodes_into_roots() {
+        let mut tree = Forest::new_forest(
+            vec![
+                TestNode::new(1, None),
+                TestNode::new(2, Some(1)),
+                TestNode::new(3, Some(2)),
+                TestNode::new(4, None),
+            ]
+            .into_iter(),
+        );
+        tree.flatten();
+        assert_eq!(
+            tree.test_format(),
+            "
+                one
+                two
+                three
+                four
+            "
+            .unindent()
+        );
+    }
+
+    #[test]
+    fn o_flatten_sorted_sorts_across_siblings() {
+        let tree = Forest::new_forest(
+            vec![
+                TestNode::new(1, None),
+                TestNode::new(2, Some(1)),
+                TestNode::new(4, Some(2)),
+                TestNode::new(3, None),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(
+            tree.flatten_sorted(&|a: &TestNode, b: &TestNode| b.id.cmp(&a.id))
+                .into_iter()
+                .map(|(depth, node)| (depth, node.id))
+                .collect::<Vec<_>>(),
+            vec![(2, 4), (0, 3), (1, 2), (0, 1)]
+        );
+    }
+
+    #[test]
+    fn p_reroot_keeps_only_the_subtree() {
+        let mut tree = Forest::new_forest(
+            vec![
+                TestNode::new(1, None),
+                TestNode::new(2, Some(1)),
+                TestNode::new(3, Some(2)),
+                TestNode::new(4, Some(1)),
+            ]
+            .into_iter(),
+        );
+        tree.reroot(2);
+        assert_eq!(
+            tree.test_format(),
+            "
+                two
+                └── three
+            "
+            .unindent()
+        );
+        tree.reroot(4);
+        assert_eq!(tree.iter().count(), 0);
+    }
+
+    #[test]
+    fn q_keep_first_keeps_the_order() {
+        let mut tree = Forest::new_forest(
+            vec![
+                TestNode::new(1, None),
+                TestNode::new(2, Some(1)),
+                TestNode::new(3, None),
+                TestNode::new(4, None),
+            ]
+            .into_iter(),
+        );
+        tree.keep_first(2, &|a: &TestNode, b: &TestNode| b.id.cmp(&a.id));
+        assert_eq!(
+            tree.test_format(),
+            "
+                three
+                four
+            "
+            .unindent()
+        );
+    }
+
+    #[test]
+    fn r_renders_inverted_trees() {
+        let tree = Forest::new_forest(
+            vec![
+                TestNode::new(1, None),
+                TestNode::new(2, Some(1)),
+                TestNode::new(3, Some(2)),
+                TestNode::new(4, Some(1)),
+            ]
+            .into_iter(),
+        );
+        let table: Vec<String> = tree
+            .render_forest_prefixes_inverted(&PrefixStyle::THIN)
+            .into_iter()
+            .map(|x| format!("{}{}", x.0, x.1))
+            .collect();
+        assert_eq!(
+            format!("{}\n", table.join("\n")),
+            "
+                ┌── four
+                │ ┌── three
+                ├─┴ two
+                one
+            "
+            .unindent()
+        );
     }
 }
@@ -1,31 +1,373 @@
+use crate::column_provider::{ColumnProvider, CpuHistory, CpuPerThread};
+use crate::fuzzy;
+use crate::key_bindings::{self, Action, Mode};
+use crate::preset::{Preset, Presets};
+use crate::process::Accumulation;
+use crate::process::CellFormat;
+use crate::process::Condition;
+use crate::process::ConditionField;
+use crate::process::Details;
+use crate::process::Grouping;
+use crate::process::HeatMap;
+use crate::process::NameSource;
 use crate::process::ProcessWatcher;
 use crate::process::SortBy;
-use crate::regex::Regex;
+use crate::process::SortDirection;
+use crate::process::TableLayout;
+use crate::regex::{Regex, Target};
+use crate::signal::{parse_signal, SignalSender};
 use crate::tree::Forest;
+use crate::tree::PrefixStyle;
 use crate::{
     process::Process,
     tree::Node,
-    tui_app::{self, UpdateResult},
+    tui_app::{self, TuiApp, UpdateResult},
     R,
 };
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-use nix::sys::signal::kill;
+use chrono::Local;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use nix::sys::signal::Signal;
+use num_format::Locale;
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
-    style::Stylize,
-    text::Line,
-    widgets::{List, ListState, Paragraph, StatefulWidget, Widget},
+    style::{Color, Modifier, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Clear, List, ListItem, ListState, Paragraph, StatefulWidget, Widget, Wrap},
 };
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::PathBuf;
+use std::process::Child;
+use std::time::{Duration, Instant};
+
+const WIDE_LAYOUT_MIN_WIDTH: u16 = 120;
+
+// For how many ticks rows stay highlighted after a cpu spike.
+const SPIKE_TICKS: u8 = 2;
+
+// How many processes 'x' shows when --top isn't given.
+const DEFAULT_TOP: usize = 10;
+
+// How long to wait for a terminated process to disappear before giving up.
+const KILL_TIMEOUT: Duration = Duration::from_secs(5);
+
+// With --dim-by-age, processes younger than this (in seconds) are shown in
+// bold and ones older than `OLD_PROCESS_AGE` are dimmed.
+const NEW_PROCESS_AGE: u64 = 60;
+const OLD_PROCESS_AGE: u64 = 60 * 60;
+
+// With --depth-colors, the tree lines of each level get the next one of
+// these colors.
+const DEPTH_COLORS: [Color; 5] = [
+    Color::Blue,
+    Color::Green,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Cyan,
+];
+
+// Everything `TreetopApp` can be configured with, independent of how it's
+// parsed from the command line.
+#[derive(Debug)]
+pub(crate) struct Options {
+    pub(crate) once: bool,
+    pub(crate) wait: Option<u32>,
+    pub(crate) exit_with_command: bool,
+    pub(crate) patterns: Vec<String>,
+    pub(crate) fuzzy: bool,
+    pub(crate) fixed_strings: bool,
+    pub(crate) ignore_case: bool,
+    pub(crate) filter_display: FilterDisplay,
+    pub(crate) no_subtrees: bool,
+    pub(crate) preset: Option<String>,
+    pub(crate) presets_file: Option<PathBuf>,
+    pub(crate) hide_arguments: bool,
+    pub(crate) wrap: bool,
+    pub(crate) selection_marker: String,
+    pub(crate) selection_color: Option<Color>,
+    pub(crate) highlight: Highlight,
+    pub(crate) tooltips: bool,
+    pub(crate) depth_colors: bool,
+    pub(crate) name_source: NameSource,
+    pub(crate) collapse_by_name: bool,
+    pub(crate) inverted: bool,
+    pub(crate) global_sort: bool,
+    pub(crate) leaves: bool,
+    pub(crate) group: bool,
+    pub(crate) group_by_user: bool,
+    pub(crate) top: Option<usize>,
+    pub(crate) read_only: bool,
+    pub(crate) confirm_signals: Vec<Signal>,
+    pub(crate) quick_signals: bool,
+    pub(crate) hide_self: bool,
+    pub(crate) compact: bool,
+    pub(crate) descendants: bool,
+    pub(crate) wide: bool,
+    pub(crate) own_cpu: bool,
+    pub(crate) own_ram: bool,
+    pub(crate) accumulate_collapsed: bool,
+    pub(crate) ram_percent: bool,
+    pub(crate) per_cpu: bool,
+    pub(crate) locale: Locale,
+    pub(crate) dim_by_age: bool,
+    pub(crate) heat_map: bool,
+    pub(crate) cpu_thresholds: [f32; 2],
+    pub(crate) ram_thresholds: [u64; 2],
+    pub(crate) spike_threshold: Option<f32>,
+    pub(crate) settle: Option<usize>,
+    pub(crate) differences: bool,
+    pub(crate) generation: bool,
+    pub(crate) cgroup: bool,
+    pub(crate) tty: bool,
+    pub(crate) oom_score: bool,
+    pub(crate) swap: bool,
+    pub(crate) cpu_per_thread: bool,
+    pub(crate) cpu_history: bool,
+    pub(crate) threads: bool,
+    pub(crate) columns: Vec<SortBy>,
+    pub(crate) status_bar: Option<String>,
+    pub(crate) sort: SortBy,
+    pub(crate) sort_dir: Option<SortDirection>,
+    pub(crate) disk_io: bool,
+    pub(crate) run_time: bool,
+    pub(crate) start_time: bool,
+    pub(crate) tree_style: PrefixStyle,
+    pub(crate) idle_interval: Option<Duration>,
+    pub(crate) freeze_widths: bool,
+    pub(crate) record: Option<PathBuf>,
+    pub(crate) dump_dir: Option<PathBuf>,
+    pub(crate) log: Option<PathBuf>,
+    pub(crate) signal_log: Option<PathBuf>,
+}
 
 #[derive(Debug)]
 pub(crate) struct TreetopApp {
     process_watcher: ProcessWatcher,
     forest: Forest<Process>,
-    pattern: Regex,
+    patterns: Vec<Regex>,
+    search_scope: SearchScope,
+    fuzzy: bool,
+    // Scores of the processes matching the fuzzy patterns, computed once per
+    // update instead of on every comparison while sorting.
+    fuzzy_scores: HashMap<sysinfo::Pid, i64>,
+    fixed_strings: bool,
+    ignore_case: bool,
+    // Parents whose children are all shown regardless of the search
+    // patterns, to see matches in context. Forgotten whenever the patterns
+    // change.
+    revealed_parents: HashSet<sysinfo::Pid>,
+    marked: HashSet<sysinfo::Pid>,
+    // Composed with the filter builder, all of them have to match.
+    conditions: Vec<Condition>,
+    filter_input: String,
     list_state: ListState,
     ui_mode: UiMode,
+    signal_sender: SignalSender,
+    read_only: bool,
+    confirm_signals: Vec<Signal>,
+    quick_signals: bool,
+    signal_input: String,
+    // Only read once presets are used, so that a broken presets file
+    // doesn't get in the way otherwise.
+    presets: Option<Presets>,
+    presets_file: Option<PathBuf>,
+    preset_input: String,
+    current_preset: Option<String>,
+    status_message: Option<String>,
+    pending_kills: HashMap<sysinfo::Pid, Instant>,
+    kill_status: Option<String>,
+    status_bar_format: Option<String>,
     sort_column: SortBy,
+    sort_direction: SortDirection,
+    columns: Vec<SortBy>,
+    collapsed: HashSet<sysinfo::Pid>,
+    collapse_by_name: bool,
+    collapsed_names: HashSet<String>,
+    differences: bool,
+    launch_pids: Option<HashSet<sysinfo::Pid>>,
+    compact: bool,
+    wide: bool,
+    absolute_start_time: bool,
+    prefix_style: PrefixStyle,
+    show_subtrees: bool,
+    filter_display: FilterDisplay,
+    // Matches that `FilterDisplay::Paths` shows collapsed, until all
+    // processes are expanded.
+    auto_collapsed: HashSet<sysinfo::Pid>,
+    expand_matches: bool,
+    own_pid: sysinfo::Pid,
+    hide_self: bool,
+    hide_arguments: bool,
+    wrap: bool,
+    depth_colors: bool,
+    selection_marker: String,
+    selection_color: Option<Color>,
+    highlight: Highlight,
+    tooltips: bool,
+    // Where the mouse cursor was last seen, as column and row.
+    hover: Option<(u16, u16)>,
+    name_source: NameSource,
+    pid_labels: bool,
+    accumulation: Accumulation,
+    // Only collapsed processes show accumulated values, which then cover
+    // their hidden children.
+    accumulate_collapsed: bool,
+    only_leaves: bool,
+    grouping: Option<Grouping>,
+    top: Option<usize>,
+    top_count: usize,
+    pin_selected: bool,
+    inverted: bool,
+    global_sort: bool,
+    show_breakdown: bool,
+    // Whether the arrow keys scroll the breakdown instead of the list.
+    breakdown_focused: bool,
+    breakdown_offset: usize,
+    terminal_width: u16,
+    heat_map: Option<HeatMap>,
+    ram_percent: bool,
+    per_cpu: bool,
+    locale: Locale,
+    dim_by_age: bool,
+    spike_threshold: Option<f32>,
+    previous_cpu: HashMap<sysinfo::Pid, f32>,
+    spikes: HashMap<sysinfo::Pid, u8>,
+    settle: Option<usize>,
+    // How many ticks in a row each process has been seen in, `None` before
+    // the first tick.
+    sightings: Option<HashMap<sysinfo::Pid, usize>>,
+    freeze_widths: bool,
+    idle_tick_length: Option<Duration>,
+    width_adjustments: HashMap<SortBy, isize>,
+    table_layout: Option<TableLayout>,
+    column_providers: Vec<Box<dyn ColumnProvider>>,
+    recording: Option<BufWriter<File>>,
+    dump_dir: PathBuf,
+    log: Option<File>,
+    signal_log: Option<File>,
+    once: bool,
+    wait_for: Option<sysinfo::Pid>,
+    follow: Option<sysinfo::Pid>,
+    followed_command: Option<Child>,
+    exit_with_command: bool,
+}
+
+// Reported as the exit code, so scripts can tell whether what they were
+// looking or waiting for happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ExitStatus {
+    Success,
+    NoMatches,
+    StillRunning,
+    Error,
+}
+
+impl ExitStatus {
+    pub(crate) fn code(self) -> u8 {
+        match self {
+            ExitStatus::Success => 0,
+            ExitStatus::NoMatches => 1,
+            ExitStatus::StillRunning => 2,
+            ExitStatus::Error => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum SearchScope {
+    Name,
+    Cmdline,
+    User,
+    All,
+}
+
+impl SearchScope {
+    fn next(self) -> SearchScope {
+        match self {
+            SearchScope::Name => SearchScope::Cmdline,
+            SearchScope::Cmdline => SearchScope::User,
+            SearchScope::User => SearchScope::All,
+            SearchScope::All => SearchScope::Name,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            SearchScope::Name => "name",
+            SearchScope::Cmdline => "cmdline",
+            SearchScope::User => "user",
+            SearchScope::All => "all",
+        }
+    }
+}
+
+// How much of the tree is shown around processes that match the patterns.
+// With `Paths`, only the paths from the roots to the matches are shown and
+// matches that don't lead to other matches are collapsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FilterDisplay {
+    Subtrees,
+    Paths,
+}
+
+impl FilterDisplay {
+    pub(crate) fn parse(display: &str) -> Result<FilterDisplay, String> {
+        match display {
+            "subtrees" => Ok(FilterDisplay::Subtrees),
+            "paths" => Ok(FilterDisplay::Paths),
+            _ => Err(format!("unknown filter display: {}", display)),
+        }
+    }
+}
+
+// How the highlighted row is set apart from the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Highlight {
+    Marker,
+    Row,
+}
+
+impl Highlight {
+    pub(crate) fn parse(highlight: &str) -> Result<Highlight, String> {
+        match highlight {
+            "marker" => Ok(Highlight::Marker),
+            "row" => Ok(Highlight::Row),
+            _ => Err(format!("unknown highlight: {}", highlight)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SignalRequest {
+    pid: sysinfo::Pid,
+    signal: Signal,
+    wait: bool,
+    // Sent to the highlighted row from normal mode, which is returned to
+    // afterwards.
+    quick: bool,
+}
+
+// A line of --signal-log.
+#[derive(Debug, Serialize)]
+struct SignalLogEntry {
+    pid: u32,
+    signal: &'static str,
+    result: String,
+}
+
+// A filter that's shown in the filter bar and can be removed from there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Filter {
+    Pattern(usize),
+    OnlyLeaves,
+    Top,
+    HideSelf,
+    Condition(usize),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -33,157 +375,1754 @@ enum UiMode {
     Normal,
     EditingPattern,
     ProcessSelected(sysinfo::Pid),
+    EnteringSignal(sysinfo::Pid),
+    ConfirmingSignal(SignalRequest),
+    // Always asked for, since marks can include rows that aren't visible.
+    ConfirmingMarkedSignal(Signal),
+    EnteringPresetName,
+    ConfirmingQuit,
+    EditingFilters,
+    // Picking a field first, then typing the comparison for it.
+    FilterBuilder(Option<ConditionField>),
+}
+
+impl UiMode {
+    fn mode(self) -> Mode {
+        match self {
+            UiMode::Normal => Mode::Normal,
+            UiMode::EditingPattern => Mode::EditingPattern,
+            UiMode::ProcessSelected(_) => Mode::ProcessSelected,
+            UiMode::EnteringSignal(_) => Mode::EnteringSignal,
+            UiMode::ConfirmingSignal(_) | UiMode::ConfirmingMarkedSignal(_) => {
+                Mode::ConfirmingSignal
+            }
+            UiMode::EnteringPresetName => Mode::EnteringPresetName,
+            UiMode::ConfirmingQuit => Mode::ConfirmingQuit,
+            UiMode::EditingFilters => Mode::EditingFilters,
+            UiMode::FilterBuilder(None) => Mode::ChoosingFilterField,
+            UiMode::FilterBuilder(Some(_)) => Mode::EnteringFilterValue,
+        }
+    }
+
+    fn selected_process(self) -> Option<sysinfo::Pid> {
+        match self {
+            UiMode::Normal
+            | UiMode::EditingPattern
+            | UiMode::EnteringPresetName
+            | UiMode::ConfirmingQuit
+            | UiMode::ConfirmingMarkedSignal(_)
+            | UiMode::EditingFilters
+            | UiMode::FilterBuilder(_) => None,
+            UiMode::ProcessSelected(pid) | UiMode::EnteringSignal(pid) => Some(pid),
+            UiMode::ConfirmingSignal(request) => Some(request.pid),
+        }
+    }
 }
 
 impl TreetopApp {
-    pub(crate) fn new(process_watcher: ProcessWatcher, pattern: Option<Regex>) -> R<TreetopApp> {
-        Ok(TreetopApp {
+    pub(crate) fn new(process_watcher: ProcessWatcher, options: &Options) -> R<TreetopApp> {
+        let mut app = TreetopApp {
             process_watcher,
             forest: Forest::empty(),
-            pattern: pattern.unwrap_or(Regex::empty()?),
+            patterns: if options.patterns.is_empty() {
+                vec![Regex::empty(options.fixed_strings, options.ignore_case)?]
+            } else {
+                options
+                    .patterns
+                    .iter()
+                    .map(|pattern| {
+                        Regex::parse(pattern, options.fixed_strings, options.ignore_case)
+                    })
+                    .collect::<R<_>>()?
+            },
+            search_scope: SearchScope::Name,
+            fuzzy: options.fuzzy,
+            fuzzy_scores: HashMap::new(),
+            fixed_strings: options.fixed_strings,
+            ignore_case: options.ignore_case,
+            revealed_parents: HashSet::new(),
+            marked: HashSet::new(),
             list_state: ListState::default().with_selected(Some(0)),
             ui_mode: UiMode::Normal,
-            sort_column: SortBy::default(),
-        })
+            signal_sender: SignalSender::new(),
+            read_only: options.read_only,
+            confirm_signals: options.confirm_signals.clone(),
+            quick_signals: options.quick_signals,
+            signal_input: String::new(),
+            presets: None,
+            presets_file: options.presets_file.clone().or_else(Presets::default_path),
+            preset_input: String::new(),
+            conditions: Vec::new(),
+            filter_input: String::new(),
+            current_preset: None,
+            status_message: None,
+            pending_kills: HashMap::new(),
+            kill_status: None,
+            status_bar_format: options.status_bar.clone(),
+            sort_column: options.sort,
+            sort_direction: options
+                .sort_dir
+                .unwrap_or_else(|| options.sort.default_direction()),
+            columns: if options.columns.is_empty() {
+                SortBy::all()
+                    .filter(|column| match column {
+                        SortBy::Descendants => options.descendants,
+                        SortBy::Threads => options.threads,
+                        SortBy::Swap => options.swap,
+                        SortBy::Generation => options.generation,
+                        SortBy::Cgroup => options.cgroup,
+                        SortBy::Tty => options.tty,
+                        SortBy::Name => false,
+                        SortBy::OomScore => options.oom_score,
+                        SortBy::RunTime => options.run_time || options.start_time,
+                        SortBy::DiskIo => options.disk_io,
+                        _ => true,
+                    })
+                    .collect()
+            } else {
+                options.columns.clone()
+            },
+            collapsed: HashSet::new(),
+            collapse_by_name: options.collapse_by_name,
+            collapsed_names: HashSet::new(),
+            differences: options.differences,
+            launch_pids: None,
+            compact: options.compact,
+            wide: options.wide,
+            absolute_start_time: options.start_time,
+            prefix_style: options.tree_style.clone(),
+            show_subtrees: !options.no_subtrees,
+            filter_display: options.filter_display,
+            auto_collapsed: HashSet::new(),
+            expand_matches: false,
+            own_pid: sysinfo::Pid::from_u32(std::process::id()),
+            hide_self: options.hide_self,
+            hide_arguments: options.hide_arguments,
+            wrap: options.wrap,
+            depth_colors: options.depth_colors,
+            selection_marker: options.selection_marker.clone(),
+            selection_color: options.selection_color,
+            highlight: options.highlight,
+            tooltips: options.tooltips,
+            hover: None,
+            name_source: options.name_source,
+            pid_labels: false,
+            accumulation: Accumulation {
+                cpu: !options.own_cpu,
+                ram: !options.own_ram,
+            },
+            accumulate_collapsed: options.accumulate_collapsed,
+            only_leaves: options.leaves,
+            grouping: if options.group_by_user {
+                Some(Grouping::User)
+            } else {
+                options.group.then_some(Grouping::Executable)
+            },
+            top: options.top,
+            top_count: options.top.unwrap_or(DEFAULT_TOP),
+            pin_selected: false,
+            inverted: options.inverted,
+            global_sort: options.global_sort,
+            show_breakdown: false,
+            breakdown_focused: false,
+            breakdown_offset: 0,
+            terminal_width: 0,
+            heat_map: options.heat_map.then_some(HeatMap {
+                cpu: options.cpu_thresholds,
+                ram_in_mb: options.ram_thresholds,
+            }),
+            ram_percent: options.ram_percent,
+            per_cpu: options.per_cpu,
+            locale: options.locale,
+            dim_by_age: options.dim_by_age,
+            spike_threshold: options.spike_threshold,
+            previous_cpu: HashMap::new(),
+            spikes: HashMap::new(),
+            settle: options.settle,
+            sightings: None,
+            freeze_widths: options.freeze_widths,
+            idle_tick_length: options.idle_interval,
+            width_adjustments: HashMap::new(),
+            table_layout: None,
+            column_providers: {
+                let mut providers: Vec<Box<dyn ColumnProvider>> = Vec::new();
+                if options.cpu_per_thread {
+                    providers.push(Box::new(CpuPerThread));
+                }
+                if options.cpu_history {
+                    providers.push(Box::new(CpuHistory::default()));
+                }
+                providers
+            },
+            recording: options
+                .record
+                .as_ref()
+                .map(|record| File::create(record).map(BufWriter::new))
+                .transpose()?,
+            dump_dir: options
+                .dump_dir
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(".")),
+            log: options.log.as_ref().map(File::create).transpose()?,
+            signal_log: options.signal_log.as_ref().map(File::create).transpose()?,
+            once: options.once,
+            wait_for: options.wait.map(sysinfo::Pid::from_u32),
+            follow: None,
+            followed_command: None,
+            exit_with_command: options.exit_with_command,
+        };
+        if let Some(name) = &options.preset {
+            let preset = app
+                .presets()?
+                .get(name)
+                .ok_or_else(|| format!("unknown preset: {}", name))?
+                .clone();
+            app.apply_preset(name, &preset)?;
+        }
+        Ok(app)
     }
 
-    pub(crate) fn run(self) -> R<()> {
-        tui_app::run_ui(self)
+    fn presets(&mut self) -> R<&mut Presets> {
+        let presets = match self.presets.take() {
+            Some(presets) => presets,
+            None => Presets::load(self.presets_file.as_deref())?,
+        };
+        Ok(self.presets.insert(presets))
     }
 
-    fn update_processes(&mut self) {
-        self.forest = self.process_watcher.get_forest();
-        self.forest
-            .sort_by(&|a, b| Process::compare(a, b, self.sort_column));
-        self.forest.filter(|p| {
-            self.pattern.is_match(&p.name) || self.pattern.is_match(&p.id().to_string())
+    fn current_preset(&self) -> Preset {
+        Preset {
+            patterns: self
+                .patterns
+                .iter()
+                .map(Regex::as_str)
+                .filter(|pattern| !pattern.is_empty())
+                .map(ToString::to_string)
+                .collect(),
+            search_scope: self.search_scope,
+            heat_map: self.heat_map.clone(),
+        }
+    }
+
+    fn apply_preset(&mut self, name: &str, preset: &Preset) -> R<()> {
+        self.patterns = preset
+            .patterns
+            .iter()
+            .map(|pattern| Regex::parse(pattern, self.fixed_strings, self.ignore_case))
+            .collect::<R<_>>()?;
+        if self.patterns.is_empty() {
+            self.patterns
+                .push(Regex::empty(self.fixed_strings, self.ignore_case)?);
+        }
+        self.search_scope = preset.search_scope;
+        self.heat_map = preset.heat_map.clone();
+        self.current_preset = Some(name.to_string());
+        self.revealed_parents.clear();
+        Ok(())
+    }
+
+    // Only shows the command and its descendants, including ones started
+    // later.
+    pub(crate) fn follow(&mut self, command: Child) {
+        self.follow = Some(sysinfo::Pid::from_u32(command.id()));
+        self.followed_command = Some(command);
+    }
+
+    // Reaps the followed command once it exited, so it doesn't linger as a
+    // zombie. Returns whether it exited.
+    fn check_followed_command(&mut self) -> R<bool> {
+        match &mut self.followed_command {
+            Some(command) => match command.try_wait()? {
+                Some(status) => {
+                    self.status_message = Some(format!("command exited: {}", status));
+                    self.followed_command = None;
+                    Ok(true)
+                }
+                None => Ok(false),
+            },
+            None => Ok(false),
+        }
+    }
+
+    pub(crate) fn run(mut self) -> R<ExitStatus> {
+        if self.once {
+            self.tick()?;
+            let width = crossterm::terminal::size().map_or(120, |(width, _)| width);
+            print!("{}", self.render_once(width));
+        } else {
+            tui_app::run_ui(&mut self)?;
+        }
+        if let Some(recording) = &mut self.recording {
+            recording.flush()?;
+        }
+        Ok(self.exit_status())
+    }
+
+    fn render_once(&mut self, width: u16) -> String {
+        // Room for the header, all rows and the status bar.
+        let height = self.forest.iter().count() as u16 + 3;
+        let area = Rect::new(0, 0, width, height);
+        let mut buffer = Buffer::empty(area);
+        self.render(area, &mut buffer);
+        let mut lines: Vec<String> = (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect();
+        lines.pop();
+        while lines.last().is_some_and(String::is_empty) {
+            lines.pop();
+        }
+        lines.into_iter().map(|line| line + "\n").collect()
+    }
+
+    fn is_running(&self, pid: sysinfo::Pid) -> bool {
+        self.process_watcher
+            .processes()
+            .iter()
+            .any(|process| process.id() == pid)
+    }
+
+    pub(crate) fn exit_status(&self) -> ExitStatus {
+        match self.wait_for {
+            Some(pid) if self.is_running(pid) => ExitStatus::StillRunning,
+            Some(_) => ExitStatus::Success,
+            None if self.has_patterns() && self.forest.iter().next().is_none() => {
+                ExitStatus::NoMatches
+            }
+            None => ExitStatus::Success,
+        }
+    }
+
+    fn record(&mut self, processes: &[Process]) -> R<()> {
+        if let Some(recording) = &mut self.recording {
+            serde_json::to_writer(&mut *recording, processes)?;
+            writeln!(recording)?;
+        }
+        Ok(())
+    }
+
+    // Writes the processes that are currently shown in the same format as
+    // `--record`, so the file can be opened again with `--replay`.
+    fn dump_processes(&self) -> R<PathBuf> {
+        let shown: HashSet<sysinfo::Pid> = self.forest.iter().map(Node::id).collect();
+        let processes: Vec<Process> = self
+            .process_watcher
+            .processes()
+            .into_iter()
+            .filter(|process| shown.contains(&process.id()))
+            .collect();
+        let stem = format!("treetop-{}", Local::now().format("%Y-%m-%d-%H%M%S"));
+        // Dumps taken within the same second get numbered instead of
+        // overwriting each other.
+        let mut suffix = 0;
+        let (path, file) = loop {
+            let path = self.dump_dir.join(match suffix {
+                0 => format!("{}.json", stem),
+                suffix => format!("{}-{}.json", stem, suffix),
+            });
+            match File::options().write(true).create_new(true).open(&path) {
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => suffix += 1,
+                file => break (path, file?),
+            }
+        };
+        let mut file = BufWriter::new(file);
+        serde_json::to_writer(&mut file, &processes)?;
+        writeln!(file)?;
+        file.flush()?;
+        Ok(path)
+    }
+
+    fn sort(&self, forest: &mut Forest<Process>) {
+        forest.sort_by(&|a, b| self.compare_rows(a, b));
+    }
+
+    // Compares processes by the values their rows show.
+    fn compare_rows(&self, a: &Process, b: &Process) -> Ordering {
+        a.compare_with(
+            b,
+            self.sort_column,
+            self.sort_direction,
+            [self.shown_accumulation(a), self.shown_accumulation(b)],
+        )
+    }
+
+    fn shown_accumulation(&self, process: &Process) -> Accumulation {
+        if self.accumulate_collapsed && !self.is_collapsed(process) {
+            Accumulation {
+                cpu: false,
+                ram: false,
+            }
+        } else {
+            self.accumulation
+        }
+    }
+
+    fn detect_spikes(&mut self, processes: &[Process]) {
+        let Some(threshold) = self.spike_threshold else {
+            return;
+        };
+        self.spikes.retain(|_, ticks| {
+            *ticks -= 1;
+            *ticks > 0
+        });
+        let cpu: HashMap<sysinfo::Pid, f32> = processes
+            .iter()
+            .map(|process| (process.id(), process.own_cpu()))
+            .collect();
+        for (pid, cpu) in &cpu {
+            if let Some(previous) = self.previous_cpu.get(pid) {
+                if cpu - previous >= threshold {
+                    self.spikes.insert(*pid, SPIKE_TICKS);
+                }
+            }
+        }
+        self.previous_cpu = cpu;
+    }
+
+    fn count_sightings(&mut self, processes: &[Process]) {
+        let Some(ticks) = self.settle else {
+            return;
+        };
+        let sightings = processes
+            .iter()
+            .map(|process| {
+                let sightings = match &self.sightings {
+                    // Processes that were already running when treetop
+                    // started are settled.
+                    None => ticks,
+                    Some(sightings) => sightings.get(&process.id()).copied().unwrap_or(0) + 1,
+                };
+                (process.id(), sightings)
+            })
+            .collect();
+        self.sightings = Some(sightings);
+    }
+
+    fn is_settled(&self, process: &Process) -> bool {
+        match (self.settle, &self.sightings) {
+            (Some(ticks), Some(sightings)) => {
+                sightings.get(&process.id()).copied().unwrap_or(0) >= ticks
+            }
+            _ => true,
+        }
+    }
+
+    fn check_pending_kills(&mut self, processes: &[Process]) {
+        if self.pending_kills.is_empty() {
+            return;
+        }
+        let pids: HashSet<sysinfo::Pid> = processes.iter().map(Node::id).collect();
+        let mut kill_status = None;
+        self.pending_kills.retain(|pid, sent| {
+            if !pids.contains(pid) {
+                kill_status = Some(format!("{} terminated", pid));
+                false
+            } else if sent.elapsed() >= KILL_TIMEOUT {
+                kill_status = Some(format!(
+                    "{} still running after {}s",
+                    pid,
+                    sent.elapsed().as_secs()
+                ));
+                false
+            } else {
+                true
+            }
         });
-        if let UiMode::ProcessSelected(selected) = self.ui_mode {
+        if kill_status.is_some() {
+            self.kill_status = kill_status;
+        }
+    }
+
+    fn refresh(&mut self) -> R<()> {
+        let searches = |target| {
+            self.patterns
+                .iter()
+                .any(|pattern| !pattern.as_str().is_empty() && pattern.target() == target)
+        };
+        let needs = |column| self.columns.contains(&column) || self.sort_column == column;
+        self.process_watcher.set_details(Details {
+            environment: searches(Target::Environment),
+            swap: needs(SortBy::Swap),
+            oom_score: needs(SortBy::OomScore),
+            cgroup: needs(SortBy::Cgroup) || searches(Target::Cgroup),
+            tty: needs(SortBy::Tty) || searches(Target::Tty),
+        });
+        self.process_watcher.refresh()?;
+        let processes = self.process_watcher.processes();
+        if self.differences && self.launch_pids.is_none() {
+            self.launch_pids = Some(processes.iter().map(Node::id).collect());
+        }
+        self.record(&processes)?;
+        self.check_pending_kills(&processes);
+        self.detect_spikes(&processes);
+        self.count_sightings(&processes);
+        for provider in &mut self.column_providers {
+            provider.tick(&processes);
+        }
+        let highlighted = self.highlighted_process();
+        self.update_processes();
+        if let Some(pid) = highlighted {
+            self.highlight_process(pid);
+        }
+        Ok(())
+    }
+
+    // Logging is best effort, failing to write shouldn't take down the ui.
+    fn log(&mut self, message: &str) {
+        if let Some(log) = &mut self.log {
+            let _ = writeln!(log, "{} {}", Local::now().format("%H:%M:%S%.3f"), message);
+            let _ = log.flush();
+        }
+    }
+
+    fn update_processes(&mut self) {
+        let highlighted = self.highlighted_process();
+        let row_count = self.forest.iter().count();
+        let mut forest = self.process_watcher.get_forest();
+        self.fuzzy_scores = if self.fuzzy && self.has_patterns() {
+            forest
+                .iter()
+                .filter_map(|p| Some((p.id(), self.fuzzy_score(p)?)))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        self.sort(&mut forest);
+        if let Some(pid) = self.follow {
+            forest.reroot(pid);
+        }
+        if self.hide_self {
+            forest.remove(&|p| p.id() == self.own_pid);
+        }
+        if self.settle.is_some() {
+            forest.remove(&|p| !self.is_settled(p));
+        }
+        self.auto_collapsed = HashSet::new();
+        if !self.show_subtrees {
+            forest.filter_without_subtrees(|p| self.is_match(p));
+        } else if self.filter_display == FilterDisplay::Paths && !self.expand_matches {
+            forest.filter_without_subtrees(|p| self.is_match(p));
+            self.auto_collapsed = forest
+                .iter()
+                .filter(|p| {
+                    p.includes_descendants()
+                        && forest.children_of(p.id()).is_some_and(|c| c.is_empty())
+                })
+                .map(Node::id)
+                .collect();
+        } else {
+            forest.filter(|p| self.is_match(p));
+        }
+        if self.only_leaves {
+            forest.only_leaves();
+            self.sort(&mut forest);
+        }
+        if self.fuzzy && self.has_patterns() {
+            // Fuzzy matches are ranked, which only makes sense in a flat list.
+            forest.flatten();
+            forest.filter_without_subtrees(|p| self.is_match(p));
+            forest.sort_by(&|a, b| {
+                self.fuzzy_scores
+                    .get(&b.id())
+                    .cmp(&self.fuzzy_scores.get(&a.id()))
+                    .then_with(|| self.compare_rows(a, b))
+            });
+        }
+        if let Some(grouping) = self.grouping {
+            forest = Forest::new_forest(Process::group(grouping, forest.iter()).into_iter());
+            self.sort(&mut forest);
+        }
+        if let Some(top) = self.top {
+            forest.keep_first(top, &|a, b| {
+                Process::compare(
+                    a,
+                    b,
+                    SortBy::Cpu,
+                    SortDirection::Descending,
+                    self.accumulation,
+                )
+            });
+        }
+        forest.collapse(&|p| self.is_collapsed(p));
+        self.forest = forest;
+        // When filters add or remove rows, the selection follows the
+        // highlighted process instead of staying at its index.
+        if let Some(pid) = highlighted {
+            if self.forest.iter().count() != row_count {
+                self.highlight_process(pid);
+            }
+        }
+        if let Some(selected) = self.ui_mode.selected_process() {
             if !self.forest.iter().any(|node| node.id() == selected) {
                 self.ui_mode = UiMode::Normal;
             }
         }
     }
-}
 
-impl tui_app::TuiApp for TreetopApp {
-    fn update(&mut self, event: KeyEvent) -> R<UpdateResult> {
-        match (event.modifiers, self.ui_mode, event.code) {
-            (KeyModifiers::CONTROL, _, KeyCode::Char('c'))
-            | (KeyModifiers::NONE, UiMode::Normal, KeyCode::Char('q')) => {
-                return Ok(UpdateResult::Exit);
+    fn is_collapsed(&self, process: &Process) -> bool {
+        self.collapsed.contains(&process.id())
+            || self.auto_collapsed.contains(&process.id())
+            || (self.collapse_by_name && self.collapsed_names.contains(&process.name))
+    }
+
+    // Splits the usage of a process into its own share and the accumulated
+    // shares of its children. Uses the unfiltered tree, so hidden and
+    // collapsed children are included.
+    fn breakdown(&self, pid: sysinfo::Pid) -> Vec<String> {
+        let mut forest = self.process_watcher.get_forest();
+        self.sort(&mut forest);
+        let (Some(process), Some(children)) = (
+            forest.iter().find(|process| process.id() == pid),
+            forest.children_of(pid),
+        ) else {
+            return Vec::new();
+        };
+        let own = Accumulation {
+            cpu: false,
+            ram: false,
+        };
+        let mut lines = vec![format!(
+            "{} ({}) itself: {}",
+            process.executable(),
+            pid,
+            process.usage(own)
+        )];
+        for child in children {
+            lines.push(format!(
+                "  {} ({}): {}",
+                child.executable(),
+                child.id(),
+                child.usage(Accumulation::default())
+            ));
+        }
+        lines
+    }
+
+    // The indices of the rows showing top-level processes.
+    fn root_rows(&self) -> Vec<usize> {
+        render_rows(
+            &self.forest,
+            &self.prefix_style,
+            self.inverted,
+            self.global_order(),
+        )
+        .into_iter()
+        .enumerate()
+        .filter(|(_, (_, _, depth))| *depth == 0)
+        .map(|(index, _)| index)
+        .collect()
+    }
+
+    fn rows(&self) -> Vec<(String, &Process)> {
+        render_rows(
+            &self.forest,
+            &self.prefix_style,
+            self.inverted,
+            self.global_order(),
+        )
+        .into_iter()
+        .map(|(prefix, process, _)| (prefix, process))
+        .collect()
+    }
+
+    fn global_order(&self) -> Option<impl Fn(&Process, &Process) -> Ordering + '_> {
+        self.global_sort
+            .then_some(|a: &Process, b: &Process| self.compare_rows(a, b))
+    }
+
+    fn set_collapsed(&mut self, collapsed: HashSet<sysinfo::Pid>) {
+        let list = self.rows();
+        let selected = self.highlighted_process();
+        let mut ancestors = Vec::new();
+        if let Some(selected) = selected {
+            ancestors.push(selected);
+            while let Some(parent) = list
+                .iter()
+                .find(|(_, process)| Some(process.id()) == ancestors.last().copied())
+                .and_then(|(_, process)| process.parent())
+            {
+                ancestors.push(parent);
+            }
+        }
+        self.collapsed_names = self
+            .process_watcher
+            .processes()
+            .into_iter()
+            .filter(|process| collapsed.contains(&process.id()))
+            .map(|process| process.name)
+            .collect();
+        self.collapsed = collapsed;
+        self.update_processes();
+        let list = self.rows();
+        if let Some(index) = ancestors
+            .iter()
+            .find_map(|pid| list.iter().position(|(_, process)| process.id() == *pid))
+        {
+            self.list_state.select(Some(index));
+        }
+    }
+
+    fn highlighted_process(&self) -> Option<sysinfo::Pid> {
+        self.list_state
+            .selected()
+            .and_then(|selected| self.rows().get(selected).map(|(_, process)| process.id()))
+    }
+
+    fn highlight_process(&mut self, pid: sysinfo::Pid) {
+        if let Some(index) = self
+            .rows()
+            .iter()
+            .position(|(_, process)| process.id() == pid)
+        {
+            self.list_state.select(Some(index));
+        }
+    }
+
+    fn has_patterns(&self) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| !pattern.as_str().is_empty())
+    }
+
+    // The strings of a process that a pattern is matched against.
+    fn search_fields(&self, pattern: &Regex, process: &Process) -> Vec<String> {
+        let name = || {
+            vec![
+                process.display_name(self.name_source),
+                process.id().to_string(),
+            ]
+        };
+        let cmdline = || vec![process.command_line(self.name_source)];
+        let user = || {
+            process
+                .user()
+                .map(ToString::to_string)
+                .into_iter()
+                .collect()
+        };
+        match (pattern.target(), self.search_scope) {
+            (Target::Cgroup, _) => process
+                .cgroup()
+                .map(ToString::to_string)
+                .into_iter()
+                .collect(),
+            (Target::Environment, _) => process.environment().to_vec(),
+            (Target::Tty, _) => process.tty().map(ToString::to_string).into_iter().collect(),
+            (Target::Process, SearchScope::Name) => name(),
+            (Target::Process, SearchScope::Cmdline) => cmdline(),
+            (Target::Process, SearchScope::User) => user(),
+            (Target::Process, SearchScope::All) => [name(), cmdline(), user()].concat(),
+        }
+    }
+
+    fn is_match(&self, process: &Process) -> bool {
+        if !self
+            .conditions
+            .iter()
+            .all(|condition| condition.matches(process, self.accumulation))
+        {
+            return false;
+        }
+        if process
+            .parent()
+            .is_some_and(|parent| self.revealed_parents.contains(&parent))
+        {
+            return true;
+        }
+        if self.fuzzy {
+            return !self.has_patterns() || self.fuzzy_scores.contains_key(&process.id());
+        }
+        !self.has_patterns()
+            || self
+                .patterns
+                .iter()
+                .filter(|pattern| !pattern.as_str().is_empty())
+                .any(|pattern| {
+                    self.search_fields(pattern, process)
+                        .iter()
+                        .any(|field| pattern.is_match(field))
+                })
+    }
+
+    fn fuzzy_score(&self, process: &Process) -> Option<i64> {
+        self.patterns
+            .iter()
+            .filter(|pattern| !pattern.as_str().is_empty())
+            .flat_map(|pattern| {
+                self.search_fields(pattern, process)
+                    .into_iter()
+                    .filter_map(|field| fuzzy::score(pattern.text(), &field))
+            })
+            .max()
+    }
+
+    fn pattern(&mut self) -> &mut Regex {
+        self.revealed_parents.clear();
+        self.patterns
+            .last_mut()
+            .expect("there's always at least one pattern")
+    }
+
+    fn pattern_mode(&self) -> String {
+        let mut mode = self.search_scope.name().to_string();
+        if self.fixed_strings {
+            mode.push_str(", literal");
+        }
+        mode.push_str(if self.ignore_case {
+            ", Aa off"
+        } else {
+            ", Aa on"
+        });
+        mode
+    }
+
+    fn patterns_string(&self) -> String {
+        self.patterns
+            .iter()
+            .map(Regex::as_str)
+            .filter(|pattern| !pattern.is_empty())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn active_filters(&self) -> Vec<(Filter, String)> {
+        let mut filters: Vec<(Filter, String)> = self
+            .patterns
+            .iter()
+            .enumerate()
+            .filter(|(_, pattern)| !pattern.as_str().is_empty())
+            .map(|(i, pattern)| {
+                let label = match pattern.target() {
+                    Target::Process => self.search_scope.name(),
+                    Target::Cgroup => "cgroup",
+                    Target::Environment => "env",
+                    Target::Tty => "tty",
+                };
+                (Filter::Pattern(i), format!("{}: {}", label, pattern.text()))
+            })
+            .collect();
+        if self.only_leaves {
+            filters.push((Filter::OnlyLeaves, "leaves only".to_string()));
+        }
+        if let Some(top) = self.top {
+            filters.push((Filter::Top, format!("top {}", top)));
+        }
+        if self.hide_self {
+            filters.push((Filter::HideSelf, "without treetop".to_string()));
+        }
+        for (i, condition) in self.conditions.iter().enumerate() {
+            filters.push((Filter::Condition(i), condition.to_string()));
+        }
+        filters
+    }
+
+    fn remove_filter(&mut self, filter: Filter) -> R<()> {
+        match filter {
+            Filter::Pattern(i) => {
+                self.revealed_parents.clear();
+                self.patterns.remove(i);
+                if self.patterns.is_empty() {
+                    self.patterns
+                        .push(Regex::empty(self.fixed_strings, self.ignore_case)?);
+                }
+                self.current_preset = None;
+            }
+            Filter::OnlyLeaves => self.only_leaves = false,
+            Filter::Top => self.top = None,
+            Filter::HideSelf => self.hide_self = false,
+            Filter::Condition(i) => {
+                self.conditions.remove(i);
+                self.revealed_parents.clear();
+            }
+        }
+        Ok(())
+    }
+
+    fn perform(&mut self, action: Action) -> R<()> {
+        if self.read_only && action.sends_signal() {
+            self.status_message = Some("read-only mode".to_string());
+            return Ok(());
+        }
+        // A group row stands for many processes but only carries the pid of
+        // one of them, so signaling it would hit just that one.
+        if self.grouping.is_some() && action.sends_signal() {
+            self.status_message = Some("can't signal grouped processes".to_string());
+            return Ok(());
+        }
+        match (action, self.ui_mode) {
+            (Action::Quit | Action::ConfirmQuit, _) => {}
+            (Action::RequestQuit, _) => {
+                self.ui_mode = UiMode::ConfirmingQuit;
+            }
+            (Action::ClearPattern, _) => {
+                self.patterns.truncate(1);
+                self.pattern().modify(String::clear);
+                self.conditions.clear();
             }
-            (KeyModifiers::NONE, _, KeyCode::Up) => {
+            (
+                Action::ScrollUp | Action::PageUp | Action::ScrollDown | Action::PageDown,
+                UiMode::ProcessSelected(pid),
+            ) if self.show_breakdown && self.breakdown_focused => {
+                let last = self.breakdown(pid).len().saturating_sub(1);
+                self.breakdown_offset = match action {
+                    Action::ScrollUp => self.breakdown_offset.saturating_sub(1),
+                    Action::PageUp => self.breakdown_offset.saturating_sub(20),
+                    Action::ScrollDown => self.breakdown_offset.saturating_add(1),
+                    _ => self.breakdown_offset.saturating_add(20),
+                }
+                .min(last);
+            }
+            (Action::ScrollUp, _) => {
                 self.list_state.select(Some(
                     self.list_state.selected().unwrap_or(0).saturating_sub(1),
                 ));
             }
-            (KeyModifiers::NONE, _, KeyCode::PageUp) => {
+            (Action::PageUp, _) => {
                 self.list_state.select(Some(
                     self.list_state.selected().unwrap_or(0).saturating_sub(20),
                 ));
             }
-            (KeyModifiers::NONE, _, KeyCode::Down) => {
+            (Action::ScrollDown, _) => {
                 self.list_state.select(Some(
                     self.list_state.selected().unwrap_or(0).saturating_add(1),
                 ));
             }
-            (KeyModifiers::NONE, _, KeyCode::PageDown) => {
+            (Action::PageDown, _) => {
                 self.list_state.select(Some(
                     self.list_state.selected().unwrap_or(0).saturating_add(20),
                 ));
             }
-            (KeyModifiers::NONE, UiMode::EditingPattern, KeyCode::Enter) => {
+            (Action::PreviousRoot, _) => {
+                let selected = self.list_state.selected().unwrap_or(0);
+                if let Some(&root) = self.root_rows().iter().rev().find(|&&i| i < selected) {
+                    self.list_state.select(Some(root));
+                }
+            }
+            (Action::NextRoot, _) => {
+                let selected = self.list_state.selected().unwrap_or(0);
+                if let Some(&root) = self.root_rows().iter().find(|&&i| i > selected) {
+                    self.list_state.select(Some(root));
+                }
+            }
+            (Action::StopEditingPattern | Action::BackToNormal, _) => {
+                self.status_message = None;
                 self.ui_mode = UiMode::Normal;
             }
-            (KeyModifiers::NONE, _, KeyCode::Enter) => {
-                if let Some(selected) = self.list_state.selected() {
-                    if let Some(process) = self
-                        .forest
-                        .render_forest_prefixes()
-                        .into_iter()
-                        .nth(selected)
-                    {
-                        self.ui_mode = UiMode::ProcessSelected(process.1.id());
+            (Action::SendTypedSignal, UiMode::EnteringSignal(pid)) => {
+                match parse_signal(&self.signal_input) {
+                    Ok(signal) => {
+                        self.status_message = None;
+                        self.request_signal(SignalRequest {
+                            pid,
+                            signal,
+                            wait: false,
+                            quick: false,
+                        })?;
                     }
+                    Err(error) => self.status_message = Some(error),
                 }
             }
-            (KeyModifiers::NONE, _, KeyCode::Char('/')) => {
+            (Action::SelectProcess, _) => {
+                if let Some(pid) = self.highlighted_process() {
+                    self.ui_mode = UiMode::ProcessSelected(pid);
+                    self.breakdown_focused = false;
+                    self.breakdown_offset = 0;
+                }
+            }
+            (Action::EditPattern, _) => {
+                self.current_preset = None;
                 self.ui_mode = UiMode::EditingPattern;
             }
-            (KeyModifiers::NONE, _, KeyCode::Tab) => {
-                self.sort_column = self.sort_column.next();
+            (Action::DumpProcesses, _) => {
+                self.status_message = Some(match self.dump_processes() {
+                    Ok(path) => format!("wrote {}", path.display()),
+                    Err(error) => format!("writing processes failed: {}", error),
+                });
             }
-
-            // mode specific actions
-            (
-                KeyModifiers::NONE,
-                UiMode::EditingPattern | UiMode::ProcessSelected(_),
-                KeyCode::Esc,
-            ) => {
-                self.ui_mode = UiMode::Normal;
+            (Action::EditFilters, _) => {
+                self.ui_mode = UiMode::EditingFilters;
             }
-            (KeyModifiers::NONE, UiMode::EditingPattern, KeyCode::Char(key)) if key.is_ascii() => {
-                self.pattern.modify(|pattern| pattern.push(key));
+            (Action::RemoveFilter(index), _) => {
+                if let Some(&(filter, _)) = self.active_filters().get(index) {
+                    self.remove_filter(filter)?;
+                }
             }
-            (KeyModifiers::NONE, UiMode::EditingPattern, KeyCode::Backspace) => {
-                self.pattern.modify(|pattern| {
-                    pattern.pop();
-                });
+            (Action::BuildFilter, _) => {
+                self.filter_input.clear();
+                self.status_message = None;
+                self.ui_mode = UiMode::FilterBuilder(None);
             }
-            (KeyModifiers::NONE, UiMode::ProcessSelected(pid), KeyCode::Char('t')) => {
-                kill(
-                    nix::unistd::Pid::from_raw(pid.as_u32().try_into()?),
-                    nix::sys::signal::Signal::SIGTERM,
-                )?;
+            (Action::ChooseFilterField(index), _) => {
+                if let Some(&field) = ConditionField::ALL.get(index) {
+                    self.ui_mode = UiMode::FilterBuilder(Some(field));
+                }
             }
-            (KeyModifiers::NONE, UiMode::ProcessSelected(pid), KeyCode::Char('k')) => {
-                kill(
-                    nix::unistd::Pid::from_raw(pid.as_u32().try_into()?),
-                    nix::sys::signal::Signal::SIGKILL,
-                )?;
+            (Action::AddFilter, UiMode::FilterBuilder(Some(field))) => {
+                match Condition::parse(field, &self.filter_input) {
+                    Ok(condition) => {
+                        self.conditions.push(condition);
+                        self.revealed_parents.clear();
+                        self.status_message = None;
+                        self.ui_mode = UiMode::Normal;
+                    }
+                    Err(error) => self.status_message = Some(error),
+                }
             }
-            _ => {}
-        }
-        self.update_processes();
-        Ok(UpdateResult::Continue)
-    }
+            (Action::AddFilter, _) => {}
+            (Action::NamePreset, _) => {
+                self.preset_input.clear();
+                self.ui_mode = UiMode::EnteringPresetName;
+            }
+            (Action::SavePreset, _) => {
+                let name = self.preset_input.trim().to_string();
+                if name.is_empty() {
+                    self.status_message = Some("empty preset name".to_string());
+                } else {
+                    let preset = self.current_preset();
+                    match self
+                        .presets()
+                        .and_then(|presets| presets.save(&name, preset))
+                    {
+                        Ok(()) => {
+                            self.status_message = None;
+                            self.current_preset = Some(name);
+                            self.ui_mode = UiMode::Normal;
+                        }
+                        Err(error) => self.status_message = Some(error.to_string()),
+                    }
+                }
+            }
+            (Action::NextPreset, _) => {
+                let current = self.current_preset.clone();
+                match self.presets() {
+                    Ok(presets) => {
+                        if let Some((name, preset)) = presets.next(current.as_deref()) {
+                            let (name, preset) = (name.to_string(), preset.clone());
+                            self.apply_preset(&name, &preset)?;
+                        }
+                    }
+                    Err(error) => self.status_message = Some(error.to_string()),
+                }
+            }
+            (Action::NextSortColumn, _) => {
+                let next = match self
+                    .columns
+                    .iter()
+                    .position(|&column| column == self.sort_column)
+                {
+                    Some(index) => (index + 1) % self.columns.len(),
+                    None => 0,
+                };
+                if let Some(&column) = self.columns.get(next) {
+                    self.sort_column = column;
+                    self.sort_direction = column.default_direction();
+                }
+            }
+            (Action::SortByColumn(index), _) => {
+                if let Some(&column) = self.columns.get(index) {
+                    self.sort_column = column;
+                    self.sort_direction = column.default_direction();
+                }
+            }
+            (Action::ReverseSortDirection, _) => {
+                self.sort_direction = self.sort_direction.reverse();
+            }
+            (Action::ShrinkColumn | Action::GrowColumn, _) => {
+                let adjustment = self.width_adjustments.entry(self.sort_column).or_insert(0);
+                *adjustment += if action == Action::GrowColumn { 1 } else { -1 };
+                *adjustment =
+                    (*adjustment).clamp(-TableLayout::MAX_ADJUSTMENT, TableLayout::MAX_ADJUSTMENT);
+                // Widths are only recomputed when needed, e.g. when frozen.
+                self.table_layout = None;
+            }
+            (Action::ToggleCompact, _) => {
+                self.compact = !self.compact;
+            }
+            (Action::ToggleStartTimeFormat, _) => {
+                self.absolute_start_time = !self.absolute_start_time;
+            }
+            (Action::ToggleSelf, _) => {
+                self.hide_self = !self.hide_self;
+            }
+            (Action::ToggleFixedStrings, _) => {
+                self.fixed_strings = !self.fixed_strings;
+                for pattern in &mut self.patterns {
+                    pattern.set_fixed(self.fixed_strings);
+                }
+                self.revealed_parents.clear();
+            }
+            (Action::ToggleIgnoreCase, _) => {
+                self.ignore_case = !self.ignore_case;
+                for pattern in &mut self.patterns {
+                    pattern.set_ignore_case(self.ignore_case);
+                }
+                self.revealed_parents.clear();
+            }
+            (Action::NextSearchScope, _) => {
+                self.search_scope = self.search_scope.next();
+                self.revealed_parents.clear();
+            }
+            (Action::ToggleArguments, _) => {
+                self.hide_arguments = !self.hide_arguments;
+            }
+            (Action::ToggleWrap, _) => {
+                self.wrap = !self.wrap;
+            }
+            (Action::ToggleDepthColors, _) => {
+                self.depth_colors = !self.depth_colors;
+            }
+            (Action::NextNameSource, _) => {
+                self.name_source = self.name_source.next();
+            }
+            (Action::TogglePidLabels, _) => {
+                self.pid_labels = !self.pid_labels;
+            }
+            (Action::ToggleOnlyLeaves, _) => {
+                self.only_leaves = !self.only_leaves;
+            }
+            (Action::NextGrouping, _) => {
+                self.grouping = Grouping::next(self.grouping);
+            }
+            (Action::ToggleTop, _) => {
+                self.top = match self.top {
+                    Some(_) => None,
+                    None => Some(self.top_count),
+                };
+            }
+            (Action::ToggleRamPercent, _) => {
+                self.ram_percent = !self.ram_percent;
+            }
+            (Action::TogglePerCpu, _) => {
+                self.per_cpu = !self.per_cpu;
+            }
+            (Action::TogglePinSelected, _) => {
+                self.pin_selected = !self.pin_selected;
+            }
+            (Action::ToggleInverted, _) => {
+                let highlighted = self.highlighted_process();
+                self.inverted = !self.inverted;
+                if let Some(pid) = highlighted {
+                    self.highlight_process(pid);
+                }
+            }
+            (Action::ToggleGlobalSort, _) => {
+                let highlighted = self.highlighted_process();
+                self.global_sort = !self.global_sort;
+                if let Some(pid) = highlighted {
+                    self.highlight_process(pid);
+                }
+            }
+            (Action::CollapseAll, _) => {
+                let collapsed = self
+                    .process_watcher
+                    .get_forest()
+                    .iter()
+                    .filter(|process| process.includes_descendants())
+                    .map(Node::id)
+                    .collect();
+                self.set_collapsed(collapsed);
+                self.expand_matches = false;
+            }
+            (Action::ExpandAll, _) => {
+                self.set_collapsed(HashSet::new());
+                self.expand_matches = true;
+            }
+            (Action::CancelSignal, UiMode::EnteringSignal(pid)) => {
+                self.status_message = None;
+                self.ui_mode = UiMode::ProcessSelected(pid);
+            }
+            (Action::CancelSignal, UiMode::ConfirmingSignal(request)) => {
+                self.ui_mode = if request.quick {
+                    UiMode::Normal
+                } else {
+                    UiMode::ProcessSelected(request.pid)
+                };
+            }
+            (Action::ConfirmSignal, UiMode::ConfirmingSignal(request)) => {
+                self.send_signal(request)?;
+            }
+            (Action::SignalMarked, _) => {
+                if self.marked.is_empty() {
+                    self.status_message = Some("no marked processes".to_string());
+                } else {
+                    self.ui_mode = UiMode::ConfirmingMarkedSignal(Signal::SIGTERM);
+                }
+            }
+            (Action::CancelSignal, UiMode::ConfirmingMarkedSignal(_)) => {
+                self.ui_mode = UiMode::Normal;
+            }
+            (Action::ConfirmSignal, UiMode::ConfirmingMarkedSignal(signal)) => {
+                self.signal_marked(signal)?;
+            }
+            (Action::ToggleMark, _) => {
+                if let Some(pid) = self.highlighted_process() {
+                    if !self.marked.remove(&pid) {
+                        self.marked.insert(pid);
+                    }
+                }
+            }
+            (Action::InvertMarks, _) => {
+                // Marks of rows that are currently hidden stay as they are.
+                for process in self.forest.iter() {
+                    if !self.marked.remove(&process.id()) {
+                        self.marked.insert(process.id());
+                    }
+                }
+            }
+            (Action::ToggleSiblings, _) => {
+                let pid = self
+                    .ui_mode
+                    .selected_process()
+                    .or_else(|| self.highlighted_process());
+                let parent = pid.and_then(|pid| {
+                    self.forest
+                        .iter()
+                        .find(|process| process.id() == pid)
+                        .and_then(Node::parent)
+                });
+                if let (Some(pid), Some(parent)) = (pid, parent) {
+                    if !self.revealed_parents.remove(&parent) {
+                        self.revealed_parents.insert(parent);
+                    }
+                    self.update_processes();
+                    self.highlight_process(pid);
+                }
+            }
+            (Action::ToggleBreakdown, _) => {
+                self.show_breakdown = !self.show_breakdown;
+                self.breakdown_focused = false;
+                self.breakdown_offset = 0;
+            }
+            (Action::FocusBreakdown, _) => {
+                self.breakdown_focused = self.show_breakdown && !self.breakdown_focused;
+            }
+            (Action::SendSigterm, UiMode::ProcessSelected(pid)) => {
+                self.request_signal(SignalRequest {
+                    pid,
+                    signal: Signal::SIGTERM,
+                    wait: false,
+                    quick: false,
+                })?;
+            }
+            (Action::TerminateAndWait, UiMode::ProcessSelected(pid)) => {
+                self.request_signal(SignalRequest {
+                    pid,
+                    signal: Signal::SIGTERM,
+                    wait: true,
+                    quick: false,
+                })?;
+            }
+            (Action::SendSigkill, UiMode::ProcessSelected(pid)) => {
+                self.request_signal(SignalRequest {
+                    pid,
+                    signal: Signal::SIGKILL,
+                    wait: false,
+                    quick: false,
+                })?;
+            }
+            (Action::SendSigusr1 | Action::SendSigusr2, UiMode::ProcessSelected(pid)) => {
+                self.request_signal(SignalRequest {
+                    pid,
+                    signal: if action == Action::SendSigusr1 {
+                        Signal::SIGUSR1
+                    } else {
+                        Signal::SIGUSR2
+                    },
+                    wait: false,
+                    quick: false,
+                })?;
+            }
+            (Action::SendSigterm | Action::SendSigkill, UiMode::Normal) if self.quick_signals => {
+                if let Some(pid) = self.highlighted_process() {
+                    // Without an explicit selection it's easy to hit the
+                    // wrong row, so quick signals are always confirmed.
+                    self.ui_mode = UiMode::ConfirmingSignal(SignalRequest {
+                        pid,
+                        signal: if action == Action::SendSigterm {
+                            Signal::SIGTERM
+                        } else {
+                            Signal::SIGKILL
+                        },
+                        wait: false,
+                        quick: true,
+                    });
+                }
+            }
+            (Action::TypeSignal, UiMode::ProcessSelected(pid)) => {
+                self.signal_input = "kill ".to_string();
+                self.ui_mode = UiMode::EnteringSignal(pid);
+            }
+            (
+                Action::SendTypedSignal
+                | Action::CancelSignal
+                | Action::ConfirmSignal
+                | Action::SendSigterm
+                | Action::TerminateAndWait
+                | Action::SendSigkill
+                | Action::SendSigusr1
+                | Action::SendSigusr2
+                | Action::TypeSignal,
+                _,
+            ) => {}
+        }
+        Ok(())
+    }
+
+    fn request_signal(&mut self, request: SignalRequest) -> R<()> {
+        if self.confirm_signals.contains(&request.signal) {
+            self.ui_mode = UiMode::ConfirmingSignal(request);
+            Ok(())
+        } else {
+            self.send_signal(request)
+        }
+    }
+
+    // Sends a signal and writes it to --signal-log. Failing to send is
+    // returned in the inner result, failing to log in the outer one.
+    fn send_and_log(&mut self, pid: sysinfo::Pid, signal: Signal) -> R<R<()>> {
+        let result = self.signal_sender.send(pid, signal);
+        if let Some(signal_log) = &mut self.signal_log {
+            let entry = SignalLogEntry {
+                pid: pid.as_u32(),
+                signal: signal.as_str(),
+                result: match &result {
+                    Ok(()) => "ok".to_string(),
+                    Err(error) => error.to_string(),
+                },
+            };
+            serde_json::to_writer(&mut *signal_log, &entry)?;
+            writeln!(signal_log)?;
+        }
+        Ok(result)
+    }
+
+    fn signal_marked(&mut self, signal: Signal) -> R<()> {
+        let mut marked = self.marked.iter().copied().collect::<Vec<_>>();
+        marked.sort();
+        let mut failed = 0;
+        for pid in &marked {
+            // Marked processes may be gone by now, which shouldn't stop
+            // the others from being signaled.
+            if self.send_and_log(*pid, signal)?.is_err() {
+                failed += 1;
+            }
+        }
+        self.status_message = Some(if failed == 0 {
+            format!("sent {} to {} processes", signal, marked.len())
+        } else {
+            format!(
+                "sent {} to {} of {} processes",
+                signal,
+                marked.len() - failed,
+                marked.len()
+            )
+        });
+        self.marked.clear();
+        self.ui_mode = UiMode::Normal;
+        Ok(())
+    }
+
+    fn send_signal(&mut self, request: SignalRequest) -> R<()> {
+        match self.send_and_log(request.pid, request.signal)? {
+            Ok(()) => {
+                if request.wait {
+                    self.pending_kills.insert(request.pid, Instant::now());
+                    self.kill_status = Some(format!("waiting for {} to terminate", request.pid));
+                }
+            }
+            // Processes can exit or belong to other users, which isn't worth
+            // tearing down the ui for, so it's only reported.
+            Err(error) => {
+                self.status_message = Some(format!("can't send {}: {}", request.signal, error));
+            }
+        }
+        self.ui_mode = if request.quick {
+            UiMode::Normal
+        } else {
+            UiMode::ProcessSelected(request.pid)
+        };
+        Ok(())
+    }
+
+    fn type_text(&mut self, event: KeyEvent) {
+        match (event.modifiers, self.ui_mode, event.code) {
+            (
+                KeyModifiers::NONE | KeyModifiers::SHIFT,
+                UiMode::EnteringSignal(_),
+                KeyCode::Char(key),
+            ) => {
+                self.signal_input.push(key);
+            }
+            (KeyModifiers::NONE, UiMode::EnteringSignal(_), KeyCode::Backspace) => {
+                self.signal_input.pop();
+            }
+            (
+                KeyModifiers::NONE | KeyModifiers::SHIFT,
+                UiMode::EnteringPresetName,
+                KeyCode::Char(key),
+            ) => {
+                self.preset_input.push(key);
+            }
+            (KeyModifiers::NONE, UiMode::EnteringPresetName, KeyCode::Backspace) => {
+                self.preset_input.pop();
+            }
+            (
+                KeyModifiers::NONE | KeyModifiers::SHIFT,
+                UiMode::FilterBuilder(Some(_)),
+                KeyCode::Char(key),
+            ) => {
+                self.filter_input.push(key);
+            }
+            (KeyModifiers::NONE, UiMode::FilterBuilder(Some(_)), KeyCode::Backspace) => {
+                self.filter_input.pop();
+            }
+            (KeyModifiers::NONE, UiMode::EditingPattern, KeyCode::Char(key)) if key.is_ascii() => {
+                self.pattern().modify(|pattern| pattern.push(key));
+            }
+            (KeyModifiers::NONE, UiMode::EditingPattern, KeyCode::Backspace) => {
+                self.pattern().modify(|pattern| {
+                    pattern.pop();
+                });
+            }
+            // Quitting defaults to no, so any other key cancels.
+            (_, UiMode::ConfirmingQuit, _) => {
+                self.ui_mode = UiMode::Normal;
+            }
+            _ => {}
+        }
+    }
+
+    fn table_layout(&mut self) -> &TableLayout {
+        let format = CellFormat {
+            compact: self.compact,
+            wide: self.wide && self.terminal_width >= WIDE_LAYOUT_MIN_WIDTH,
+            absolute_start_time: self.absolute_start_time,
+            accumulation: self.accumulation,
+            total_memory: if self.ram_percent {
+                self.process_watcher.total_memory()
+            } else {
+                None
+            },
+            locale: self.locale,
+            cpu_count: if self.per_cpu {
+                self.process_watcher.cpu_count()
+            } else {
+                None
+            },
+        };
+        match &self.table_layout {
+            Some(table_layout)
+                if self.freeze_widths && table_layout.is_for(&self.columns, format) => {}
+            _ => {
+                self.table_layout = Some(TableLayout::new(
+                    &self.columns,
+                    format,
+                    self.forest.iter_with_depth(),
+                    &self.width_adjustments,
+                    &self.column_providers,
+                ));
+            }
+        }
+        self.table_layout.as_ref().unwrap()
+    }
+}
+
+impl tui_app::TuiApp for TreetopApp {
+    fn update(&mut self, event: KeyEvent) -> R<UpdateResult> {
+        let action = key_bindings::lookup(self.ui_mode.mode(), event);
+        self.log(&format!(
+            "key {:?} {:?} in {:?}: {:?}",
+            event.modifiers, event.code, self.ui_mode, action
+        ));
+        match action {
+            Some(Action::Quit | Action::ConfirmQuit) => return Ok(UpdateResult::Exit),
+            Some(Action::RequestQuit) if self.marked.is_empty() => return Ok(UpdateResult::Exit),
+            Some(action) => {
+                if let Err(error) = self.perform(action) {
+                    self.log(&format!("{:?} failed: {}", action, error));
+                    return Err(error);
+                }
+            }
+            None => self.type_text(event),
+        }
+        self.update_processes();
+        Ok(UpdateResult::Continue)
+    }
+
+    fn mouse(&mut self, event: MouseEvent) {
+        if self.tooltips && event.kind == MouseEventKind::Moved {
+            self.hover = Some((event.column, event.row));
+        }
+        if event.kind == MouseEventKind::Down(MouseButton::Left) && event.row == 0 {
+            if let Some(column) = self.table_layout().column_at(event.column) {
+                self.sort_column = column;
+                self.sort_direction = column.default_direction();
+                self.update_processes();
+            }
+        }
+    }
+
+    fn idle_tick_length(&self) -> Option<Duration> {
+        self.idle_tick_length
+    }
 
     fn render(&mut self, area: Rect, buffer: &mut Buffer) {
-        let header_height = Process::render_header(area, self.sort_column, buffer);
-        let list_rect = Rect {
+        self.terminal_width = area.width;
+        let table_layout = self.table_layout().clone();
+        let header_height = Process::render_header(
+            area,
+            self.sort_column,
+            &table_layout,
+            &self.column_providers,
+            buffer,
+        );
+        let breakdown = match self.ui_mode {
+            UiMode::ProcessSelected(pid) if self.show_breakdown => self.breakdown(pid),
+            _ => Vec::new(),
+        };
+        let list_height = area.height.saturating_sub(header_height + 1);
+        let breakdown_height = (breakdown.len() as u16).min(list_height / 2);
+        let filter_bar_height = u16::from(matches!(
+            self.ui_mode,
+            UiMode::EditingFilters | UiMode::FilterBuilder(None)
+        ))
+        .min(list_height - breakdown_height);
+        let mut list_rect = Rect {
             x: area.x,
             y: area.y + header_height,
             width: area.width,
-            height: area.height - header_height - 1,
+            height: list_height - breakdown_height - filter_bar_height,
+        };
+        if filter_bar_height > 0 {
+            let mut filter_bar = Line::default();
+            let labels = match self.ui_mode {
+                UiMode::FilterBuilder(_) => ConditionField::ALL
+                    .iter()
+                    .map(|field| field.name().to_string())
+                    .collect(),
+                _ => self
+                    .active_filters()
+                    .into_iter()
+                    .map(|(_, label)| label)
+                    .collect::<Vec<_>>(),
+            };
+            for (i, label) in labels.into_iter().enumerate() {
+                if i > 0 {
+                    filter_bar.push_span(" ");
+                }
+                filter_bar.push_span(format!(" {}: {} ", i + 1, label).reversed().yellow());
+            }
+            buffer.set_line(
+                area.x,
+                list_rect.y + list_rect.height,
+                &filter_bar,
+                area.width,
+            );
+        }
+        if breakdown_height > 0 {
+            self.breakdown_offset = self
+                .breakdown_offset
+                .min(breakdown.len() - usize::from(breakdown_height));
+            let breakdown =
+                Paragraph::new(breakdown.into_iter().map(Line::from).collect::<Vec<_>>())
+                    .scroll((self.breakdown_offset as u16, 0));
+            let breakdown = if self.breakdown_focused {
+                breakdown
+            } else {
+                breakdown.dark_gray()
+            };
+            breakdown.render(
+                Rect {
+                    y: list_rect.y + list_rect.height,
+                    height: breakdown_height,
+                    ..list_rect
+                },
+                buffer,
+            );
+        }
+        let list = render_rows(
+            &self.forest,
+            &self.prefix_style,
+            self.inverted,
+            self.global_order(),
+        );
+        normalize_list_state(&mut self.list_state, &list, &list_rect, self.pin_selected);
+        let own_layout = table_layout.with_accumulation(Accumulation {
+            cpu: false,
+            ram: false,
+        });
+        scroll_to_selected(&mut self.list_state, list_rect.height);
+        let top_row_ancestors = |list_state: &ListState| {
+            list.get(list_state.offset()).and_then(|x| {
+                self.forest
+                    .ancestors_of(x.1.id())
+                    .filter(|ancestors| !ancestors.is_empty())
+            })
         };
-        let list = self.forest.render_forest_prefixes();
-        normalize_list_state(&mut self.list_state, &list, &list_rect);
+        // Inverted and globally sorted rows aren't shown below their
+        // ancestors, so there's nothing to keep track of.
+        let nested = !self.inverted && self.global_order().is_none();
+        if nested && list_rect.height > 1 && top_row_ancestors(&self.list_state).is_some() {
+            list_rect.y += 1;
+            list_rect.height -= 1;
+            scroll_to_selected(&mut self.list_state, list_rect.height);
+            let breadcrumb = top_row_ancestors(&self.list_state)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|process| process.display_name(self.name_source))
+                .collect::<Vec<_>>()
+                .join(" › ");
+            buffer.set_line(
+                area.x,
+                list_rect.y - 1,
+                &Line::from(breadcrumb.dark_gray()),
+                area.width,
+            );
+        }
         let tree_lines = list.iter().enumerate().map(|(i, x)| {
             let mut line = Line::default();
-            line.push_span(format!("{} ", x.1.table_data()));
+            let layout = if self.accumulate_collapsed && !self.is_collapsed(x.1) {
+                &own_layout
+            } else {
+                &table_layout
+            };
+            for span in
+                x.1.table_data(layout, &self.column_providers, self.heat_map.as_ref(), x.2)
+            {
+                line.push_span(span);
+            }
+            line.push_span(if x.1.includes_descendants() {
+                "+".dark_gray()
+            } else {
+                " ".into()
+            });
             line.push_span("┃".dark_gray());
-            line.push_span(if self.list_state.selected() == Some(i) {
-                " ▶ "
+            line.push_span(if self.marked.contains(&x.1.id()) {
+                "*"
+            } else {
+                " "
+            });
+            let marker = if self.list_state.selected() == Some(i) {
+                Span::from(format!("{} ", self.selection_marker))
             } else {
-                "   "
+                Span::from(" ".repeat(Span::from(self.selection_marker.as_str()).width() + 1))
+            };
+            line.push_span(match self.selection_color {
+                Some(color) => marker.fg(color),
+                None => marker,
             });
-            line.push_span(x.0.as_str().blue());
-            line.push_span(if self.ui_mode == UiMode::ProcessSelected(x.1.id()) {
-                x.1.to_string().reversed().red()
+            if self.depth_colors {
+                for span in depth_colored(&x.0) {
+                    line.push_span(span);
+                }
+            } else {
+                line.push_span(x.0.as_str().blue());
+            }
+            let command_column = line.width();
+            let command = if self.pid_labels {
+                x.1.id().to_string()
+            } else if x.1.group_size() > 0 {
+                format!(
+                    "{} ({} {})",
+                    x.1.display_name(self.name_source),
+                    x.1.group_size(),
+                    if x.1.group_size() == 1 {
+                        "process"
+                    } else {
+                        "processes"
+                    }
+                )
+            } else if self.hide_arguments {
+                x.1.display_name(self.name_source)
+            } else {
+                x.1.command_line(self.name_source)
+            };
+            // Ratatui's list scrolls by whole items, so the continuation lines
+            // are kept in view together with their row.
+            let continuation = if self.wrap && self.list_state.selected() == Some(i) {
+                wrap_command(&command, command_column, usize::from(area.width))
+            } else {
+                Vec::new()
+            };
+            let command = if self.ui_mode.selected_process() == Some(x.1.id()) {
+                command.reversed().red()
             } else {
-                x.1.to_string().not_reversed()
+                command.not_reversed()
+            };
+            line.push_span(if self.dim_by_age {
+                command.add_modifier(age_modifier(x.1.run_time()))
+            } else {
+                command
             });
-            line
+            if let Some(launch_pids) = &self.launch_pids {
+                if !launch_pids.contains(&x.1.id()) {
+                    line.push_span(" NEW".green());
+                }
+            }
+            let mut line = match self.spikes.get(&x.1.id()) {
+                Some(&SPIKE_TICKS) => line.on_yellow(),
+                Some(_) => line.on_dark_gray(),
+                None => line,
+            };
+            let highlighted =
+                self.highlight == Highlight::Row && self.list_state.selected() == Some(i);
+            if highlighted {
+                // Spans can unset the item's style, so they're reversed one
+                // by one.
+                for span in line.spans.iter_mut() {
+                    span.style = span.style.add_modifier(Modifier::REVERSED);
+                }
+            }
+            let item = ListItem::new(
+                std::iter::once(line)
+                    .chain(continuation.into_iter().map(Line::from))
+                    .collect::<Vec<_>>(),
+            );
+            if highlighted {
+                item.reversed()
+            } else {
+                item
+            }
         });
+        let tree_lines = tree_lines.collect::<Vec<_>>();
+        let heights = tree_lines.iter().map(ListItem::height).collect::<Vec<_>>();
         StatefulWidget::render(
             List::new(tree_lines),
             list_rect,
             buffer,
             &mut self.list_state,
         );
+        let tooltip = self.hover.and_then(|(column, row)| {
+            if row < list_rect.y || row >= list_rect.y + list_rect.height {
+                return None;
+            }
+            let index = row_at(
+                &heights,
+                self.list_state.offset(),
+                usize::from(row - list_rect.y),
+            )?;
+            Some((column, row, list[index].1.command_line(self.name_source)))
+        });
+        if list.is_empty() {
+            Paragraph::new("no matching processes")
+                .centered()
+                .dark_gray()
+                .render(
+                    Rect {
+                        y: list_rect.y + list_rect.height / 2,
+                        height: 1,
+                        ..list_rect
+                    },
+                    buffer,
+                );
+        }
         {
-            let status_bar = match self.ui_mode {
+            let commands = match self.ui_mode {
                 UiMode::Normal => {
                     let mut commands = vec![
                         "Ctrl+C: Quit".to_string(),
@@ -191,8 +2130,28 @@ impl tui_app::TuiApp for TreetopApp {
                         "ENTER: select process".to_string(),
                         "/: filter processes".to_string(),
                     ];
-                    if !self.pattern.as_str().is_empty() {
-                        commands.push(format!("search pattern: {}", self.pattern.as_str()));
+                    if let Some(preset) = &self.current_preset {
+                        commands.push(format!("preset: {}", preset));
+                    }
+                    if !self.conditions.is_empty() {
+                        commands.push(format!(
+                            "filters: {}",
+                            self.conditions
+                                .iter()
+                                .map(Condition::to_string)
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ));
+                    }
+                    if let Some(status_message) = &self.status_message {
+                        commands.push(status_message.clone());
+                    }
+                    if !self.patterns_string().is_empty() {
+                        commands.push(format!(
+                            "search pattern ({}): {}",
+                            self.pattern_mode(),
+                            self.patterns_string()
+                        ));
                     }
                     commands.join(" | ")
                 }
@@ -201,241 +2160,3567 @@ impl tui_app::TuiApp for TreetopApp {
                     "↑↓ : scroll",
                     "ENTER: select process",
                     "ESC: exit search mode",
-                    &format!("type search pattern: {}▌", self.pattern.as_str()),
+                    "Ctrl+S: search scope",
+                    "Ctrl+A: case",
+                    &format!(
+                        "type search pattern ({}): {}▌",
+                        self.pattern_mode(),
+                        self.patterns
+                            .iter()
+                            .map(Regex::as_str)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
                 ]
                 .join(" | "),
                 UiMode::ProcessSelected(_pid) => {
-                    let mut commands = vec![
-                        "Ctrl+C: Quit".to_string(),
-                        "↑↓ : scroll".to_string(),
-                        "t: SIGTERM process".to_string(),
-                        "k: SIGKILL process".to_string(),
+                    let mut commands = vec!["Ctrl+C: Quit".to_string(), "↑↓ : scroll".to_string()];
+                    if !self.read_only && self.grouping.is_none() {
+                        commands.extend([
+                            "t: SIGTERM process".to_string(),
+                            "k: SIGKILL process".to_string(),
+                            "1/2: SIGUSR1/2 (program-defined)".to_string(),
+                            ":: send signal by name".to_string(),
+                        ]);
+                    }
+                    commands.extend([
                         "ESC: unselect".to_string(),
                         "ENTER: select other".to_string(),
+                    ]);
+                    if self.show_breakdown {
+                        commands.push(if self.breakdown_focused {
+                            "f: scroll list".to_string()
+                        } else {
+                            "f: scroll breakdown".to_string()
+                        });
+                    }
+                    if let Some(status_message) = &self.status_message {
+                        commands.push(status_message.clone());
+                    }
+                    if !self.patterns_string().is_empty() {
+                        commands.push(format!(
+                            "search pattern ({}): {}",
+                            self.pattern_mode(),
+                            self.patterns_string()
+                        ));
+                    }
+                    commands.join(" | ")
+                }
+                UiMode::EnteringSignal(_pid) => {
+                    let mut commands = vec![
+                        "Ctrl+C: Quit".to_string(),
+                        "ENTER: send signal".to_string(),
+                        "ESC: cancel".to_string(),
+                        format!("signal: {}▌", self.signal_input),
+                    ];
+                    if let Some(status_message) = &self.status_message {
+                        commands.push(status_message.clone());
+                    }
+                    commands.join(" | ")
+                }
+                UiMode::ConfirmingSignal(request) => [
+                    "Ctrl+C: Quit".to_string(),
+                    format!("send {} to {}?", request.signal, request.pid),
+                    "y: yes".to_string(),
+                    "n: no".to_string(),
+                ]
+                .join(" | "),
+                UiMode::ConfirmingMarkedSignal(signal) => [
+                    "Ctrl+C: Quit".to_string(),
+                    format!("send {} to {} marked processes?", signal, self.marked.len()),
+                    "y: yes".to_string(),
+                    "n: no".to_string(),
+                ]
+                .join(" | "),
+                UiMode::ConfirmingQuit => [
+                    "Ctrl+C: Quit".to_string(),
+                    format!("Quit and discard {} marked? [y/N]", self.marked.len()),
+                ]
+                .join(" | "),
+                UiMode::EditingFilters => {
+                    let filters = if self.active_filters().is_empty() {
+                        "no active filters"
+                    } else {
+                        "1-9: remove filter"
+                    };
+                    ["Ctrl+C: Quit", "ESC: done", filters].join(" | ")
+                }
+                UiMode::FilterBuilder(None) => {
+                    ["Ctrl+C: Quit", "ESC: cancel", "1-4: filter on field"].join(" | ")
+                }
+                UiMode::FilterBuilder(Some(field)) => {
+                    let mut commands = vec![
+                        "Ctrl+C: Quit".to_string(),
+                        "ENTER: add filter".to_string(),
+                        "ESC: cancel".to_string(),
+                        format!("{} (<, = or >): {}▌", field.name(), self.filter_input),
+                    ];
+                    if let Some(status_message) = &self.status_message {
+                        commands.push(status_message.clone());
+                    }
+                    commands.join(" | ")
+                }
+                UiMode::EnteringPresetName => {
+                    let mut commands = vec![
+                        "Ctrl+C: Quit".to_string(),
+                        "ENTER: save preset".to_string(),
+                        "ESC: cancel".to_string(),
+                        format!("preset name: {}▌", self.preset_input),
                     ];
-                    if !self.pattern.as_str().is_empty() {
-                        commands.push(format!("search pattern: {}", self.pattern.as_str()));
+                    if let Some(status_message) = &self.status_message {
+                        commands.push(status_message.clone());
                     }
                     commands.join(" | ")
                 }
             };
+            let commands = match &self.kill_status {
+                Some(kill_status) => format!("{} | {}", kill_status, commands),
+                None => commands,
+            };
+            let status_bar = match &self.status_bar_format {
+                None => commands,
+                Some(format) => fill_template(
+                    format,
+                    &[
+                        ("commands", commands),
+                        ("pattern", self.patterns_string()),
+                        ("count", list.len().to_string()),
+                        ("sort", format!("{:?}", self.sort_column).to_lowercase()),
+                    ],
+                ),
+            };
             let mut status_bar = Paragraph::new(status_bar).reversed();
             match self.ui_mode {
                 UiMode::Normal => {}
-                UiMode::EditingPattern => {
+                UiMode::EditingPattern
+                | UiMode::EnteringPresetName
+                | UiMode::ConfirmingQuit
+                | UiMode::EditingFilters
+                | UiMode::FilterBuilder(_) => {
                     status_bar = status_bar.yellow();
                 }
-                UiMode::ProcessSelected(_) => {
+                UiMode::ProcessSelected(_)
+                | UiMode::EnteringSignal(_)
+                | UiMode::ConfirmingSignal(_)
+                | UiMode::ConfirmingMarkedSignal(_) => {
                     status_bar = status_bar.red();
                 }
             }
             status_bar.render(
                 Rect {
                     x: area.x,
-                    y: area.height - 1,
+                    y: area.height.saturating_sub(1),
                     width: area.width,
                     height: 1,
                 },
                 buffer,
             );
         }
+        if let Some((column, row, command)) = tooltip {
+            let rect = tooltip_rect(area, column, row, command.chars().count());
+            Clear.render(rect, buffer);
+            Paragraph::new(command)
+                .wrap(Wrap { trim: false })
+                .block(Block::bordered())
+                .render(rect, buffer);
+        }
+    }
+
+    fn tick(&mut self) -> R<UpdateResult> {
+        let start = Instant::now();
+        let result = self.refresh();
+        match &result {
+            Ok(()) => self.log(&format!("tick took {:?}", start.elapsed())),
+            Err(error) => self.log(&format!("tick failed: {}", error)),
+        }
+        result?;
+        if self.check_followed_command()? && self.exit_with_command {
+            return Ok(UpdateResult::Exit);
+        }
+        Ok(match self.wait_for {
+            Some(pid) if !self.is_running(pid) => UpdateResult::Exit,
+            _ => UpdateResult::Continue,
+        })
+    }
+}
+
+// Substitutes in a single pass, so tokens inside of values are left alone.
+fn fill_template(template: &str, values: &[(&str, String)]) -> String {
+    let mut result = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest.find('}').and_then(|end| {
+            let (_, value) = values.iter().find(|(token, _)| *token == &rest[1..end])?;
+            Some((value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                result.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+// The rest of a command that doesn't fit into its row, split into lines that
+// are indented to where the command starts.
+fn wrap_command(command: &str, column: usize, width: usize) -> Vec<String> {
+    let available = width.saturating_sub(column);
+    if available == 0 {
+        return Vec::new();
+    }
+    let chars = command.chars().collect::<Vec<_>>();
+    chars[available.min(chars.len())..]
+        .chunks(available)
+        .map(|chunk| format!("{}{}", " ".repeat(column), chunk.iter().collect::<String>()))
+        .collect()
+}
+
+fn age_modifier(run_time: u64) -> Modifier {
+    if run_time < NEW_PROCESS_AGE {
+        Modifier::BOLD
+    } else if run_time >= OLD_PROCESS_AGE {
+        Modifier::DIM
+    } else {
+        Modifier::empty()
+    }
+}
+
+// Every level of a tree prefix takes up two characters, which are colored
+// by the level they belong to.
+fn depth_colored(prefix: &str) -> Vec<Span<'static>> {
+    let chars = prefix.chars().collect::<Vec<_>>();
+    chars
+        .chunks(2)
+        .enumerate()
+        .map(|(level, segment)| {
+            Span::from(segment.iter().collect::<String>())
+                .fg(DEPTH_COLORS[level % DEPTH_COLORS.len()])
+        })
+        .collect()
+}
+
+// Rows with their tree prefix and depth. With a global order processes are
+// sorted across the whole tree and only indented by their depth.
+fn render_rows<'a>(
+    forest: &'a Forest<Process>,
+    style: &PrefixStyle,
+    inverted: bool,
+    global_order: Option<impl Fn(&Process, &Process) -> Ordering>,
+) -> Vec<(String, &'a Process, usize)> {
+    if let Some(compare) = global_order {
+        return forest
+            .flatten_sorted(&compare)
+            .into_iter()
+            .map(|(depth, process)| (style.indentation(depth), process, depth))
+            .collect();
+    }
+    let mut depths: Vec<usize> = forest.iter_with_depth().map(|(depth, _)| depth).collect();
+    let rows = if inverted {
+        depths.reverse();
+        forest.render_forest_prefixes_inverted(style)
+    } else {
+        forest.render_forest_prefixes(style)
+    };
+    rows.into_iter()
+        .zip(depths)
+        .map(|((prefix, process), depth)| (prefix, process, depth))
+        .collect()
+}
+
+fn normalize_list_state<T>(
+    list_state: &mut ListState,
+    list: &[T],
+    rect: &Rect,
+    pin_selected: bool,
+) {
+    if let Some(ref mut selected) = list_state.selected_mut() {
+        *selected = (*selected).min(list.len().saturating_sub(1));
+    }
+    if pin_selected {
+        if let Some(selected) = list_state.selected() {
+            *list_state.offset_mut() = selected;
+            return;
+        }
+    }
+    *list_state.offset_mut() = list_state
+        .offset()
+        .min(list.len().saturating_sub(rect.height.into()));
+}
+
+// The index of the item shown at line `y` of a list that starts with item
+// `offset`, given the heights of all items.
+fn row_at(heights: &[usize], offset: usize, y: usize) -> Option<usize> {
+    let mut top = 0;
+    for (index, height) in heights.iter().enumerate().skip(offset) {
+        if y < top + height {
+            return Some(index);
+        }
+        top += height;
+    }
+    None
+}
+
+// A bordered box for a tooltip of `length` characters, below the cursor if
+// there's room and above it otherwise, and always within `area`. Text that's
+// wider than `area` is wrapped.
+fn tooltip_rect(area: Rect, column: u16, row: u16, length: usize) -> Rect {
+    let width = (length + 2).min(usize::from(area.width));
+    let lines = length.div_ceil(width.saturating_sub(2).max(1)).max(1);
+    let height = u16::try_from(lines + 2)
+        .unwrap_or(u16::MAX)
+        .min(area.height);
+    let width = width as u16;
+    let x = column.min(area.right().saturating_sub(width));
+    let y = if row + 1 + height <= area.bottom() {
+        row + 1
+    } else {
+        row.saturating_sub(height).max(area.y)
+    };
+    Rect {
+        x,
+        y,
+        width,
+        height,
+    }
+}
+
+// Scrolls the way ratatui's list does when rendering, so the topmost
+// visible row is known beforehand.
+fn scroll_to_selected(list_state: &mut ListState, height: u16) {
+    let height = usize::from(height).max(1);
+    if let Some(selected) = list_state.selected() {
+        if selected < list_state.offset() {
+            *list_state.offset_mut() = selected;
+        } else if selected >= list_state.offset() + height {
+            *list_state.offset_mut() = selected + 1 - height;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tui_app::TuiApp;
+    use crate::utils::test::TempDir;
+    use crate::Args;
+    use clap::Parser;
+    use crossterm::event::{KeyEventKind, KeyEventState};
+    use insta::assert_snapshot;
+    use ratatui::buffer::Cell;
+    use ratatui::layout::Rect;
+    use ratatui::style::Color;
+    use ratatui::style::Modifier;
+    use ratatui::widgets::ListState;
+
+    const RECT: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 80,
+        height: 20,
+    };
+
+    #[test]
+    fn fill_template_substitutes_tokens() {
+        assert_eq!(
+            fill_template(
+                "{count} processes, sorted by {sort} ({unknown})",
+                &[("count", "3".to_string()), ("sort", "cpu".to_string())]
+            ),
+            "3 processes, sorted by cpu ({unknown})"
+        );
+    }
+
+    #[test]
+    fn fill_template_leaves_tokens_in_values_alone() {
+        assert_eq!(
+            fill_template(
+                "{pattern}: {count}",
+                &[
+                    ("pattern", "{count}{".to_string()),
+                    ("count", "3".to_string())
+                ]
+            ),
+            "{count}{: 3"
+        );
+        assert_eq!(
+            fill_template("{{count}} {", &[("count", "3".to_string())]),
+            "{3} {"
+        );
+    }
+
+    #[test]
+    fn normalize_leaves_state_unmodified() {
+        let mut list_state = ListState::default().with_selected(Some(7)).with_offset(5);
+        normalize_list_state(&mut list_state, &[(); 30], &RECT, false);
+        assert_eq!(list_state.selected(), Some(7));
+        assert_eq!(list_state.offset(), 5);
+    }
+
+    #[test]
+    fn normalize_caps_at_the_list_end() {
+        let mut list_state = ListState::default().with_selected(Some(11));
+        normalize_list_state(&mut list_state, &[(); 10], &RECT, false);
+        assert_eq!(list_state.selected(), Some(9));
+    }
+
+    #[test]
+    fn normalize_resets_offset_to_zero_when_the_list_fits_the_area() {
+        let mut list_state = ListState::default().with_selected(Some(0)).with_offset(5);
+        normalize_list_state(&mut list_state, &[(); 10], &RECT, false);
+        assert_eq!(list_state.offset(), 0);
+    }
+
+    #[test]
+    fn normalize_keeps_the_selected_row_at_the_top_when_pinned() {
+        let mut list_state = ListState::default().with_selected(Some(27)).with_offset(5);
+        normalize_list_state(&mut list_state, &[(); 30], &RECT, true);
+        assert_eq!(list_state.offset(), 27);
+    }
+
+    #[test]
+    fn normalize_scrolls_up_when_offset_is_too_big() {
+        let mut list_state = ListState::default().with_selected(Some(0)).with_offset(25);
+        normalize_list_state(&mut list_state, &[(); 30], &RECT, false);
+        assert_eq!(list_state.offset(), 10);
+    }
+
+    fn test_app(processes: Vec<Process>) -> R<TreetopApp> {
+        test_app_with_args(processes, &["--presets-file", "/nonexistent/presets.json"])
+    }
+
+    fn test_app_with_args(processes: Vec<Process>, args: &[&str]) -> R<TreetopApp> {
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(processes),
+            &Args::parse_from([&["treetop"], args].concat()).options(),
+        )?;
+        app.tick()?;
+        Ok(app)
+    }
+
+    fn render_ui(app: TreetopApp) -> String {
+        render_ui_with_width(app, 80)
+    }
+
+    fn render_ui_with_width(mut app: TreetopApp, width: u16) -> String {
+        let area = Rect::new(0, 0, width, 10);
+        let mut buffer = Buffer::filled(area, Cell::new(" "));
+        app.render(area, &mut buffer);
+        let mut result = String::new();
+        for y in 0..area.height {
+            for x in 0..area.width {
+                let symbol = buffer[(x, y)].symbol();
+                let symbol = if buffer[(x, y)].modifier.contains(Modifier::REVERSED) {
+                    crate::utils::test::underline(symbol)
+                } else {
+                    symbol.to_string()
+                };
+                result.push_str(&symbol);
+            }
+            result.push('\n')
+        }
+        result
+    }
+
+    fn simulate_key_press(app: &mut TreetopApp, code: KeyCode) -> R<UpdateResult> {
+        app.update(KeyEvent {
+            code,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        })
+    }
+
+    fn set_pattern(app: &mut TreetopApp, pattern: &str) -> R<()> {
+        app.patterns = vec![Regex::parse(pattern, app.fixed_strings, app.ignore_case)?];
+        Ok(())
+    }
+
+    #[test]
+    fn shows_a_tree_with_header_and_side_columns() -> R<()> {
+        let app = test_app(vec![
+            Process::fake(1, 4.0, None),
+            Process::fake(2, 3.0, Some(1)),
+            Process::fake(3, 2.0, Some(2)),
+            Process::fake(4, 1.0, None),
+            Process::fake(5, 0.0, Some(4)),
+        ])?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn processes_get_sorted_by_pid() -> R<()> {
+        let app = test_app(vec![
+            Process::fake(1, 1.0, None),
+            Process::fake(2, 2.0, None),
+            Process::fake(3, 4.0, None),
+            Process::fake(4, 3.0, None),
+        ])?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn selection_follows_the_highlighted_process_when_filtering() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, None),
+            Process::fake(3, 0.0, None),
+            Process::fake(4, 0.0, None),
+            Process::fake(5, 0.0, None),
+        ])?;
+        app.list_state.select(Some(2));
+        assert_eq!(app.highlighted_process(), Some(3.into()));
+        simulate_key_press(&mut app, KeyCode::Char('/'))?;
+        simulate_key_press(&mut app, KeyCode::Char('e'))?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![1.into(), 3.into(), 5.into()]
+        );
+        assert_eq!(app.list_state.selected(), Some(1));
+        assert_eq!(app.highlighted_process(), Some(3.into()));
+        Ok(())
+    }
+
+    fn app_with_filter_display(args: &[&str]) -> R<TreetopApp> {
+        test_app_with_args(
+            vec![
+                Process::fake(1, 0.0, None),
+                Process::fake(2, 0.0, Some(1)),
+                Process::fake(3, 0.0, Some(2)),
+                Process::fake(4, 0.0, Some(1)),
+                Process::fake(5, 0.0, Some(2)),
+                Process::fake(6, 0.0, Some(3)),
+            ],
+            &[&["--pattern", "three"], args].concat(),
+        )
+    }
+
+    #[test]
+    fn filtering_shows_the_subtrees_of_matches_by_default() -> R<()> {
+        let app = app_with_filter_display(&[])?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![1.into(), 2.into(), 3.into(), 6.into()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn filter_display_paths_only_shows_the_paths_to_matches() -> R<()> {
+        let app = app_with_filter_display(&["--filter-display", "paths"])?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn filter_display_paths_hides_branches_off_the_paths_between_matches() -> R<()> {
+        let mut app = app_with_filter_display(&["--filter-display", "paths"])?;
+        set_pattern(&mut app, "two|three")?;
+        app.tick()?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![1.into(), 2.into(), 3.into()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn filter_display_paths_collapses_matches() -> R<()> {
+        let mut app = app_with_filter_display(&["--filter-display", "paths"])?;
+        assert_eq!(app.auto_collapsed, HashSet::from([3.into()]));
+        simulate_key_press(&mut app, KeyCode::Char('_'))?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![1.into(), 2.into(), 3.into(), 6.into()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn no_subtrees_does_not_collapse_matches() -> R<()> {
+        let mut app = app_with_filter_display(&["--no-subtrees"])?;
+        assert!(app.auto_collapsed.is_empty());
+        simulate_key_press(&mut app, KeyCode::Char('_'))?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![1.into(), 2.into(), 3.into()]
+        );
+        Ok(())
+    }
+
+    fn app_with_highlight_args(args: &[&str]) -> R<TreetopApp> {
+        let mut app = test_app_with_args(
+            vec![
+                Process::fake(1, 0.0, None),
+                Process::fake(2, 0.0, Some(1)),
+                Process::fake(3, 0.0, Some(1)),
+            ],
+            args,
+        )?;
+        simulate_key_press(&mut app, KeyCode::Down)?;
+        Ok(app)
+    }
+
+    #[test]
+    fn shows_a_custom_selection_marker() -> R<()> {
+        let app = app_with_highlight_args(&["--selection-marker", "=>"])?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn highlights_the_whole_selected_row() -> R<()> {
+        let app = app_with_highlight_args(&["--highlight", "row", "--selection-marker", " "])?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn colors_the_selection_marker() -> R<()> {
+        let mut app = app_with_highlight_args(&["--selection-color", "magenta"])?;
+        let area = Rect::new(0, 0, 80, 10);
+        let mut buffer = Buffer::empty(area);
+        app.render(area, &mut buffer);
+        let marker = (0..area.width)
+            .map(|x| &buffer[(x, 3)])
+            .find(|cell| cell.symbol() == "▶")
+            .unwrap();
+        assert_eq!(marker.fg, Color::Magenta);
+        Ok(())
+    }
+
+    #[test]
+    fn jumping_between_roots() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, None),
+            Process::fake(4, 0.0, Some(3)),
+            Process::fake(5, 0.0, Some(4)),
+            Process::fake(6, 0.0, None),
+        ])?;
+        let mut highlighted = Vec::new();
+        for _ in 0..3 {
+            simulate_key_press(&mut app, KeyCode::Char('}'))?;
+            highlighted.push(app.highlighted_process());
+        }
+        assert_eq!(
+            highlighted,
+            vec![Some(3.into()), Some(6.into()), Some(6.into())]
+        );
+        simulate_key_press(&mut app, KeyCode::Up)?;
+        simulate_key_press(&mut app, KeyCode::Char('{'))?;
+        assert_eq!(app.highlighted_process(), Some(3.into()));
+        simulate_key_press(&mut app, KeyCode::Char('{'))?;
+        assert_eq!(app.highlighted_process(), Some(1.into()));
+        Ok(())
+    }
+
+    #[test]
+    fn processes_can_be_sorted_by_cpu() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 1.0, None),
+            Process::fake(2, 2.0, None),
+            Process::fake(3, 4.0, None),
+            Process::fake(4, 3.0, None),
+        ])?;
+        simulate_key_press(&mut app, KeyCode::Tab)?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn compact_layout() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 4.0, None),
+            Process::fake(2, 3.0, Some(1)),
+            Process::fake(3, 2.0, Some(2)),
+            Process::fake(4, 1.0, None),
+            Process::fake(5, 0.0, Some(4)),
+        ])?;
+        simulate_key_press(&mut app, KeyCode::Char('c'))?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    fn simulate_click(app: &mut TreetopApp, column: u16, row: u16) {
+        app.mouse(MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        });
+    }
+
+    fn app_with_tooltips() -> R<TreetopApp> {
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![
+                Process::fake(1, 0.0, None),
+                Process::fake(2, 0.0, Some(1)).with_arguments(&["two", "--some", "--arguments"]),
+                Process::fake(3, 0.0, Some(1)),
+            ]),
+            &Args::parse_from(["treetop", "--tooltips"]).options(),
+        )?;
+        app.tick()?;
+        Ok(app)
+    }
+
+    fn simulate_hover(app: &mut TreetopApp, column: u16, row: u16) {
+        app.mouse(MouseEvent {
+            kind: MouseEventKind::Moved,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        });
+    }
+
+    #[test]
+    fn hovering_a_row_shows_its_full_command_line() -> R<()> {
+        let mut app = app_with_tooltips()?;
+        simulate_hover(&mut app, 50, 3);
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn tooltips_stay_within_the_area() -> R<()> {
+        let mut app = app_with_tooltips()?;
+        simulate_hover(&mut app, 75, 3);
+        let ui = render_ui(app).replace('\u{35f}', "");
+        let lines = ui.lines().collect::<Vec<_>>();
+        assert!(lines[5].ends_with("│two --some --arguments│"));
+        Ok(())
+    }
+
+    #[test]
+    fn tooltips_are_only_shown_over_rows() -> R<()> {
+        let mut app = app_with_tooltips()?;
+        simulate_hover(&mut app, 50, 7);
+        assert!(!render_ui(app).contains("│two"));
+        Ok(())
+    }
+
+    #[test]
+    fn finds_the_item_at_a_line_of_the_list() {
+        let heights = [1, 3, 1];
+        assert_eq!(row_at(&heights, 0, 0), Some(0));
+        assert_eq!(row_at(&heights, 0, 1), Some(1));
+        assert_eq!(row_at(&heights, 0, 3), Some(1));
+        assert_eq!(row_at(&heights, 0, 4), Some(2));
+        assert_eq!(row_at(&heights, 0, 5), None);
+        assert_eq!(row_at(&heights, 1, 0), Some(1));
+        assert_eq!(row_at(&heights, 1, 3), Some(2));
+    }
+
+    #[test]
+    fn clicking_a_header_column_sorts_by_it() -> R<()> {
+        let mut app = test_app(vec![])?;
+        simulate_click(&mut app, 12, 0);
+        assert_eq!(app.sort_column, SortBy::Cpu);
+        simulate_click(&mut app, 20, 0);
+        assert_eq!(app.sort_column, SortBy::Ram);
+        simulate_click(&mut app, 5, 0);
+        assert_eq!(app.sort_column, SortBy::Pid);
+        Ok(())
+    }
+
+    #[test]
+    fn initial_sort_column_and_direction_can_be_configured() -> R<()> {
+        let app = test_app_with_args(
+            vec![
+                Process::fake(1, 30.0, None),
+                Process::fake(2, 10.0, None),
+                Process::fake(3, 20.0, None),
+            ],
+            &["--sort", "cpu", "--sort-dir", "asc"],
+        )?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_sort_directions() {
+        assert!(Args::try_parse_from(["treetop", "--sort-dir", "up"]).is_err());
+        assert!(Args::try_parse_from(["treetop", "--sort", "nothing"]).is_err());
+    }
+
+    #[test]
+    fn reversing_the_sort_direction() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, None),
+        ])?;
+        simulate_key_press(&mut app, KeyCode::Char('r'))?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![2.into(), 1.into()]
+        );
+        simulate_key_press(&mut app, KeyCode::Tab)?;
+        assert_eq!(app.sort_direction, SortDirection::Descending);
+        Ok(())
+    }
+
+    #[test]
+    fn number_keys_select_sort_columns() -> R<()> {
+        let mut app = test_app(vec![])?;
+        simulate_key_press(&mut app, KeyCode::Char('2'))?;
+        assert_eq!(app.sort_column, SortBy::Cpu);
+        assert_eq!(app.sort_direction, SortDirection::Descending);
+        simulate_key_press(&mut app, KeyCode::Char('1'))?;
+        assert_eq!(app.sort_column, SortBy::Pid);
+        simulate_key_press(&mut app, KeyCode::Char('3'))?;
+        assert_eq!(app.sort_column, SortBy::Ram);
+        Ok(())
+    }
+
+    #[test]
+    fn columns_can_be_reordered() -> R<()> {
+        let mut app = test_app_with_args(
+            vec![Process::fake(1, 2.0, None), Process::fake(2, 1.0, Some(1))],
+            &["--columns", "cpu,threads,pid"],
+        )?;
+        simulate_key_press(&mut app, KeyCode::Char('1'))?;
+        assert_eq!(app.sort_column, SortBy::Cpu);
+        simulate_key_press(&mut app, KeyCode::Tab)?;
+        assert_eq!(app.sort_column, SortBy::Threads);
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn columns_given_on_launch_replace_the_default_columns() -> R<()> {
+        let app = test_app_with_args(vec![Process::fake(1, 2.0, None)], &["--columns", "cpu,pid"])?;
+        let header = render_ui(app)
+            .lines()
+            .next()
+            .unwrap()
+            .replace('\u{35f}', "");
+        assert_eq!(
+            header.split_whitespace().collect::<Vec<_>>(),
+            vec!["cpu", "pid", "┃", "executable"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unknown_columns() {
+        let error = Args::try_parse_from(["treetop", "--columns", "cpu,nothing"])
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("unknown column: nothing, expected one of: pid, cpu, ram"));
+    }
+
+    #[test]
+    fn number_keys_ignore_missing_and_hidden_columns() -> R<()> {
+        let mut app = test_app(vec![])?;
+        simulate_key_press(&mut app, KeyCode::Char('2'))?;
+        simulate_key_press(&mut app, KeyCode::Char('9'))?;
+        assert_eq!(app.sort_column, SortBy::Cpu);
+        app.perform(Action::SortByColumn(100))?;
+        assert_eq!(app.sort_column, SortBy::Cpu);
+        Ok(())
+    }
+
+    #[test]
+    fn clicking_outside_the_header_does_not_change_sorting() -> R<()> {
+        let mut app = test_app(vec![])?;
+        simulate_click(&mut app, 12, 3);
+        assert_eq!(app.sort_column, SortBy::Pid);
+        simulate_click(&mut app, 50, 0);
+        assert_eq!(app.sort_column, SortBy::Pid);
+        Ok(())
+    }
+
+    #[test]
+    fn column_widths_grow_with_the_values() -> R<()> {
+        let mut app = test_app(vec![Process::fake(1, 1.0, None)])?;
+        let area = Rect::new(0, 0, 80, 10);
+        app.render(area, &mut Buffer::empty(area));
+        let before = app.table_layout.clone();
+        app.process_watcher =
+            ProcessWatcher::fake(vec![Process::fake(1, 1.0, None).with_ram(2_u64.pow(50))]);
+        app.tick()?;
+        app.render(area, &mut Buffer::empty(area));
+        assert_ne!(app.table_layout, before);
+        Ok(())
+    }
+
+    #[test]
+    fn frozen_column_widths_stay_the_same_after_the_first_render() -> R<()> {
+        let mut app = test_app(vec![Process::fake(1, 1.0, None)])?;
+        app.freeze_widths = true;
+        let area = Rect::new(0, 0, 80, 10);
+        app.render(area, &mut Buffer::empty(area));
+        let before = app.table_layout.clone();
+        app.process_watcher = ProcessWatcher::fake(vec![Process::fake(1, 123456.0, None)]);
+        app.tick()?;
+        app.render(area, &mut Buffer::empty(area));
+        assert_eq!(app.table_layout, before);
+        Ok(())
+    }
+
+    #[test]
+    fn large_accumulated_cpu_values_keep_the_columns_aligned() -> R<()> {
+        let subtree = |cpu| {
+            let mut processes = vec![Process::fake(1, 0.0, None)];
+            processes.extend((2..=5).map(|pid| Process::fake(pid, cpu, Some(1))));
+            ProcessWatcher::fake(processes)
+        };
+        let mut app = test_app(Vec::new())?;
+        app.freeze_widths = true;
+        app.process_watcher = subtree(1.0);
+        app.tick()?;
+        let area = Rect::new(0, 0, 80, 10);
+        app.render(area, &mut Buffer::empty(area));
+        app.process_watcher = subtree(400.0);
+        app.tick()?;
+        let ui = render_ui(app).replace('\u{35f}', "");
+        assert!(ui.contains("16.0x"));
+        let separators = ui
+            .lines()
+            .filter_map(|line| line.chars().position(|c| c == '┃' || c == '╋'))
+            .collect::<HashSet<_>>();
+        assert_eq!(separators.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn logs_key_events_and_ticks() -> R<()> {
+        let temp = TempDir::new("log-test")?;
+        let log = temp.join("treetop.log");
+        let mut app = test_app_with_args(
+            vec![Process::fake(1, 0.0, None)],
+            &["--log", log.to_str().unwrap()],
+        )?;
+        simulate_key_press(&mut app, KeyCode::Char('c'))?;
+        let lines = std::fs::read_to_string(&log)?
+            .lines()
+            .map(|line| line.split_once(' ').unwrap().1.to_string())
+            .collect::<Vec<_>>();
+        assert!(lines[0].starts_with("tick took "));
+        assert_eq!(
+            lines[1..],
+            ["key KeyModifiers(0x0) Char('c') in Normal: Some(ToggleCompact)"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn logs_sent_signals_as_json() -> R<()> {
+        let temp = TempDir::new("signal-log-test")?;
+        let log = temp.join("signal-log.jsonl");
+        let mut app = test_app_with_args(
+            vec![Process::fake(1, 0.0, None)],
+            &["--signal-log", log.to_str().unwrap()],
+        )?;
+        app.signal_sender = SignalSender::fake();
+        simulate_key_press(&mut app, KeyCode::Enter)?;
+        simulate_key_press(&mut app, KeyCode::Char('t'))?;
+        app.signal_sender = SignalSender::failing();
+        simulate_key_press(&mut app, KeyCode::Char('1'))?;
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("can't send SIGUSR1: EPERM: Operation not permitted")
+        );
+        assert_eq!(
+            std::fs::read_to_string(&log)?.lines().collect::<Vec<_>>(),
+            [
+                r#"{"pid":1,"signal":"SIGTERM","result":"ok"}"#,
+                r#"{"pid":1,"signal":"SIGUSR1","result":"EPERM: Operation not permitted"}"#,
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn the_sorted_column_can_be_resized() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+        ])?;
+        for _ in 0..3 {
+            simulate_key_press(&mut app, KeyCode::Char('>'))?;
+        }
+        simulate_key_press(&mut app, KeyCode::Char('<'))?;
+        assert_eq!(app.table_layout().widths()[0], (SortBy::Pid, 10));
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn columns_cant_shrink_below_their_contents() -> R<()> {
+        let mut app = test_app(vec![Process::fake(1, 0.0, None)])?;
+        for _ in 0..100 {
+            simulate_key_press(&mut app, KeyCode::Char('<'))?;
+        }
+        assert_eq!(
+            app.width_adjustments[&SortBy::Pid],
+            -TableLayout::MAX_ADJUSTMENT
+        );
+        assert_eq!(app.table_layout().widths()[0], (SortBy::Pid, 3));
+        Ok(())
+    }
+
+    #[test]
+    fn cpu_spikes_are_highlighted_for_a_few_ticks() -> R<()> {
+        let mut app = test_app_with_args(
+            vec![Process::fake(1, 1.0, None), Process::fake(2, 1.0, Some(1))],
+            &["--spike-threshold", "50"],
+        )?;
+        let row_backgrounds = |app: &mut TreetopApp| {
+            let area = Rect::new(0, 0, 80, 10);
+            let mut buffer = Buffer::empty(area);
+            app.render(area, &mut buffer);
+            [buffer[(0, 2)].bg, buffer[(0, 3)].bg]
+        };
+        assert_eq!(row_backgrounds(&mut app), [Color::Reset, Color::Reset]);
+        app.process_watcher = ProcessWatcher::fake(vec![
+            Process::fake(1, 1.0, None),
+            Process::fake(2, 80.0, Some(1)),
+        ]);
+        app.tick()?;
+        assert_eq!(row_backgrounds(&mut app), [Color::Reset, Color::Yellow]);
+        app.tick()?;
+        assert_eq!(row_backgrounds(&mut app), [Color::Reset, Color::DarkGray]);
+        app.tick()?;
+        assert_eq!(row_backgrounds(&mut app), [Color::Reset, Color::Reset]);
+        Ok(())
+    }
+
+    #[test]
+    fn new_processes_are_hidden_until_they_settle() -> R<()> {
+        let mut app = test_app_with_args(vec![Process::fake(1, 0.0, None)], &["--settle", "2"])?;
+        let pids = |app: &TreetopApp| app.forest.iter().map(Node::id).collect::<Vec<_>>();
+        assert_eq!(pids(&app), vec![1.into()]);
+        app.process_watcher = ProcessWatcher::fake(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)).with_run_time(100),
+        ]);
+        app.tick()?;
+        assert_eq!(pids(&app), vec![1.into()]);
+        app.tick()?;
+        assert_eq!(pids(&app), vec![1.into(), 2.into()]);
+        Ok(())
+    }
+
+    #[test]
+    fn processes_that_vanish_start_settling_again() -> R<()> {
+        let mut app = test_app_with_args(vec![Process::fake(1, 0.0, None)], &["--settle", "2"])?;
+        app.process_watcher = ProcessWatcher::fake(vec![]);
+        app.tick()?;
+        app.process_watcher = ProcessWatcher::fake(vec![Process::fake(1, 0.0, None)]);
+        app.tick()?;
+        assert_eq!(app.forest.iter().count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn dims_old_processes_and_brightens_new_ones() -> R<()> {
+        let mut app = test_app_with_args(
+            vec![
+                Process::fake(1, 0.0, None).with_run_time(2 * 24 * 60 * 60),
+                Process::fake(2, 0.0, Some(1)).with_run_time(10 * 60),
+                Process::fake(3, 0.0, Some(1)).with_run_time(5),
+            ],
+            &["--dim-by-age"],
+        )?;
+        let area = Rect::new(0, 0, 80, 10);
+        let mut buffer = Buffer::empty(area);
+        app.render(area, &mut buffer);
+        let name_modifier = |row: u16| {
+            let separator = (0..area.width)
+                .find(|&x| buffer[(x, row)].symbol() == "┃")
+                .unwrap();
+            let x = (separator..area.width)
+                .find(|&x| buffer[(x, row)].symbol().chars().all(char::is_alphabetic))
+                .unwrap();
+            buffer[(x, row)].modifier
+        };
+        assert!(name_modifier(2).contains(Modifier::DIM));
+        assert_eq!(
+            name_modifier(3) & (Modifier::DIM | Modifier::BOLD),
+            Modifier::empty()
+        );
+        assert!(name_modifier(4).contains(Modifier::BOLD));
+        Ok(())
+    }
+
+    #[test]
+    fn recording_writes_a_snapshot_per_tick() -> R<()> {
+        let temp = TempDir::new("record-test")?;
+        let recording = temp.join("record.jsonl");
+        let mut app = test_app_with_args(
+            vec![Process::fake(1, 0.0, None), Process::fake(2, 0.0, Some(1))],
+            &["--record", recording.to_str().unwrap()],
+        )?;
+        app.tick()?;
+        drop(app);
+        let lines = std::fs::read_to_string(&recording)?
+            .lines()
+            .map(|line| serde_json::from_str::<Vec<Process>>(line).map(|processes| processes.len()))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(lines, vec![2, 2]);
+        Ok(())
+    }
+
+    fn app_with_descendants(processes: Vec<Process>) -> R<TreetopApp> {
+        let app = test_app_with_args(processes, &["--descendants"])?;
+        Ok(app)
+    }
+
+    #[test]
+    fn shows_descendant_counts() -> R<()> {
+        let app = app_with_descendants(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(2)),
+            Process::fake(4, 0.0, None),
+        ])?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn ctrl_s_dumps_the_shown_processes() -> R<()> {
+        let temp = TempDir::new("dump-test")?;
+        let dump_dir = temp.path();
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![
+                Process::fake(1, 0.0, None),
+                Process::fake(2, 0.0, Some(1)),
+                Process::fake(3, 0.0, None),
+            ]),
+            &Args::parse_from(["treetop", "--dump-dir", dump_dir.to_str().unwrap()]).options(),
+        )?;
+        set_pattern(&mut app, "two")?;
+        app.tick()?;
+        app.update(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL))?;
+        let files = std::fs::read_dir(dump_dir)?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<R<Vec<_>>>()?;
+        assert_eq!(files.len(), 1);
+        assert_eq!(
+            app.status_message,
+            Some(format!("wrote {}", files[0].display()))
+        );
+        let processes: Vec<Process> = serde_json::from_str(&std::fs::read_to_string(&files[0])?)?;
+        assert_eq!(
+            processes
+                .iter()
+                .map(|process| (process.id(), process.parent()))
+                .collect::<Vec<_>>(),
+            vec![(1.into(), None), (2.into(), Some(1.into()))]
+        );
+        assert!(render_ui_with_width(app, 200)
+            .replace('\u{35f}', "")
+            .contains("| wrote "));
+        Ok(())
+    }
+
+    #[test]
+    fn dumping_twice_keeps_both_dumps() -> R<()> {
+        let temp = TempDir::new("dump-twice-test")?;
+        let dump_dir = temp.path();
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![Process::fake(1, 0.0, None)]),
+            &Args::parse_from(["treetop", "--dump-dir", dump_dir.to_str().unwrap()]).options(),
+        )?;
+        app.tick()?;
+        for _ in 0..3 {
+            app.update(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL))?;
+        }
+        let files = std::fs::read_dir(dump_dir)?.count();
+        assert_eq!(files, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn shows_columns_from_registered_providers() -> R<()> {
+        #[derive(Debug)]
+        struct DoublePid;
+
+        impl ColumnProvider for DoublePid {
+            fn header(&self) -> &str {
+                "double"
+            }
+
+            fn value(&self, process: &Process) -> String {
+                (process.id().as_u32() * 2).to_string()
+            }
+        }
+
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+        ])?;
+        app.column_providers.push(Box::new(DoublePid));
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn shows_cpu_per_thread() -> R<()> {
+        let app = test_app_with_args(
+            vec![Process::fake(1, 30.0, None).with_threads(4)],
+            &["--cpu-per-thread"],
+        )?;
+        let ui = render_ui(app).replace('\u{35f}', "");
+        let lines = ui.lines().collect::<Vec<_>>();
+        assert!(lines[0].contains("cpu/thread"));
+        assert!(lines[2].contains("7.5%"));
+        Ok(())
+    }
+
+    #[test]
+    fn shows_cpu_history_sparklines() -> R<()> {
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(Vec::new()),
+            &Args::parse_from(["treetop", "--cpu-history"]).options(),
+        )?;
+        for cpu in [100.0, 50.0, 0.0] {
+            app.process_watcher = ProcessWatcher::fake(vec![Process::fake(1, cpu, None)]);
+            app.tick()?;
+        }
+        let ui = render_ui(app).replace('\u{35f}', "");
+        let lines = ui.lines().collect::<Vec<_>>();
+        assert!(lines[0].contains("history"));
+        assert!(lines[2].contains("█▄  ┃"));
+        Ok(())
+    }
+
+    #[test]
+    fn cpu_history_column_is_as_wide_as_its_sparkline() -> R<()> {
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![Process::fake(1, 100.0, None)]),
+            &Args::parse_from(["treetop", "--cpu-history"]).options(),
+        )?;
+        for _ in 0..8 {
+            app.tick()?;
+        }
+        let ui = render_ui(app).replace('\u{35f}', "");
+        let lines = ui.lines().collect::<Vec<_>>();
+        assert!(lines[0].contains("ram  history ┃"), "{}", lines[0]);
+        assert!(lines[2].contains("0MB ████████ ┃"), "{}", lines[2]);
+        Ok(())
+    }
+
+    #[test]
+    fn filter_bar_shows_active_filters_as_chips() -> R<()> {
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![
+                Process::fake(1, 0.0, None),
+                Process::fake(2, 0.0, Some(1)),
+                Process::fake(3, 0.0, None),
+            ]),
+            &Args::parse_from([
+                "treetop",
+                "--pattern",
+                "two",
+                "--pattern",
+                "tty:pts",
+                "--leaves",
+            ])
+            .options(),
+        )?;
+        app.tick()?;
+        simulate_key_press(&mut app, KeyCode::Char('-'))?;
+        assert_eq!(app.ui_mode, UiMode::EditingFilters);
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn number_keys_remove_filters_from_the_filter_bar() -> R<()> {
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![
+                Process::fake(1, 0.0, None),
+                Process::fake(2, 0.0, Some(1)),
+            ]),
+            &Args::parse_from([
+                "treetop",
+                "--pattern",
+                "one",
+                "--pattern",
+                "two",
+                "--leaves",
+            ])
+            .options(),
+        )?;
+        app.tick()?;
+        simulate_key_press(&mut app, KeyCode::Char('-'))?;
+        simulate_key_press(&mut app, KeyCode::Char('1'))?;
+        assert_eq!(app.patterns_string(), "two");
+        simulate_key_press(&mut app, KeyCode::Char('2'))?;
+        assert!(!app.only_leaves);
+        simulate_key_press(&mut app, KeyCode::Char('1'))?;
+        assert!(app.active_filters().is_empty());
+        simulate_key_press(&mut app, KeyCode::Char('1'))?;
+        simulate_key_press(&mut app, KeyCode::Esc)?;
+        assert_eq!(app.ui_mode, UiMode::Normal);
+        assert_eq!(app.forest.iter().count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn filter_builder_lists_the_filterable_fields() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 1.0, None),
+            Process::fake(2, 3.0, None),
+        ])?;
+        simulate_key_press(&mut app, KeyCode::Char('#'))?;
+        assert_eq!(app.ui_mode, UiMode::FilterBuilder(None));
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn filter_builder_adds_conditions_that_narrow_the_forest() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 1.0, None),
+            Process::fake(2, 3.0, None),
+            Process::fake(3, 5.0, None),
+        ])?;
+        simulate_key_press(&mut app, KeyCode::Char('#'))?;
+        simulate_key_press(&mut app, KeyCode::Char('1'))?;
+        for key in "> 2".chars() {
+            simulate_key_press(&mut app, KeyCode::Char(key))?;
+        }
+        simulate_key_press(&mut app, KeyCode::Enter)?;
+        assert_eq!(app.ui_mode, UiMode::Normal);
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![2.into(), 3.into()]
+        );
+        assert_eq!(
+            app.active_filters()
+                .into_iter()
+                .map(|(_, label)| label)
+                .collect::<Vec<_>>(),
+            vec!["cpu > 2"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn filter_builder_conditions_apply_to_revealed_siblings() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 5.0, None),
+            Process::fake(2, 1.0, Some(1)),
+            Process::fake(3, 3.0, Some(1)),
+            Process::fake(4, 4.0, Some(1)),
+        ])?;
+        app.conditions
+            .push(Condition::parse(ConditionField::Cpu, "> 2")?);
+        set_pattern(&mut app, "three")?;
+        app.tick()?;
+        simulate_key_press(&mut app, KeyCode::Down)?;
+        simulate_key_press(&mut app, KeyCode::Char('z'))?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![1.into(), 3.into(), 4.into()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn clearing_the_pattern_removes_filter_builder_conditions() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 1.0, None),
+            Process::fake(2, 3.0, None),
+        ])?;
+        app.conditions
+            .push(Condition::parse(ConditionField::Cpu, "> 2")?);
+        app.tick()?;
+        assert_eq!(app.forest.iter().count(), 1);
+        app.update(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL))?;
+        assert!(app.conditions.is_empty());
+        assert_eq!(app.forest.iter().count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn filter_builder_reports_invalid_values() -> R<()> {
+        let mut app = test_app(vec![Process::fake(1, 1.0, None)])?;
+        simulate_key_press(&mut app, KeyCode::Char('#'))?;
+        simulate_key_press(&mut app, KeyCode::Char('4'))?;
+        for key in "> root".chars() {
+            simulate_key_press(&mut app, KeyCode::Char(key))?;
+        }
+        simulate_key_press(&mut app, KeyCode::Enter)?;
+        assert_eq!(
+            app.ui_mode,
+            UiMode::FilterBuilder(Some(ConditionField::User))
+        );
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("users can only be compared with =")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn processes_can_be_sorted_by_descendants() -> R<()> {
+        let mut app = app_with_descendants(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, None),
+            Process::fake(3, 0.0, Some(2)),
+            Process::fake(4, 0.0, Some(2)),
+            Process::fake(5, 0.0, Some(4)),
+        ])?;
+        app.sort_column = SortBy::Descendants;
+        app.sort_direction = SortDirection::Descending;
+        app.tick()?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![2.into(), 4.into(), 5.into(), 3.into(), 1.into()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn shows_thread_counts() -> R<()> {
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![
+                Process::fake(1, 0.0, None).with_threads(3),
+                Process::fake(2, 0.0, Some(1)).with_threads(2),
+                Process::fake(3, 0.0, None),
+            ]),
+            &Args::parse_from(["treetop", "--threads"]).options(),
+        )?;
+        app.tick()?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn processes_can_be_sorted_by_threads() -> R<()> {
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![
+                Process::fake(1, 0.0, None),
+                Process::fake(2, 0.0, None).with_threads(4),
+                Process::fake(3, 0.0, None).with_threads(2),
+            ]),
+            &Args::parse_from(["treetop", "--threads"]).options(),
+        )?;
+        while app.sort_column != SortBy::Threads {
+            simulate_key_press(&mut app, KeyCode::Tab)?;
+        }
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![2.into(), 3.into(), 1.into()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sorting_skips_hidden_columns() -> R<()> {
+        let mut app = test_app(vec![])?;
+        let mut sort_columns = Vec::new();
+        for _ in 0..4 {
+            simulate_key_press(&mut app, KeyCode::Tab)?;
+            sort_columns.push(app.sort_column);
+        }
+        assert_eq!(
+            sort_columns,
+            vec![SortBy::Cpu, SortBy::Ram, SortBy::Pid, SortBy::Cpu]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn marks_rows_that_include_accumulated_values() -> R<()> {
+        let app = test_app(vec![
+            Process::fake(1, 1.0, None),
+            Process::fake(2, 2.0, Some(1)),
+            Process::fake(3, 3.0, None),
+        ])?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn colors_tree_lines_by_depth() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(2)),
+            Process::fake(4, 0.0, Some(3)),
+            Process::fake(5, 0.0, Some(1)),
+        ])?;
+        simulate_key_press(&mut app, KeyCode::Char('y'))?;
+        let area = Rect::new(0, 0, 80, 10);
+        let mut buffer = Buffer::empty(area);
+        app.render(area, &mut buffer);
+        // Each tree line cell followed by the index of its color.
+        let rows = (2..7).map(|y| {
+            (0..area.width)
+                .filter_map(|x| {
+                    let cell = &buffer[(x, y)];
+                    let level = DEPTH_COLORS.iter().position(|color| *color == cell.fg)?;
+                    Some(format!("{}{}", cell.symbol(), level))
+                })
+                .collect::<String>()
+        });
+        assert_snapshot!(rows.collect::<Vec<_>>().join("\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn tree_lines_are_blue_without_depth_colors() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(2)),
+        ])?;
+        let area = Rect::new(0, 0, 80, 10);
+        let mut buffer = Buffer::empty(area);
+        app.render(area, &mut buffer);
+        let prefix_colors = (0..area.width)
+            .map(|x| &buffer[(x, 4)])
+            .filter(|cell| "├└─┬│".contains(cell.symbol()))
+            .map(|cell| cell.fg)
+            .collect::<HashSet<_>>();
+        assert_eq!(prefix_colors, HashSet::from([Color::Blue]));
+        Ok(())
+    }
+
+    #[test]
+    fn heat_map_colors_cpu_cells() -> R<()> {
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![
+                Process::fake(1, 95.0, None),
+                Process::fake(2, 60.0, None),
+                Process::fake(3, 10.0, None),
+            ]),
+            &Args::parse_from(["treetop", "--heat-map", "--cpu-thresholds", "50,90"]).options(),
+        )?;
+        app.tick()?;
+        let area = Rect::new(0, 0, 80, 10);
+        let mut buffer = Buffer::empty(area);
+        app.render(area, &mut buffer);
+        let cpu_column = 12;
+        assert_eq!(
+            (2..5)
+                .map(|y| buffer[(cpu_column, y)].fg)
+                .collect::<Vec<_>>(),
+            vec![Color::Red, Color::Yellow, Color::Green]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn collapsing_and_expanding_the_whole_tree() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(2)),
+            Process::fake(4, 0.0, None),
+            Process::fake(5, 0.0, Some(4)),
+        ])?;
+        simulate_key_press(&mut app, KeyCode::Char('*'))?;
+        assert_eq!(
+            app.forest.render_forest_prefixes(&app.prefix_style).len(),
+            2
+        );
+        simulate_key_press(&mut app, KeyCode::Char('_'))?;
+        assert_eq!(
+            app.forest.render_forest_prefixes(&app.prefix_style).len(),
+            5
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn collapsing_the_whole_tree_selects_the_visible_ancestor() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(2)),
+            Process::fake(4, 0.0, None),
+            Process::fake(5, 0.0, Some(4)),
+        ])?;
+        app.list_state.select(Some(4));
+        simulate_key_press(&mut app, KeyCode::Char('*'))?;
+        assert_eq!(app.list_state.selected(), Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn state_stays_attached_to_reparented_processes() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(2)),
+            Process::fake(4, 0.0, Some(1)),
+            Process::fake(5, 0.0, Some(3)),
+        ])?;
+        app.collapsed.insert(3.into());
+        app.list_state.select(Some(2));
+        simulate_key_press(&mut app, KeyCode::Enter)?;
+        assert_eq!(app.ui_mode, UiMode::ProcessSelected(3.into()));
+        app.process_watcher = ProcessWatcher::fake(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(4)),
+            Process::fake(4, 0.0, Some(1)),
+            Process::fake(5, 0.0, Some(3)),
+        ]);
+        app.tick()?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![1.into(), 2.into(), 4.into(), 3.into()]
+        );
+        assert_eq!(app.list_state.selected(), Some(3));
+        assert_eq!(app.ui_mode, UiMode::ProcessSelected(3.into()));
+        Ok(())
+    }
+
+    #[test]
+    fn collapsed_state_survives_restarts_when_collapsing_by_name() -> R<()> {
+        let mut app = test_app_with_args(
+            vec![
+                Process::fake(1, 0.0, None),
+                Process::fake(2, 0.0, Some(1)),
+                Process::fake(3, 0.0, Some(2)),
+            ],
+            &["--collapse-by-name"],
+        )?;
+        simulate_key_press(&mut app, KeyCode::Char('*'))?;
+        assert_eq!(app.forest.iter().count(), 1);
+        let mut restarted = Process::fake(5, 0.0, None);
+        restarted.name = "one".to_string();
+        app.process_watcher = ProcessWatcher::fake(vec![restarted, Process::fake(6, 0.0, Some(5))]);
+        app.tick()?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![5.into()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn collapsed_state_is_keyed_by_pid_by_default() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+        ])?;
+        simulate_key_press(&mut app, KeyCode::Char('*'))?;
+        let mut restarted = Process::fake(5, 0.0, None);
+        restarted.name = "one".to_string();
+        app.process_watcher = ProcessWatcher::fake(vec![restarted, Process::fake(6, 0.0, Some(5))]);
+        app.tick()?;
+        assert_eq!(app.forest.iter().count(), 2);
+        Ok(())
+    }
+
+    fn app_accumulating_collapsed() -> R<TreetopApp> {
+        let app = test_app_with_args(
+            vec![
+                Process::fake(1, 10.0, None),
+                Process::fake(2, 20.0, Some(1)),
+                Process::fake(3, 30.0, Some(2)),
+            ],
+            &["--accumulate-collapsed"],
+        )?;
+        Ok(app)
+    }
+
+    // The cpu cells of all rows, top to bottom.
+    fn shown_cpu(app: &mut TreetopApp) -> Vec<String> {
+        let area = Rect::new(0, 0, 80, 10);
+        let mut buffer = Buffer::empty(area);
+        app.render(area, &mut buffer);
+        (2..2 + app.forest.iter().count() as u16)
+            .map(|y| {
+                (9..14)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+                    .trim()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn accumulate_collapsed_shows_own_values_for_expanded_processes() -> R<()> {
+        let mut app = app_accumulating_collapsed()?;
+        assert_eq!(shown_cpu(&mut app), ["10%", "20%", "30%"]);
+        Ok(())
+    }
+
+    #[test]
+    fn accumulate_collapsed_shows_subtree_totals_for_collapsed_processes() -> R<()> {
+        let mut app = app_accumulating_collapsed()?;
+        simulate_key_press(&mut app, KeyCode::Char('*'))?;
+        assert_eq!(shown_cpu(&mut app), ["60%"]);
+        app.set_collapsed(HashSet::from([2.into()]));
+        app.update_processes();
+        assert_eq!(shown_cpu(&mut app), ["10%", "50%"]);
+        Ok(())
+    }
+
+    #[test]
+    fn accumulate_collapsed_sorts_by_the_shown_values() -> R<()> {
+        let mut app = test_app_with_args(
+            vec![
+                Process::fake(1, 10.0, None),
+                Process::fake(2, 50.0, Some(1)),
+                Process::fake(3, 30.0, None),
+            ],
+            &["--accumulate-collapsed", "--sort", "cpu"],
+        )?;
+        assert_eq!(shown_cpu(&mut app), ["30%", "10%", "50%"]);
+        app.set_collapsed(HashSet::from([1.into()]));
+        app.update_processes();
+        assert_eq!(shown_cpu(&mut app), ["60%", "30%"]);
+        Ok(())
+    }
+
+    fn type_signal(app: &mut TreetopApp, signal: &str) -> R<()> {
+        simulate_key_press(app, KeyCode::Enter)?;
+        simulate_key_press(app, KeyCode::Char(':'))?;
+        for key in signal.chars() {
+            simulate_key_press(app, KeyCode::Char(key))?;
+        }
+        simulate_key_press(app, KeyCode::Enter)?;
+        Ok(())
+    }
+
+    #[test]
+    fn signaling_pids_beyond_the_range_of_kill_shows_an_error() -> R<()> {
+        let mut app = test_app(vec![Process::fake(1, 0.0, None).with_pid(u32::MAX)])?;
+        app.signal_sender = SignalSender::fake();
+        simulate_key_press(&mut app, KeyCode::Enter)?;
+        simulate_key_press(&mut app, KeyCode::Char('t'))?;
+        assert_eq!(app.signal_sender.sent(), &[]);
+        assert_eq!(
+            app.ui_mode,
+            UiMode::ProcessSelected(sysinfo::Pid::from_u32(u32::MAX))
+        );
+        assert!(render_ui_with_width(app, 300)
+            .replace('\u{35f}', "")
+            .contains("can't send SIGTERM: pid 4294967295 is too large to be signaled"));
+        Ok(())
+    }
+
+    #[test]
+    fn sending_signals_by_name() -> R<()> {
+        let mut app = test_app(vec![Process::fake(1, 0.0, None)])?;
+        app.signal_sender = SignalSender::fake();
+        type_signal(&mut app, "SIGUSR1")?;
+        assert_eq!(app.signal_sender.sent(), &[(1.into(), Signal::SIGUSR1)]);
+        assert_eq!(app.ui_mode, UiMode::ProcessSelected(1.into()));
+        Ok(())
+    }
+
+    #[test]
+    fn sending_signals_by_number() -> R<()> {
+        let mut app = test_app(vec![Process::fake(1, 0.0, None)])?;
+        app.signal_sender = SignalSender::fake();
+        type_signal(&mut app, "9")?;
+        simulate_key_press(&mut app, KeyCode::Char('y'))?;
+        assert_eq!(app.signal_sender.sent(), &[(1.into(), Signal::SIGKILL)]);
+        Ok(())
+    }
+
+    #[test]
+    fn sending_sigusr1_and_sigusr2() -> R<()> {
+        let mut app = test_app(vec![Process::fake(1, 0.0, None)])?;
+        app.signal_sender = SignalSender::fake();
+        simulate_key_press(&mut app, KeyCode::Enter)?;
+        simulate_key_press(&mut app, KeyCode::Char('1'))?;
+        assert_eq!(app.signal_sender.sent(), &[(1.into(), Signal::SIGUSR1)]);
+        assert_eq!(app.ui_mode, UiMode::ProcessSelected(1.into()));
+        simulate_key_press(&mut app, KeyCode::Char('2'))?;
+        assert_eq!(
+            app.signal_sender.sent(),
+            &[(1.into(), Signal::SIGUSR1), (1.into(), Signal::SIGUSR2)]
+        );
+        assert_eq!(app.ui_mode, UiMode::ProcessSelected(1.into()));
+        Ok(())
+    }
+
+    #[test]
+    fn digits_only_send_signals_to_selected_processes() -> R<()> {
+        let mut app = test_app(vec![Process::fake(1, 0.0, None)])?;
+        app.signal_sender = SignalSender::fake();
+        simulate_key_press(&mut app, KeyCode::Char('1'))?;
+        assert_eq!(app.signal_sender.sent(), &[]);
+        Ok(())
+    }
+
+    #[test]
+    fn only_configured_signals_need_confirmation() -> R<()> {
+        let mut app = test_app(vec![Process::fake(1, 0.0, None)])?;
+        app.signal_sender = SignalSender::fake();
+        simulate_key_press(&mut app, KeyCode::Enter)?;
+        simulate_key_press(&mut app, KeyCode::Char('t'))?;
+        assert_eq!(app.signal_sender.sent(), &[(1.into(), Signal::SIGTERM)]);
+        assert_eq!(app.ui_mode, UiMode::ProcessSelected(1.into()));
+        simulate_key_press(&mut app, KeyCode::Char('k'))?;
+        assert_eq!(app.signal_sender.sent().len(), 1);
+        assert!(matches!(app.ui_mode, UiMode::ConfirmingSignal(_)));
+        simulate_key_press(&mut app, KeyCode::Char('n'))?;
+        assert_eq!(app.signal_sender.sent().len(), 1);
+        assert_eq!(app.ui_mode, UiMode::ProcessSelected(1.into()));
+        simulate_key_press(&mut app, KeyCode::Char('k'))?;
+        simulate_key_press(&mut app, KeyCode::Char('y'))?;
+        assert_eq!(
+            app.signal_sender.sent(),
+            &[(1.into(), Signal::SIGTERM), (1.into(), Signal::SIGKILL)]
+        );
+        assert_eq!(app.ui_mode, UiMode::ProcessSelected(1.into()));
+        Ok(())
+    }
+
+    fn quick_signals_app() -> R<TreetopApp> {
+        let mut app = test_app_with_args(
+            vec![
+                Process::fake(1, 0.0, None),
+                Process::fake(2, 0.0, Some(1)),
+                Process::fake(3, 0.0, Some(1)),
+            ],
+            &["--quick-signals"],
+        )?;
+        app.signal_sender = SignalSender::fake();
+        Ok(app)
+    }
+
+    #[test]
+    fn quick_signals_go_to_the_highlighted_row() -> R<()> {
+        let mut app = quick_signals_app()?;
+        simulate_key_press(&mut app, KeyCode::Down)?;
+        simulate_key_press(&mut app, KeyCode::Char('t'))?;
+        assert_eq!(app.signal_sender.sent(), &[]);
+        assert!(matches!(app.ui_mode, UiMode::ConfirmingSignal(_)));
+        simulate_key_press(&mut app, KeyCode::Char('y'))?;
+        assert_eq!(app.signal_sender.sent(), &[(2.into(), Signal::SIGTERM)]);
+        assert_eq!(app.ui_mode, UiMode::Normal);
+        simulate_key_press(&mut app, KeyCode::Down)?;
+        simulate_key_press(&mut app, KeyCode::Char('k'))?;
+        simulate_key_press(&mut app, KeyCode::Char('y'))?;
+        assert_eq!(
+            app.signal_sender.sent(),
+            &[(2.into(), Signal::SIGTERM), (3.into(), Signal::SIGKILL)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn quick_signals_can_be_cancelled() -> R<()> {
+        let mut app = quick_signals_app()?;
+        simulate_key_press(&mut app, KeyCode::Char('k'))?;
+        simulate_key_press(&mut app, KeyCode::Char('n'))?;
+        assert_eq!(app.signal_sender.sent(), &[]);
+        assert_eq!(app.ui_mode, UiMode::Normal);
+        Ok(())
+    }
+
+    #[test]
+    fn quick_signals_need_to_be_enabled() -> R<()> {
+        let mut app = test_app(vec![Process::fake(1, 0.0, None)])?;
+        app.signal_sender = SignalSender::fake();
+        simulate_key_press(&mut app, KeyCode::Char('k'))?;
+        assert_eq!(app.ui_mode, UiMode::Normal);
+        assert_eq!(app.signal_sender.sent(), &[]);
+        Ok(())
+    }
+
+    #[test]
+    fn confirmed_signals_can_be_configured() -> R<()> {
+        let app_confirming = |args: &[&str]| -> R<TreetopApp> {
+            let mut app = TreetopApp::new(
+                ProcessWatcher::fake(vec![Process::fake(1, 0.0, None)]),
+                &Args::parse_from(args).options(),
+            )?;
+            app.tick()?;
+            app.signal_sender = SignalSender::fake();
+            simulate_key_press(&mut app, KeyCode::Enter)?;
+            Ok(app)
+        };
+        let mut app = app_confirming(&["treetop", "--confirm-signals", "SIGTERM,SIGHUP"])?;
+        simulate_key_press(&mut app, KeyCode::Char('k'))?;
+        assert_eq!(app.signal_sender.sent(), &[(1.into(), Signal::SIGKILL)]);
+        simulate_key_press(&mut app, KeyCode::Char('t'))?;
+        assert_eq!(app.signal_sender.sent().len(), 1);
+        assert!(matches!(app.ui_mode, UiMode::ConfirmingSignal(_)));
+
+        let mut app = app_confirming(&["treetop", "--confirm-signals"])?;
+        simulate_key_press(&mut app, KeyCode::Char('k'))?;
+        assert_eq!(app.signal_sender.sent(), &[(1.into(), Signal::SIGKILL)]);
+        Ok(())
+    }
+
+    #[test]
+    fn read_only_mode_sends_no_signals() -> R<()> {
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![Process::fake(1, 0.0, None)]),
+            &Args::parse_from(["treetop", "--read-only"]).options(),
+        )?;
+        app.tick()?;
+        app.signal_sender = SignalSender::fake();
+        simulate_key_press(&mut app, KeyCode::Enter)?;
+        for key in ['t', 'k', 'w', ':'] {
+            simulate_key_press(&mut app, KeyCode::Char(key))?;
+        }
+        assert_eq!(app.signal_sender.sent(), &[]);
+        assert_eq!(app.ui_mode, UiMode::ProcessSelected(1.into()));
+        assert_eq!(app.status_message, Some("read-only mode".to_string()));
+        let ui = render_ui_with_width(app, 200).replace('\u{35f}', "");
+        assert!(!ui.contains("SIGTERM"));
+        assert!(ui.contains("read-only mode"));
+        Ok(())
+    }
+
+    #[test]
+    fn terminate_and_wait_reports_when_the_process_is_gone() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, None),
+        ])?;
+        app.signal_sender = SignalSender::fake();
+        simulate_key_press(&mut app, KeyCode::Enter)?;
+        simulate_key_press(&mut app, KeyCode::Char('w'))?;
+        assert_eq!(app.signal_sender.sent(), &[(1.into(), Signal::SIGTERM)]);
+        assert_eq!(
+            app.kill_status,
+            Some("waiting for 1 to terminate".to_string())
+        );
+        app.tick()?;
+        assert_eq!(
+            app.kill_status,
+            Some("waiting for 1 to terminate".to_string())
+        );
+        app.process_watcher = ProcessWatcher::fake(vec![Process::fake(2, 0.0, None)]);
+        app.tick()?;
+        assert_eq!(app.kill_status, Some("1 terminated".to_string()));
+        assert!(app.pending_kills.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn terminate_and_wait_reports_processes_that_keep_running() -> R<()> {
+        let mut app = test_app(vec![Process::fake(1, 0.0, None)])?;
+        app.signal_sender = SignalSender::fake();
+        simulate_key_press(&mut app, KeyCode::Enter)?;
+        simulate_key_press(&mut app, KeyCode::Char('w'))?;
+        app.pending_kills
+            .insert(1.into(), Instant::now() - Duration::from_secs(6));
+        app.tick()?;
+        assert_eq!(
+            app.kill_status,
+            Some("1 still running after 6s".to_string())
+        );
+        assert!(app.pending_kills.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_signal_names_show_an_error() -> R<()> {
+        let mut app = test_app(vec![Process::fake(1, 0.0, None)])?;
+        app.signal_sender = SignalSender::fake();
+        type_signal(&mut app, "SIGFOO")?;
+        assert_eq!(app.signal_sender.sent(), &[]);
+        assert_eq!(app.ui_mode, UiMode::EnteringSignal(1.into()));
+        assert_eq!(
+            app.status_message,
+            Some("unknown signal: SIGFOO".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn custom_status_bar_format() -> R<()> {
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![
+                Process::fake(1, 0.0, None),
+                Process::fake(2, 0.0, Some(1)),
+                Process::fake(3, 0.0, None),
+            ]),
+            &Args::parse_from([
+                "treetop",
+                "--pattern",
+                "two",
+                "--status-bar",
+                "{count} shown | sort: {sort} | pattern: {pattern}",
+            ])
+            .options(),
+        )?;
+        app.tick()?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn marks_processes_started_after_launch() -> R<()> {
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![Process::fake(1, 0.0, None)]),
+            &Args::parse_from(["treetop", "--differences"]).options(),
+        )?;
+        app.tick()?;
+        app.process_watcher = ProcessWatcher::fake(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+        ]);
+        app.tick()?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn wide_layout_shows_own_and_accumulated_values() -> R<()> {
+        let app = test_app_with_args(
+            vec![
+                Process::fake(1, 4.0, None),
+                Process::fake(2, 3.0, Some(1)),
+                Process::fake(3, 2.0, Some(2)),
+            ],
+            &["--wide"],
+        )?;
+        assert_snapshot!(render_ui_with_width(app, 120));
+        Ok(())
+    }
+
+    #[test]
+    fn formats_ram_with_the_given_locale() -> R<()> {
+        let ram = |locale: &str| -> R<String> {
+            let app = test_app_with_args(
+                vec![Process::fake(1, 0.0, None).with_ram(1234 * 2_u64.pow(20))],
+                &["--locale", locale],
+            )?;
+            Ok(render_ui(app)
+                .lines()
+                .nth(2)
+                .unwrap()
+                .split_whitespace()
+                .nth(2)
+                .unwrap()
+                .to_string())
+        };
+        assert_eq!(ram("en")?, "1,234MB");
+        assert_eq!(ram("de")?, "1.234MB");
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unknown_locales() {
+        assert!(Args::try_parse_from(["treetop", "--locale", "nope"]).is_err());
+    }
+
+    #[test]
+    fn rejects_backends_together_with_other_process_sources() {
+        assert!(Args::try_parse_from(["treetop", "--backend", "proc", "--replay", "x"]).is_err());
+        assert!(
+            Args::try_parse_from(["treetop", "--backend", "proc", "--ps-command", "ps"]).is_err()
+        );
+        assert!(Args::try_parse_from(["treetop", "--backend", "proc"]).is_ok());
+    }
+
+    #[test]
+    fn rejects_idle_intervals_shorter_than_a_tick() -> R<()> {
+        assert!(Args::try_parse_from(["treetop", "--idle-interval", "0"]).is_err());
+        let app = TreetopApp::new(
+            ProcessWatcher::fake(vec![]),
+            &Args::try_parse_from(["treetop", "--idle-interval", "5"])?.options(),
+        )?;
+        assert_eq!(app.idle_tick_length, Some(Duration::from_secs(5)));
+        Ok(())
+    }
+
+    #[test]
+    fn shows_ram_as_a_percentage_of_total_memory() -> R<()> {
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![
+                Process::fake(1, 0.0, None).with_ram(256 * 2_u64.pow(20)),
+                Process::fake(2, 0.0, Some(1)).with_ram(256 * 2_u64.pow(20)),
+            ])
+            .with_total_memory(1024 * 2_u64.pow(20)),
+            &Args::parse_from(["treetop", "--ram-percent"]).options(),
+        )?;
+        app.tick()?;
+        let rows = render_ui(app)
+            .lines()
+            .skip(2)
+            .take(2)
+            .map(|line| {
+                line.split('┃')
+                    .next()
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(rows, vec!["1 0% 50%+", "2 0% 25%"]);
+        Ok(())
+    }
+
+    #[test]
+    fn shows_both_ram_halves_as_percentages_when_wide() -> R<()> {
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![
+                Process::fake(1, 0.0, None).with_ram(256 * 2_u64.pow(20)),
+                Process::fake(2, 0.0, Some(1)).with_ram(256 * 2_u64.pow(20)),
+            ])
+            .with_total_memory(1024 * 2_u64.pow(20)),
+            &Args::parse_from(["treetop", "--ram-percent", "--wide"]).options(),
+        )?;
+        app.tick()?;
+        let rows = render_ui_with_width(app, 120)
+            .lines()
+            .skip(2)
+            .take(2)
+            .map(|line| {
+                line.split('┃')
+                    .next()
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(rows, vec!["1 0%/0% 25%/50%+", "2 0%/0% 25%/25%"]);
+        Ok(())
+    }
+
+    #[test]
+    fn ram_percentage_falls_back_to_mb_without_total_memory() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None).with_ram(256 * 2_u64.pow(20))
+        ])?;
+        simulate_key_press(&mut app, KeyCode::Char('m'))?;
+        assert!(render_ui(app).contains("256MB"));
+        Ok(())
+    }
+
+    #[test]
+    fn cpu_can_be_divided_by_the_number_of_cpus() -> R<()> {
+        let mut app = test_app(vec![])?;
+        app.process_watcher = ProcessWatcher::fake(vec![
+            Process::fake(1, 800.0, None),
+            Process::fake(2, 200.0, None),
+        ])
+        .with_cpu_count(8);
+        app.tick()?;
+        assert_eq!(shown_cpu(&mut app), ["800%", "200%"]);
+        simulate_key_press(&mut app, KeyCode::Char('%'))?;
+        assert_eq!(shown_cpu(&mut app), ["100%", "25%"]);
+        Ok(())
+    }
+
+    #[test]
+    fn per_cpu_falls_back_to_plain_cpu_without_a_cpu_count() -> R<()> {
+        let app = test_app_with_args(vec![Process::fake(1, 800.0, None)], &["--per-cpu"])?;
+        assert!(render_ui(app).contains("800%"));
+        Ok(())
+    }
+
+    #[test]
+    fn renders_nan_cpu_as_zero() -> R<()> {
+        let app = test_app(vec![
+            Process::fake(1, 1.0, None),
+            Process::fake(2, f32::NAN, Some(1)),
+        ])?;
+        let rendered = render_ui(app);
+        assert!(!rendered.contains("NaN"));
+        assert_snapshot!(rendered);
+        Ok(())
+    }
+
+    #[test]
+    fn accumulates_cpu_but_not_ram_with_own_ram() -> R<()> {
+        let app = test_app_with_args(
+            vec![
+                Process::fake(1, 1.0, None).with_ram(2_u64.pow(20)),
+                Process::fake(2, 2.0, Some(1)).with_ram(2 * 2_u64.pow(20)),
+            ],
+            &["--own-ram"],
+        )?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn wide_layout_is_not_used_on_narrow_terminals() -> R<()> {
+        let mut app = test_app_with_args(vec![Process::fake(1, 4.0, None)], &["--wide"])?;
+        let area = Rect::new(0, 0, 80, 10);
+        app.render(area, &mut Buffer::empty(area));
+        assert_eq!(
+            app.table_layout,
+            Some(TableLayout::new(
+                &app.columns,
+                CellFormat::default(),
+                app.forest.iter_with_depth(),
+                &HashMap::new(),
+                &[],
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn more_complicated_tree() -> R<()> {
+        let app = test_app(vec![
+            Process::fake(1, 1.0, None),
+            Process::fake(2, 2.0, Some(1)),
+            Process::fake(3, 3.0, Some(2)),
+            Process::fake(4, 4.0, Some(1)),
+            Process::fake(5, 5.0, Some(4)),
+            Process::fake(6, 5.0, Some(4)),
+            Process::fake(7, 5.0, Some(6)),
+        ])?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn filtering() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 1.0, None),
+            Process::fake(2, 2.0, Some(1)),
+            Process::fake(3, 3.0, Some(2)),
+            Process::fake(4, 4.0, Some(1)),
+            Process::fake(5, 5.0, Some(4)),
+            Process::fake(6, 5.0, Some(4)),
+            Process::fake(7, 5.0, Some(6)),
+        ])?;
+        set_pattern(&mut app, "four")?;
+        app.tick()?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn filtering_with_regexes() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(1)),
+            Process::fake(4, 0.0, Some(1)),
+        ])?;
+        set_pattern(&mut app, "two|three")?;
+        app.tick()?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn filtering_by_pid() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, None),
+            Process::fake(3, 0.0, None),
+        ])?;
+        set_pattern(&mut app, "2")?;
+        app.tick()?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn shows_a_message_when_no_process_matches() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+        ])?;
+        set_pattern(&mut app, "nothing")?;
+        app.tick()?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn shows_processes_matching_any_of_multiple_patterns() -> R<()> {
+        let processes = vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(1)),
+            Process::fake(4, 0.0, None),
+        ];
+        let app = test_app_with_args(
+            processes.clone(),
+            &["--pattern", "two", "--pattern", "four"],
+        )?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![1.into(), 2.into(), 4.into()]
+        );
+        let app = test_app_with_args(processes, &["two", "--pattern", "four"])?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![1.into(), 2.into(), 4.into()]
+        );
+        assert_snapshot!(render_ui_with_width(app, 120));
+        Ok(())
+    }
+
+    #[test]
+    fn patterns_with_commas_are_not_split() -> R<()> {
+        let app = test_app_with_args(
+            vec![
+                Process::fake(1, 0.0, None),
+                Process::fake(2, 0.0, None),
+                Process::fake(3, 0.0, None),
+            ],
+            &["--pattern", "^t(wo|h{1,2}ree)$"],
+        )?;
+        assert_eq!(app.patterns_string(), "^t(wo|h{1,2}ree)$");
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![2.into(), 3.into()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn hiding_treetop_itself() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(2)),
+            Process::fake(4, 0.0, Some(1)),
+        ])?;
+        app.own_pid = 2.into();
+        simulate_key_press(&mut app, KeyCode::Char('h'))?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![1.into(), 4.into()]
+        );
+        simulate_key_press(&mut app, KeyCode::Char('h'))?;
+        assert_eq!(app.forest.iter().count(), 4);
+        Ok(())
+    }
+
+    fn app_with_long_arguments() -> R<TreetopApp> {
+        let arguments = ["/usr/bin/java", "-Xmx4g", "-Dfoo=bar", "-jar", "app.jar"];
+        test_app(vec![
+            Process::fake(1, 0.0, None).with_arguments(&arguments),
+            Process::fake(2, 0.0, Some(1)).with_arguments(&arguments),
+        ])
+    }
+
+    #[test]
+    fn shows_command_line_arguments() -> R<()> {
+        assert_snapshot!(render_ui(app_with_long_arguments()?));
+        Ok(())
+    }
+
+    #[test]
+    fn hiding_command_line_arguments() -> R<()> {
+        let mut app = app_with_long_arguments()?;
+        simulate_key_press(&mut app, KeyCode::Char('e'))?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    fn app_for_labels() -> R<TreetopApp> {
+        test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(1)),
+        ])
+    }
+
+    #[test]
+    fn labeling_processes_by_pid() -> R<()> {
+        let mut app = app_for_labels()?;
+        simulate_key_press(&mut app, KeyCode::Char('p'))?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn labeling_processes_by_name_again() -> R<()> {
+        let mut app = app_for_labels()?;
+        simulate_key_press(&mut app, KeyCode::Char('p'))?;
+        simulate_key_press(&mut app, KeyCode::Char('p'))?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn showing_only_leaves() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(2)),
+            Process::fake(4, 0.0, Some(1)),
+            Process::fake(5, 0.0, None),
+        ])?;
+        simulate_key_press(&mut app, KeyCode::Char('l'))?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![3.into(), 4.into(), 5.into()]
+        );
+        simulate_key_press(&mut app, KeyCode::Char('l'))?;
+        assert_eq!(app.forest.iter().count(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn q_quits_in_normal_mode() -> R<()> {
+        let mut app = test_app(vec![Process::fake(1, 0.0, None)])?;
+        assert_eq!(
+            app.update(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE))?,
+            UpdateResult::Exit
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn q_asks_for_confirmation_when_processes_are_marked() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, None),
+        ])?;
+        simulate_key_press(&mut app, KeyCode::Char(' '))?;
+        assert_eq!(
+            simulate_key_press(&mut app, KeyCode::Char('q'))?,
+            UpdateResult::Continue
+        );
+        assert_eq!(app.ui_mode, UiMode::ConfirmingQuit);
+        assert_eq!(
+            simulate_key_press(&mut app, KeyCode::Char('n'))?,
+            UpdateResult::Continue
+        );
+        assert_eq!(app.ui_mode, UiMode::Normal);
+        simulate_key_press(&mut app, KeyCode::Char('q'))?;
+        assert_eq!(
+            simulate_key_press(&mut app, KeyCode::Char('x'))?,
+            UpdateResult::Continue
+        );
+        assert_eq!(app.ui_mode, UiMode::Normal);
+        assert_eq!(app.marked, HashSet::from([1.into()]));
+        simulate_key_press(&mut app, KeyCode::Char('q'))?;
+        assert_eq!(
+            simulate_key_press(&mut app, KeyCode::Char('y'))?,
+            UpdateResult::Exit
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn quit_confirmation_shows_the_number_of_marks() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, None),
+        ])?;
+        simulate_key_press(&mut app, KeyCode::Char('!'))?;
+        simulate_key_press(&mut app, KeyCode::Char('q'))?;
+        assert!(render_ui(app)
+            .replace('\u{35f}', "")
+            .contains("Quit and discard 2 marked? [y/N]"));
+        Ok(())
+    }
+
+    #[test]
+    fn following_shows_only_the_subtree_of_the_command() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(2)),
+            Process::fake(4, 0.0, Some(1)),
+        ])?;
+        app.follow = Some(2.into());
+        app.tick()?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![2.into(), 3.into()]
+        );
+        app.process_watcher = ProcessWatcher::fake(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(2)),
+            Process::fake(5, 0.0, Some(3)),
+        ]);
+        app.tick()?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![2.into(), 3.into(), 5.into()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn exits_with_the_followed_command() -> R<()> {
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![]),
+            &Args::parse_from(["treetop", "--exit-with-command", "--follow", "true"]).options(),
+        )?;
+        app.follow(std::process::Command::new("true").spawn()?);
+        let start = Instant::now();
+        while app.tick()? == UpdateResult::Continue {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(app.followed_command.is_none());
+        Ok(())
+    }
+
+    fn waiting_app() -> R<TreetopApp> {
+        TreetopApp::new(
+            ProcessWatcher::fake(vec![
+                Process::fake(1, 0.0, None),
+                Process::fake(2, 0.0, Some(1)),
+            ]),
+            &Args::parse_from(["treetop", "--wait", "2"]).options(),
+        )
+    }
+
+    #[test]
+    fn waiting_exits_once_the_process_is_gone() -> R<()> {
+        let mut app = waiting_app()?;
+        assert_eq!(app.tick()?, UpdateResult::Continue);
+        app.process_watcher = ProcessWatcher::fake(vec![Process::fake(1, 0.0, None)]);
+        assert_eq!(app.tick()?, UpdateResult::Exit);
+        assert_eq!(app.exit_status().code(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn quitting_while_waiting_fails() -> R<()> {
+        let mut app = waiting_app()?;
+        app.tick()?;
+        simulate_key_press(&mut app, KeyCode::Char('q'))?;
+        assert_eq!(app.exit_status(), ExitStatus::StillRunning);
+        assert_eq!(app.exit_status().code(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn exit_status_reflects_whether_patterns_match() -> R<()> {
+        let mut app = test_app(vec![Process::fake(1, 0.0, None)])?;
+        assert_eq!(app.exit_status(), ExitStatus::Success);
+        set_pattern(&mut app, "nothing")?;
+        app.tick()?;
+        assert_eq!(app.exit_status(), ExitStatus::NoMatches);
+        set_pattern(&mut app, "1")?;
+        app.tick()?;
+        assert_eq!(app.exit_status(), ExitStatus::Success);
+        Ok(())
+    }
+
+    #[test]
+    fn errors_have_their_own_exit_code() {
+        let codes = [
+            ExitStatus::Success,
+            ExitStatus::NoMatches,
+            ExitStatus::StillRunning,
+        ]
+        .map(ExitStatus::code);
+        assert!(!codes.contains(&ExitStatus::Error.code()));
+    }
+
+    #[test]
+    fn once_renders_the_tree_without_the_status_bar() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+        ])?;
+        assert_snapshot!(app.render_once(60));
+        Ok(())
+    }
+
+    #[test]
+    fn q_unselects_a_selected_process() -> R<()> {
+        let mut app = test_app(vec![Process::fake(1, 0.0, None)])?;
+        simulate_key_press(&mut app, KeyCode::Enter)?;
+        assert_eq!(
+            app.update(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE))?,
+            UpdateResult::Continue
+        );
+        assert_eq!(app.ui_mode, UiMode::Normal);
+        Ok(())
+    }
+
+    #[test]
+    fn q_is_typed_when_editing_text() -> R<()> {
+        let mut app = test_app(vec![Process::fake(1, 0.0, None)])?;
+        simulate_key_press(&mut app, KeyCode::Char('/'))?;
+        simulate_key_press(&mut app, KeyCode::Char('q'))?;
+        assert_eq!(app.ui_mode, UiMode::EditingPattern);
+        assert_eq!(app.pattern().as_str(), "q");
+        let mut app = test_app(vec![Process::fake(1, 0.0, None)])?;
+        simulate_key_press(&mut app, KeyCode::Enter)?;
+        simulate_key_press(&mut app, KeyCode::Char(':'))?;
+        simulate_key_press(&mut app, KeyCode::Char('q'))?;
+        assert_eq!(app.ui_mode, UiMode::EnteringSignal(1.into()));
+        assert_eq!(app.signal_input, "kill q");
+        Ok(())
+    }
+
+    #[test]
+    fn shows_the_generation_of_processes() -> R<()> {
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![
+                Process::fake(1, 0.0, None),
+                Process::fake(2, 0.0, Some(1)),
+                Process::fake(3, 0.0, Some(2)),
+                Process::fake(4, 0.0, Some(1)),
+                Process::fake(5, 0.0, None),
+            ]),
+            &Args::parse_from(["treetop", "--generation"]).options(),
+        )?;
+        app.tick()?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn pinning_the_selected_row_to_the_top() -> R<()> {
+        let mut app = test_app((1..=7).map(|pid| Process::fake(pid, 0.0, None)).collect())?;
+        simulate_key_press(&mut app, KeyCode::Char('f'))?;
+        for _ in 0..5 {
+            simulate_key_press(&mut app, KeyCode::Down)?;
+        }
+        let area = Rect::new(0, 0, 80, 10);
+        app.render(area, &mut Buffer::empty(area));
+        assert_eq!(app.list_state.selected(), Some(5));
+        assert_eq!(app.list_state.offset(), 5);
+        Ok(())
+    }
+
+    fn app_with_cgroups() -> R<TreetopApp> {
+        let app = test_app_with_args(
+            vec![
+                Process::fake(1, 0.0, None),
+                Process::fake(2, 0.0, Some(1)).with_cgroup("/system.slice/docker-abc.scope"),
+                Process::fake(3, 0.0, Some(2)).with_cgroup("/system.slice/docker-abc.scope"),
+                Process::fake(4, 0.0, Some(1)).with_cgroup("/user.slice/session-2.scope"),
+            ],
+            &["--cgroup"],
+        )?;
+        Ok(app)
+    }
+
+    #[test]
+    fn shows_cgroups() -> R<()> {
+        assert_snapshot!(render_ui(app_with_cgroups()?));
+        Ok(())
+    }
+
+    #[test]
+    fn filtering_by_cgroup() -> R<()> {
+        let mut app = app_with_cgroups()?;
+        set_pattern(&mut app, "cgroup:docker")?;
+        app.tick()?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![1.into(), 2.into(), 3.into()]
+        );
+        set_pattern(&mut app, "cgroup:session")?;
+        app.tick()?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![1.into(), 4.into()]
+        );
+        set_pattern(&mut app, "cgroup")?;
+        app.tick()?;
+        assert_eq!(app.forest.iter().count(), 0);
+        Ok(())
+    }
+
+    fn app_with_ttys() -> R<TreetopApp> {
+        let app = test_app_with_args(
+            vec![
+                Process::fake(1, 0.0, None),
+                Process::fake(2, 0.0, Some(1)).with_tty("pts/3"),
+                Process::fake(3, 0.0, Some(2)).with_tty("pts/3"),
+                Process::fake(4, 0.0, Some(1)).with_tty("tty1"),
+            ],
+            &["--tty"],
+        )?;
+        Ok(app)
+    }
+
+    #[test]
+    fn shows_ttys() -> R<()> {
+        assert_snapshot!(render_ui(app_with_ttys()?));
+        Ok(())
+    }
+
+    #[test]
+    fn filtering_by_tty() -> R<()> {
+        let mut app = app_with_ttys()?;
+        set_pattern(&mut app, "tty:pts")?;
+        app.tick()?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![1.into(), 2.into(), 3.into()]
+        );
+        set_pattern(&mut app, "tty:^tty1$")?;
+        app.tick()?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![1.into(), 4.into()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fixed_strings_match_literally() -> R<()> {
+        let mut app = test_app_with_args(
+            vec![
+                Process::fake(1, 0.0, None).with_arguments(&["a.out"]),
+                Process::fake(2, 0.0, None).with_arguments(&["axout"]),
+                Process::fake(3, 0.0, None).with_arguments(&["node[1]"]),
+            ],
+            &["-F", "--pattern", "a.out"],
+        )?;
+        let ids = |app: &TreetopApp| app.forest.iter().map(Node::id).collect::<Vec<_>>();
+        assert_eq!(ids(&app), vec![1.into()]);
+        set_pattern(&mut app, "node[1]")?;
+        app.tick()?;
+        assert_eq!(ids(&app), vec![3.into()]);
+        assert_eq!(app.patterns[0].as_str(), "node[1]");
+        Ok(())
+    }
+
+    #[test]
+    fn toggling_fixed_strings_while_typing() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None).with_arguments(&["a.out"]),
+            Process::fake(2, 0.0, None).with_arguments(&["axout"]),
+        ])?;
+        simulate_key_press(&mut app, KeyCode::Char('/'))?;
+        for key in "a.out".chars() {
+            simulate_key_press(&mut app, KeyCode::Char(key))?;
+        }
+        let ids = |app: &TreetopApp| app.forest.iter().map(Node::id).collect::<Vec<_>>();
+        assert_eq!(ids(&app), vec![1.into(), 2.into()]);
+        app.update(KeyEvent::new(KeyCode::Char('f'), KeyModifiers::CONTROL))?;
+        assert_eq!(ids(&app), vec![1.into()]);
+        assert!(render_ui_with_width(app, 200)
+            .replace('\u{35f}', "")
+            .contains("type search pattern (name, literal, Aa on): a.out▌"));
+        Ok(())
+    }
+
+    #[test]
+    fn toggling_case_sensitivity_refilters() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None).with_arguments(&["Firefox"]),
+            Process::fake(2, 0.0, None).with_arguments(&["firefox"]),
+        ])?;
+        set_pattern(&mut app, "Firefox")?;
+        app.tick()?;
+        let ids = |app: &TreetopApp| app.forest.iter().map(Node::id).collect::<Vec<_>>();
+        assert_eq!(ids(&app), vec![1.into()]);
+        app.update(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL))?;
+        assert_eq!(app.ui_mode, UiMode::Normal);
+        assert_eq!(ids(&app), vec![1.into(), 2.into()]);
+        assert!(render_ui_with_width(app, 200)
+            .replace('\u{35f}', "")
+            .contains("search pattern (name, Aa off): Firefox"));
+        Ok(())
+    }
+
+    #[test]
+    fn ignoring_case_survives_typing_an_invalid_pattern() -> R<()> {
+        let mut app = test_app_with_args(
+            vec![
+                Process::fake(1, 0.0, None).with_arguments(&["Firefox"]),
+                Process::fake(2, 0.0, None).with_arguments(&["chrome"]),
+            ],
+            &["--ignore-case"],
+        )?;
+        simulate_key_press(&mut app, KeyCode::Char('/'))?;
+        for key in "fire(".chars() {
+            simulate_key_press(&mut app, KeyCode::Char(key))?;
+        }
+        assert!(matches!(app.patterns[0], Regex::Invalid { .. }));
+        simulate_key_press(&mut app, KeyCode::Char(')'))?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![1.into()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn inverting_marks() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(1)),
+            Process::fake(4, 0.0, Some(1)),
+        ])?;
+        simulate_key_press(&mut app, KeyCode::Char(' '))?;
+        simulate_key_press(&mut app, KeyCode::Down)?;
+        simulate_key_press(&mut app, KeyCode::Down)?;
+        simulate_key_press(&mut app, KeyCode::Char(' '))?;
+        assert_eq!(app.marked, HashSet::from([1.into(), 3.into()]));
+        simulate_key_press(&mut app, KeyCode::Char('!'))?;
+        assert_eq!(app.marked, HashSet::from([2.into(), 4.into()]));
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn marked_processes_can_be_signaled_together() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, None),
+            Process::fake(3, 0.0, None),
+        ])?;
+        app.signal_sender = SignalSender::fake();
+        simulate_key_press(&mut app, KeyCode::Char(' '))?;
+        simulate_key_press(&mut app, KeyCode::Char('!'))?;
+        app.update(KeyEvent::new(KeyCode::Char('t'), KeyModifiers::CONTROL))?;
+        assert_eq!(app.ui_mode, UiMode::ConfirmingMarkedSignal(Signal::SIGTERM));
+        simulate_key_press(&mut app, KeyCode::Char('y'))?;
+        assert_eq!(
+            app.signal_sender.sent(),
+            &[(2.into(), Signal::SIGTERM), (3.into(), Signal::SIGTERM)]
+        );
+        assert!(app.marked.is_empty());
+        assert_eq!(app.ui_mode, UiMode::Normal);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("sent SIGTERM to 2 processes")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn signaling_marked_processes_asks_first() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, None),
+        ])?;
+        app.signal_sender = SignalSender::fake();
+        simulate_key_press(&mut app, KeyCode::Char('!'))?;
+        app.update(KeyEvent::new(KeyCode::Char('t'), KeyModifiers::CONTROL))?;
+        simulate_key_press(&mut app, KeyCode::Char('n'))?;
+        assert_eq!(app.signal_sender.sent(), &[]);
+        assert_eq!(app.ui_mode, UiMode::Normal);
+        assert_eq!(app.marked.len(), 2);
+        app.update(KeyEvent::new(KeyCode::Char('t'), KeyModifiers::CONTROL))?;
+        let ui = render_ui_with_width(app, 120).replace('\u{35f}', "");
+        assert!(ui.contains("send SIGTERM to 2 marked processes?"));
+        Ok(())
+    }
+
+    #[test]
+    fn inverting_marks_keeps_hidden_marks() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, None),
+        ])?;
+        app.marked.insert(2.into());
+        set_pattern(&mut app, "one")?;
+        app.tick()?;
+        simulate_key_press(&mut app, KeyCode::Char('!'))?;
+        assert_eq!(app.marked, HashSet::from([1.into(), 2.into()]));
+        Ok(())
+    }
+
+    #[test]
+    fn revealing_the_siblings_of_a_match() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(1)),
+            Process::fake(4, 0.0, Some(1)),
+            Process::fake(5, 0.0, None),
+        ])?;
+        set_pattern(&mut app, "three")?;
+        app.tick()?;
+        let ids = |app: &TreetopApp| app.forest.iter().map(Node::id).collect::<Vec<_>>();
+        assert_eq!(ids(&app), vec![1.into(), 3.into()]);
+        simulate_key_press(&mut app, KeyCode::Down)?;
+        simulate_key_press(&mut app, KeyCode::Char('z'))?;
+        assert_eq!(ids(&app), vec![1.into(), 2.into(), 3.into(), 4.into()]);
+        assert_eq!(app.highlighted_process(), Some(3.into()));
+        app.tick()?;
+        assert_eq!(ids(&app).len(), 4);
+        simulate_key_press(&mut app, KeyCode::Char('z'))?;
+        assert_eq!(ids(&app), vec![1.into(), 3.into()]);
+        assert_eq!(app.highlighted_process(), Some(3.into()));
+        Ok(())
+    }
+
+    #[test]
+    fn changing_the_patterns_hides_revealed_siblings_again() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(1)),
+            Process::fake(4, 0.0, Some(1)),
+        ])?;
+        set_pattern(&mut app, "thre")?;
+        app.tick()?;
+        simulate_key_press(&mut app, KeyCode::Down)?;
+        simulate_key_press(&mut app, KeyCode::Char('z'))?;
+        simulate_key_press(&mut app, KeyCode::Char('/'))?;
+        simulate_key_press(&mut app, KeyCode::Char('e'))?;
+        simulate_key_press(&mut app, KeyCode::Enter)?;
+        app.tick()?;
+        let ids = |app: &TreetopApp| app.forest.iter().map(Node::id).collect::<Vec<_>>();
+        assert_eq!(ids(&app), vec![1.into(), 3.into()]);
+        simulate_key_press(&mut app, KeyCode::Down)?;
+        simulate_key_press(&mut app, KeyCode::Char('z'))?;
+        app.update(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL))?;
+        set_pattern(&mut app, "three")?;
+        app.tick()?;
+        assert_eq!(ids(&app), vec![1.into(), 3.into()]);
+        Ok(())
+    }
+
+    #[test]
+    fn filtering_by_environment_variable() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)).with_environment(&["HOME=/root", "RUST_LOG=debug"]),
+            Process::fake(3, 0.0, Some(1)).with_environment(&["RUST_LOG=info"]),
+            Process::fake(4, 0.0, Some(1)),
+        ])?;
+        set_pattern(&mut app, "env:RUST_LOG=debug")?;
+        app.tick()?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![1.into(), 2.into()]
+        );
+        set_pattern(&mut app, "env:^RUST_LOG=")?;
+        app.tick()?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![1.into(), 2.into(), 3.into()]
+        );
+        set_pattern(&mut app, "RUST_LOG")?;
+        app.tick()?;
+        assert_eq!(app.forest.iter().count(), 0);
+        Ok(())
     }
 
-    fn tick(&mut self) {
-        self.process_watcher.refresh();
-        self.update_processes();
+    fn app_with_users() -> R<TreetopApp> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None)
+                .with_arguments(&["one", "--config", "foo"])
+                .with_user("root"),
+            Process::fake(2, 0.0, None).with_user("alice"),
+            Process::fake(3, 0.0, None)
+                .with_arguments(&["three", "--user", "alice"])
+                .with_user("bob"),
+        ])?;
+        app.tick()?;
+        Ok(app)
     }
-}
 
-fn normalize_list_state<T>(list_state: &mut ListState, list: &[T], rect: &Rect) {
-    if let Some(ref mut selected) = list_state.selected_mut() {
-        *selected = (*selected).min(list.len().saturating_sub(1));
+    fn matching_pids(app: &mut TreetopApp, pattern: &str) -> R<Vec<sysinfo::Pid>> {
+        set_pattern(app, pattern)?;
+        app.tick()?;
+        Ok(app.forest.iter().map(Node::id).collect())
     }
-    *list_state.offset_mut() = list_state
-        .offset()
-        .min(list.len().saturating_sub(rect.height.into()));
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use crate::tui_app::TuiApp;
-    use crossterm::event::{KeyEventKind, KeyEventState};
-    use insta::assert_snapshot;
-    use ratatui::buffer::Cell;
-    use ratatui::layout::Rect;
-    use ratatui::style::Modifier;
-    use ratatui::widgets::ListState;
+    #[test]
+    fn searching_by_name() -> R<()> {
+        let mut app = app_with_users()?;
+        assert_eq!(app.search_scope, SearchScope::Name);
+        assert_eq!(matching_pids(&mut app, "two")?, vec![2.into()]);
+        assert_eq!(matching_pids(&mut app, "3")?, vec![3.into()]);
+        assert_eq!(matching_pids(&mut app, "alice")?, vec![]);
+        Ok(())
+    }
 
-    const RECT: Rect = Rect {
-        x: 0,
-        y: 0,
-        width: 80,
-        height: 20,
-    };
+    #[test]
+    fn searching_by_cmdline() -> R<()> {
+        let mut app = app_with_users()?;
+        app.search_scope = SearchScope::Cmdline;
+        assert_eq!(matching_pids(&mut app, "foo")?, vec![1.into()]);
+        assert_eq!(matching_pids(&mut app, "--user alice")?, vec![3.into()]);
+        assert_eq!(matching_pids(&mut app, "root")?, vec![]);
+        Ok(())
+    }
 
     #[test]
-    fn normalize_leaves_state_unmodified() {
-        let mut list_state = ListState::default().with_selected(Some(7)).with_offset(5);
-        normalize_list_state(&mut list_state, &vec![(); 30], &RECT);
-        assert_eq!(list_state.selected(), Some(7));
-        assert_eq!(list_state.offset(), 5);
+    fn searching_by_user() -> R<()> {
+        let mut app = app_with_users()?;
+        app.search_scope = SearchScope::User;
+        assert_eq!(matching_pids(&mut app, "alice")?, vec![2.into()]);
+        assert_eq!(matching_pids(&mut app, "root")?, vec![1.into()]);
+        assert_eq!(matching_pids(&mut app, "two")?, vec![]);
+        Ok(())
     }
 
     #[test]
-    fn normalize_caps_at_the_list_end() {
-        let mut list_state = ListState::default().with_selected(Some(11));
-        normalize_list_state(&mut list_state, &vec![(); 10], &RECT);
-        assert_eq!(list_state.selected(), Some(9));
+    fn searching_all_fields() -> R<()> {
+        let mut app = app_with_users()?;
+        app.search_scope = SearchScope::All;
+        assert_eq!(matching_pids(&mut app, "alice")?, vec![2.into(), 3.into()]);
+        assert_eq!(matching_pids(&mut app, "one|root")?, vec![1.into()]);
+        assert_eq!(matching_pids(&mut app, "^2$")?, vec![2.into()]);
+        Ok(())
     }
 
     #[test]
-    fn normalize_resets_offset_to_zero_when_the_list_fits_the_area() {
-        let mut list_state = ListState::default().with_selected(Some(0)).with_offset(5);
-        normalize_list_state(&mut list_state, &vec![(); 10], &RECT);
-        assert_eq!(list_state.offset(), 0);
+    fn ctrl_s_cycles_the_search_scope_while_editing() -> R<()> {
+        let mut app = app_with_users()?;
+        simulate_key_press(&mut app, KeyCode::Char('/'))?;
+        let mut scopes = Vec::new();
+        for _ in 0..4 {
+            app.update(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL))?;
+            scopes.push(app.search_scope);
+        }
+        assert_eq!(
+            scopes,
+            vec![
+                SearchScope::Cmdline,
+                SearchScope::User,
+                SearchScope::All,
+                SearchScope::Name
+            ]
+        );
+        assert_eq!(app.patterns_string(), "");
+        Ok(())
     }
 
     #[test]
-    fn normalize_scrolls_up_when_offset_is_too_big() {
-        let mut list_state = ListState::default().with_selected(Some(0)).with_offset(25);
-        normalize_list_state(&mut list_state, &vec![(); 30], &RECT);
-        assert_eq!(list_state.offset(), 10);
+    fn shows_the_search_scope_in_the_status_bar() -> R<()> {
+        let mut app = app_with_users()?;
+        simulate_key_press(&mut app, KeyCode::Char('/'))?;
+        app.update(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL))?;
+        simulate_key_press(&mut app, KeyCode::Char('x'))?;
+        assert!(render_ui_with_width(app, 200)
+            .replace('\u{35f}', "")
+            .contains("type search pattern (cmdline, Aa on): x▌"));
+        Ok(())
     }
 
-    fn test_app(processes: Vec<Process>) -> R<TreetopApp> {
-        let mut app = TreetopApp::new(ProcessWatcher::fake(processes), None)?;
-        app.tick();
+    fn app_with_presets(presets_file: &std::path::Path, preset: Option<&str>) -> R<TreetopApp> {
+        let presets_file = presets_file.to_str().unwrap();
+        let mut args = vec!["treetop", "--presets-file", presets_file];
+        args.extend(
+            preset
+                .map(|preset| ["--preset", preset])
+                .into_iter()
+                .flatten(),
+        );
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![
+                Process::fake(1, 0.0, None).with_user("root"),
+                Process::fake(2, 0.0, None).with_user("alice"),
+                Process::fake(3, 0.0, None).with_user("bob"),
+            ]),
+            &Args::parse_from(args).options(),
+        )?;
+        app.tick()?;
         Ok(app)
     }
 
-    fn render_ui(mut app: TreetopApp) -> String {
-        let area = Rect::new(0, 0, 80, 10);
-        let mut buffer = Buffer::filled(area, Cell::new(" "));
-        app.render(area, &mut buffer);
-        let mut result = String::new();
-        for y in 0..area.height {
-            for x in 0..area.width {
-                let symbol = buffer[(x, y)].symbol();
-                let symbol = if buffer[(x, y)].modifier.contains(Modifier::REVERSED) {
-                    crate::utils::test::underline(symbol)
-                } else {
-                    symbol.to_string()
-                };
-                result.push_str(&symbol);
-            }
-            result.push('\n')
+    #[test]
+    fn presets_can_be_saved_and_loaded_with_keys() -> R<()> {
+        let temp = TempDir::new("presets-keys-test")?;
+        let presets_file = temp.join("presets.json");
+        let mut app = app_with_presets(&presets_file, None)?;
+        set_pattern(&mut app, "alice")?;
+        app.search_scope = SearchScope::User;
+        simulate_key_press(&mut app, KeyCode::Char('s'))?;
+        assert_eq!(app.ui_mode, UiMode::EnteringPresetName);
+        for key in "mine".chars() {
+            simulate_key_press(&mut app, KeyCode::Char(key))?;
         }
-        result
+        simulate_key_press(&mut app, KeyCode::Enter)?;
+        assert_eq!(app.ui_mode, UiMode::Normal);
+        assert_eq!(app.current_preset.as_deref(), Some("mine"));
+
+        let mut app = app_with_presets(&presets_file, None)?;
+        assert_eq!(app.forest.iter().count(), 3);
+        simulate_key_press(&mut app, KeyCode::Char('o'))?;
+        assert_eq!(app.search_scope, SearchScope::User);
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![2.into()]
+        );
+        Ok(())
     }
 
-    fn simulate_key_press(app: &mut TreetopApp, code: KeyCode) -> R<UpdateResult> {
-        app.update(KeyEvent {
-            code,
-            modifiers: KeyModifiers::NONE,
-            kind: KeyEventKind::Press,
-            state: KeyEventState::NONE,
-        })
+    #[test]
+    fn presets_can_be_applied_on_startup() -> R<()> {
+        let temp = TempDir::new("presets-startup-test")?;
+        let presets_file = temp.join("presets.json");
+        let mut presets = Presets::load(Some(&presets_file))?;
+        presets.save(
+            "services",
+            Preset {
+                patterns: vec!["one".to_string(), "bob".to_string()],
+                search_scope: SearchScope::All,
+                heat_map: Some(HeatMap {
+                    cpu: [1.0, 2.0],
+                    ram_in_mb: [3, 4],
+                }),
+            },
+        )?;
+        let app = app_with_presets(&presets_file, Some("services"))?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![1.into(), 3.into()]
+        );
+        assert_eq!(
+            app.heat_map,
+            Some(HeatMap {
+                cpu: [1.0, 2.0],
+                ram_in_mb: [3, 4],
+            })
+        );
+        assert_eq!(
+            app_with_presets(&presets_file, Some("unknown"))
+                .unwrap_err()
+                .to_string(),
+            "unknown preset: unknown"
+        );
+        Ok(())
     }
 
-    fn set_pattern(app: &mut TreetopApp, pattern: &str) -> R<()> {
-        app.pattern = crate::regex::Regex::new(::regex::Regex::new(pattern)?);
+    #[test]
+    fn malformed_presets_only_fail_when_presets_are_used() -> R<()> {
+        let temp = TempDir::new("presets-malformed-test")?;
+        let presets_file = temp.join("presets.json");
+        std::fs::write(&presets_file, "not json")?;
+        let mut app = app_with_presets(&presets_file, None)?;
+        assert_eq!(app.forest.iter().count(), 3);
+        simulate_key_press(&mut app, KeyCode::Char('o'))?;
+        assert!(app
+            .status_message
+            .as_deref()
+            .is_some_and(|message| message.starts_with("invalid presets in")));
+        assert!(app_with_presets(&presets_file, Some("services")).is_err());
         Ok(())
     }
 
     #[test]
-    fn shows_a_tree_with_header_and_side_columns() -> R<()> {
-        let app = test_app(vec![
-            Process::fake(1, 4.0, None),
-            Process::fake(2, 3.0, Some(1)),
-            Process::fake(3, 2.0, Some(2)),
-            Process::fake(4, 1.0, None),
-            Process::fake(5, 0.0, Some(4)),
-        ])?;
+    fn processes_can_be_named_by_argv0_executable_or_comm() -> R<()> {
+        let mut process = Process::fake(1, 0.0, None)
+            .with_arguments(&["/usr/bin/argv-name", "--flag"])
+            .with_comm("comm-name");
+        process.name = "exe-name".to_string();
+        let mut app = test_app(vec![process])?;
+        let mut shown = Vec::new();
+        for _ in 0..3 {
+            shown.push((
+                app.name_source,
+                app.rows()[0].1.command_line(app.name_source),
+            ));
+            simulate_key_press(&mut app, KeyCode::Char('n'))?;
+        }
+        assert_eq!(
+            shown,
+            vec![
+                (NameSource::Argv0, "argv-name --flag".to_string()),
+                (NameSource::Exe, "exe-name --flag".to_string()),
+                (NameSource::Comm, "comm-name --flag".to_string()),
+            ]
+        );
+        assert_eq!(app.name_source, NameSource::Argv0);
+        for (source, visible, hidden) in [
+            (NameSource::Argv0, "argv-name", "comm-name"),
+            (NameSource::Exe, "exe-name", "argv-name"),
+            (NameSource::Comm, "comm-name", "exe-name"),
+        ] {
+            app.name_source = source;
+            set_pattern(&mut app, visible)?;
+            app.tick()?;
+            assert_eq!(app.forest.iter().count(), 1);
+            set_pattern(&mut app, hidden)?;
+            app.tick()?;
+            assert_eq!(app.forest.iter().count(), 0);
+        }
+        app.name_source = NameSource::Comm;
+        app.hide_arguments = true;
+        set_pattern(&mut app, "")?;
+        app.tick()?;
+        assert!(render_ui(app).contains("comm-name "));
+        Ok(())
+    }
+
+    #[test]
+    fn fuzzy_matches_are_ranked_by_score() -> R<()> {
+        let named = |pid, parent, name: &str| {
+            let mut process = Process::fake(pid, 0.0, parent);
+            process.name = name.to_string();
+            process
+        };
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![
+                named(1, None, "bash"),
+                named(2, Some(1), "firefox"),
+                named(3, Some(1), "fluff-finder"),
+                named(4, Some(3), "fx"),
+            ]),
+            &Args::parse_from(["treetop", "--fuzzy", "--pattern", "ff"]).options(),
+        )?;
+        app.tick()?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![3.into(), 2.into()]
+        );
         assert_snapshot!(render_ui(app));
         Ok(())
     }
 
     #[test]
-    fn processes_get_sorted_by_pid() -> R<()> {
-        let app = test_app(vec![
-            Process::fake(1, 1.0, None),
-            Process::fake(2, 2.0, None),
-            Process::fake(3, 4.0, None),
-            Process::fake(4, 3.0, None),
-        ])?;
+    fn processes_can_be_grouped_by_user() -> R<()> {
+        let mb = 2_u64.pow(20);
+        let app = test_app_with_args(
+            vec![
+                Process::fake(1, 1.0, None)
+                    .with_user("root")
+                    .with_ram(10 * mb),
+                Process::fake(2, 10.0, Some(1))
+                    .with_user("alice")
+                    .with_ram(100 * mb),
+                Process::fake(3, 20.0, Some(2))
+                    .with_user("alice")
+                    .with_ram(200 * mb),
+                Process::fake(4, 30.0, Some(1))
+                    .with_user("bob")
+                    .with_ram(50 * mb),
+                Process::fake(5, 5.0, Some(4)).with_user("alice"),
+                Process::fake(6, 0.0, None),
+            ],
+            &["--group-by-user", "--sort", "cpu"],
+        )?;
+        let groups = app
+            .forest
+            .iter()
+            .map(|process| {
+                (
+                    process.name.clone(),
+                    process.group_size(),
+                    process.usage(Accumulation::default()),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            vec![
+                ("alice".to_string(), 3, "cpu 35%, ram 300MB".to_string()),
+                ("bob".to_string(), 1, "cpu 30%, ram 50MB".to_string()),
+                ("root".to_string(), 1, "cpu 1%, ram 10MB".to_string()),
+                ("[unknown]".to_string(), 1, "cpu 0%, ram 0MB".to_string()),
+            ]
+        );
         assert_snapshot!(render_ui(app));
         Ok(())
     }
 
     #[test]
-    fn processes_can_be_sorted_by_cpu() -> R<()> {
+    fn g_cycles_through_groupings() -> R<()> {
+        let mut app = test_app(vec![Process::fake(1, 0.0, None)])?;
+        assert_eq!(app.grouping, None);
+        simulate_key_press(&mut app, KeyCode::Char('g'))?;
+        assert_eq!(app.grouping, Some(Grouping::Executable));
+        simulate_key_press(&mut app, KeyCode::Char('g'))?;
+        assert_eq!(app.grouping, Some(Grouping::User));
+        simulate_key_press(&mut app, KeyCode::Char('g'))?;
+        assert_eq!(app.grouping, None);
+        Ok(())
+    }
+
+    #[test]
+    fn processes_can_be_grouped_by_executable() -> R<()> {
+        let chrome = |pid, cpu, parent| {
+            let mut process = Process::fake(pid, cpu, Some(parent)).with_ram(100 * 2_u64.pow(20));
+            process.name = "chrome".to_string();
+            process
+        };
         let mut app = test_app(vec![
             Process::fake(1, 1.0, None),
-            Process::fake(2, 2.0, None),
-            Process::fake(3, 4.0, None),
-            Process::fake(4, 3.0, None),
+            chrome(2, 10.0, 1),
+            chrome(3, 20.0, 2),
+            chrome(4, 30.0, 2),
+            Process::fake(5, 5.0, Some(1)),
         ])?;
-        simulate_key_press(&mut app, KeyCode::Tab)?;
+        simulate_key_press(&mut app, KeyCode::Char('g'))?;
+        let groups = app
+            .forest
+            .iter()
+            .map(|process| (process.name.clone(), process.group_size()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            vec![
+                ("one".to_string(), 1),
+                ("chrome".to_string(), 3),
+                ("five".to_string(), 1)
+            ]
+        );
         assert_snapshot!(render_ui(app));
         Ok(())
     }
 
     #[test]
-    fn more_complicated_tree() -> R<()> {
-        let app = test_app(vec![
-            Process::fake(1, 1.0, None),
-            Process::fake(2, 2.0, Some(1)),
-            Process::fake(3, 3.0, Some(2)),
-            Process::fake(4, 4.0, Some(1)),
-            Process::fake(5, 5.0, Some(4)),
-            Process::fake(6, 5.0, Some(4)),
-            Process::fake(7, 5.0, Some(6)),
+    fn grouped_processes_cannot_be_signaled() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
         ])?;
-        assert_snapshot!(render_ui(app));
+        app.signal_sender = SignalSender::fake();
+        simulate_key_press(&mut app, KeyCode::Char('g'))?;
+        simulate_key_press(&mut app, KeyCode::Enter)?;
+        for key in ['t', 'k', '1', ':'] {
+            simulate_key_press(&mut app, KeyCode::Char(key))?;
+        }
+        assert_eq!(app.signal_sender.sent(), &[]);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("can't signal grouped processes")
+        );
+        let ui = render_ui_with_width(app, 200).replace('\u{35f}', "");
+        assert!(!ui.contains("SIGTERM"));
         Ok(())
     }
 
     #[test]
-    fn filtering() -> R<()> {
+    fn breakdown_lists_the_usage_of_each_child() -> R<()> {
         let mut app = test_app(vec![
-            Process::fake(1, 1.0, None),
-            Process::fake(2, 2.0, Some(1)),
-            Process::fake(3, 3.0, Some(2)),
-            Process::fake(4, 4.0, Some(1)),
-            Process::fake(5, 5.0, Some(4)),
-            Process::fake(6, 5.0, Some(4)),
-            Process::fake(7, 5.0, Some(6)),
+            Process::fake(1, 1.0, None).with_ram(2 * 2_u64.pow(20)),
+            Process::fake(2, 2.0, Some(1)).with_ram(3 * 2_u64.pow(20)),
+            Process::fake(3, 4.0, Some(2)).with_ram(4 * 2_u64.pow(20)),
+            Process::fake(4, 8.0, Some(1)).with_ram(5 * 2_u64.pow(20)),
         ])?;
-        set_pattern(&mut app, "four")?;
-        app.tick();
+        simulate_key_press(&mut app, KeyCode::Enter)?;
+        simulate_key_press(&mut app, KeyCode::Char('b'))?;
+        assert_eq!(
+            app.breakdown(1.into()),
+            vec![
+                "one (1) itself: cpu 1%, ram 2MB",
+                "  two (2): cpu 6%, ram 7MB",
+                "  four (4): cpu 8%, ram 5MB",
+            ]
+        );
         assert_snapshot!(render_ui(app));
         Ok(())
     }
 
     #[test]
-    fn filtering_with_regexes() -> R<()> {
+    fn the_focused_breakdown_scrolls_instead_of_the_list() -> R<()> {
+        let mut app = test_app(
+            (1..=7)
+                .map(|pid| Process::fake(pid, 0.0, (pid > 1).then_some(1)))
+                .collect(),
+        )?;
+        simulate_key_press(&mut app, KeyCode::Enter)?;
+        simulate_key_press(&mut app, KeyCode::Char('b'))?;
+        simulate_key_press(&mut app, KeyCode::Char('f'))?;
+        simulate_key_press(&mut app, KeyCode::Down)?;
+        simulate_key_press(&mut app, KeyCode::Down)?;
+        assert_eq!(app.breakdown_offset, 2);
+        assert_eq!(app.list_state.selected(), Some(0));
+        simulate_key_press(&mut app, KeyCode::Up)?;
+        assert_eq!(app.breakdown_offset, 1);
+        assert_eq!(app.list_state.selected(), Some(0));
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn breakdown_scrolling_stops_at_the_end() -> R<()> {
+        let mut app = test_app(
+            (1..=7)
+                .map(|pid| Process::fake(pid, 0.0, (pid > 1).then_some(1)))
+                .collect(),
+        )?;
+        simulate_key_press(&mut app, KeyCode::Enter)?;
+        simulate_key_press(&mut app, KeyCode::Char('b'))?;
+        simulate_key_press(&mut app, KeyCode::Char('f'))?;
+        simulate_key_press(&mut app, KeyCode::PageDown)?;
+        let area = Rect::new(0, 0, 80, 10);
+        app.render(area, &mut Buffer::empty(area));
+        // 7 lines, of which 3 fit below the list.
+        assert_eq!(app.breakdown_offset, 4);
+        simulate_key_press(&mut app, KeyCode::Char('f'))?;
+        simulate_key_press(&mut app, KeyCode::Down)?;
+        assert_eq!(app.breakdown_offset, 4);
+        assert_eq!(app.list_state.selected(), Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn wrapping_shows_the_full_command_of_the_highlighted_row() -> R<()> {
+        let arguments = [
+            "/usr/bin/python3",
+            "-m",
+            "http.server",
+            "--bind",
+            "127.0.0.1",
+            "--directory",
+            "/home/user/projects/some/deeply/nested/directory/that/does/not/fit",
+            "8080",
+        ];
+        let app = test_app_with_args(
+            vec![
+                Process::fake(1, 0.0, None).with_arguments(&arguments),
+                Process::fake(2, 0.0, Some(1)).with_arguments(&arguments),
+            ],
+            &["--wrap"],
+        )?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn wrapped_rows_at_the_bottom_stay_in_view() -> R<()> {
+        let mut app = test_app(
+            (1..=7)
+                .map(|pid| {
+                    Process::fake(pid, 0.0, None).with_arguments(&["command", &"x".repeat(50)])
+                })
+                .collect(),
+        )?;
+        simulate_key_press(&mut app, KeyCode::Char('d'))?;
+        for _ in 0..6 {
+            simulate_key_press(&mut app, KeyCode::Down)?;
+        }
+        let ui = render_ui(app);
+        let lines = ui.lines().collect::<Vec<_>>();
+        assert!(lines[7].contains("▶ command"));
+        assert!(lines[8].trim().starts_with("xxx"));
+        Ok(())
+    }
+
+    #[test]
+    fn wraps_commands_at_their_column() {
+        assert_eq!(
+            wrap_command("abcdefghij", 6, 10),
+            vec!["      efgh".to_string(), "      ij".to_string()]
+        );
+        assert_eq!(wrap_command("abc", 6, 10), Vec::<String>::new());
+        assert_eq!(wrap_command("abc", 10, 10), Vec::<String>::new());
+    }
+
+    #[test]
+    fn shows_oom_scores() -> R<()> {
+        let app = test_app_with_args(
+            vec![
+                Process::fake(1, 0.0, None).with_oom_score(0),
+                Process::fake(2, 0.0, Some(1)).with_oom_score(666),
+                Process::fake(3, 0.0, Some(1)),
+            ],
+            &["--oom-score"],
+        )?;
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn renders_inverted_trees() -> R<()> {
         let mut app = test_app(vec![
             Process::fake(1, 0.0, None),
             Process::fake(2, 0.0, Some(1)),
-            Process::fake(3, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(2)),
             Process::fake(4, 0.0, Some(1)),
         ])?;
-        set_pattern(&mut app, "two|three")?;
-        app.tick();
+        simulate_key_press(&mut app, KeyCode::Char('i'))?;
+        assert_eq!(app.list_state.selected(), Some(3));
+        assert_eq!(app.highlighted_process(), Some(1.into()));
         assert_snapshot!(render_ui(app));
         Ok(())
     }
 
     #[test]
-    fn filtering_by_pid() -> R<()> {
+    fn shows_the_ancestors_of_the_top_row_when_scrolled_into_a_subtree() -> R<()> {
         let mut app = test_app(vec![
             Process::fake(1, 0.0, None),
-            Process::fake(2, 0.0, None),
-            Process::fake(3, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+            Process::fake(3, 0.0, Some(2)),
+            Process::fake(4, 0.0, Some(3)),
+            Process::fake(5, 0.0, Some(4)),
+            Process::fake(6, 0.0, Some(4)),
+            Process::fake(7, 0.0, Some(1)),
         ])?;
-        set_pattern(&mut app, "2")?;
-        app.tick();
+        simulate_key_press(&mut app, KeyCode::Char('f'))?;
+        for _ in 0..3 {
+            simulate_key_press(&mut app, KeyCode::Down)?;
+        }
+        assert_snapshot!(render_ui(app));
+        Ok(())
+    }
+
+    #[test]
+    fn does_not_show_ancestors_for_top_level_rows() -> R<()> {
+        let app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, Some(1)),
+        ])?;
+        let ui = render_ui(app).replace('\u{35f}', "");
+        assert!(!ui.contains('›'));
+        Ok(())
+    }
+
+    fn app_sorted_by_own_cpu() -> R<TreetopApp> {
+        let app = test_app_with_args(
+            vec![
+                Process::fake(1, 0.0, None),
+                Process::fake(2, 50.0, Some(1)),
+                Process::fake(3, 5.0, Some(2)),
+                Process::fake(4, 80.0, Some(2)),
+                Process::fake(5, 20.0, Some(1)),
+                Process::fake(6, 60.0, None),
+            ],
+            &["--own-cpu", "--sort", "cpu"],
+        )?;
+        Ok(app)
+    }
+
+    #[test]
+    fn top_only_shows_the_roots_using_the_most_cpu() -> R<()> {
+        let app = test_app_with_args(
+            vec![
+                Process::fake(1, 10.0, None),
+                Process::fake(2, 30.0, None),
+                Process::fake(3, 5.0, None),
+                Process::fake(4, 20.0, None),
+                Process::fake(5, 25.0, Some(1)),
+                Process::fake(6, 1.0, None),
+            ],
+            &["--top", "2"],
+        )?;
+        assert_snapshot!(render_ui_with_width(app, 60));
+        Ok(())
+    }
+
+    #[test]
+    fn top_can_be_toggled() -> R<()> {
+        let processes = (1..=7)
+            .map(|pid| Process::fake(pid, pid as f32, None))
+            .collect();
+        let mut app = test_app(processes)?;
+        simulate_key_press(&mut app, KeyCode::Char('x'))?;
+        assert_eq!(app.forest.iter().count(), 7);
+        app.top_count = 3;
+        simulate_key_press(&mut app, KeyCode::Char('x'))?;
+        simulate_key_press(&mut app, KeyCode::Char('x'))?;
+        assert_eq!(
+            app.forest.iter().map(Node::id).collect::<Vec<_>>(),
+            vec![5.into(), 6.into(), 7.into()]
+        );
+        simulate_key_press(&mut app, KeyCode::Char('x'))?;
+        assert_eq!(app.forest.iter().count(), 7);
+        Ok(())
+    }
+
+    #[test]
+    fn sorts_within_siblings() -> R<()> {
+        assert_snapshot!(render_ui(app_sorted_by_own_cpu()?));
+        Ok(())
+    }
+
+    #[test]
+    fn sorts_globally() -> R<()> {
+        let mut app = app_sorted_by_own_cpu()?;
+        simulate_key_press(&mut app, KeyCode::Char('v'))?;
+        assert_eq!(app.highlighted_process(), Some(6.into()));
         assert_snapshot!(render_ui(app));
         Ok(())
     }
@@ -449,13 +5734,29 @@ mod test {
         simulate_key_press(&mut app, KeyCode::Char('/'))?;
         simulate_key_press(&mut app, KeyCode::Char('a'))?;
         simulate_key_press(&mut app, KeyCode::Char('b'))?;
-        assert_eq!(app.pattern.as_str(), "ab");
+        assert_eq!(app.pattern().as_str(), "ab");
         simulate_key_press(&mut app, KeyCode::Backspace)?;
-        assert_eq!(app.pattern.as_str(), "a");
+        assert_eq!(app.pattern().as_str(), "a");
         simulate_key_press(&mut app, KeyCode::Char('('))?;
         simulate_key_press(&mut app, KeyCode::Char('b'))?;
         simulate_key_press(&mut app, KeyCode::Char(')'))?;
-        assert_eq!(app.pattern.as_str(), "a(b)");
+        assert_eq!(app.pattern().as_str(), "a(b)");
+        Ok(())
+    }
+
+    #[test]
+    fn clearing_the_pattern_shows_all_processes_again() -> R<()> {
+        let mut app = test_app(vec![
+            Process::fake(1, 0.0, None),
+            Process::fake(2, 0.0, None),
+            Process::fake(3, 0.0, None),
+        ])?;
+        set_pattern(&mut app, "two")?;
+        app.tick()?;
+        assert_eq!(app.forest.iter().count(), 1);
+        app.update(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL))?;
+        assert_eq!(app.pattern().as_str(), "");
+        assert_eq!(app.forest.iter().count(), 3);
         Ok(())
     }
 
@@ -486,4 +5787,30 @@ mod test {
         assert_eq!(app.ui_mode, UiMode::ProcessSelected(2.into()));
         Ok(())
     }
+
+    fn tree_style_test_app(style: &str) -> R<TreetopApp> {
+        let app = test_app_with_args(
+            vec![
+                Process::fake(1, 0.0, None),
+                Process::fake(2, 0.0, Some(1)),
+                Process::fake(3, 0.0, Some(2)),
+                Process::fake(4, 0.0, Some(2)),
+                Process::fake(5, 0.0, Some(1)),
+            ],
+            &["--tree-style", style],
+        )?;
+        Ok(app)
+    }
+
+    #[test]
+    fn renders_rounded_tree_style() -> R<()> {
+        assert_snapshot!(render_ui(tree_style_test_app("rounded")?));
+        Ok(())
+    }
+
+    #[test]
+    fn renders_thick_tree_style() -> R<()> {
+        assert_snapshot!(render_ui(tree_style_test_app("thick")?));
+        Ok(())
+    }
 }
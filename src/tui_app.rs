@@ -1,15 +1,19 @@
 use crate::R;
 use crossterm::{
-    event::{self, KeyEvent, KeyEventKind},
+    event::{
+        self, DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+        Event, KeyEvent, KeyEventKind, MouseEvent,
+    },
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
-    prelude::{CrosstermBackend, Terminal},
+    prelude::{Backend, CrosstermBackend, Terminal},
     widgets::StatefulWidget,
 };
+use std::time::Duration;
 use std::{
     io::stdout,
     marker::PhantomData,
@@ -19,16 +23,24 @@ use std::{
     },
     time::Instant,
 };
-use std::{io::Stdout, time::Duration};
 
 pub(crate) trait TuiApp {
-    fn tick(&mut self);
+    fn tick(&mut self) -> R<UpdateResult>;
 
     fn update(&mut self, event: KeyEvent) -> R<UpdateResult>;
 
+    fn mouse(&mut self, event: MouseEvent);
+
     fn render(&mut self, area: Rect, buf: &mut Buffer);
+
+    // How long to wait between ticks while the terminal is unfocused or
+    // there was no input for `IDLE_AFTER`, to save power.
+    fn idle_tick_length(&self) -> Option<Duration> {
+        None
+    }
 }
 
+#[derive(Debug, PartialEq, Eq)]
 pub(crate) enum UpdateResult {
     Continue,
     Exit,
@@ -44,9 +56,11 @@ impl<T: TuiApp> StatefulWidget for &mut AppWrapper<T> {
     }
 }
 
-pub(crate) fn run_ui<T: TuiApp>(app: T) -> R<()> {
+pub(crate) fn run_ui<T: TuiApp>(app: &mut T) -> R<()> {
     let termination_signal_received = setup_signal_handlers()?;
     stdout().execute(EnterAlternateScreen)?;
+    stdout().execute(EnableMouseCapture)?;
+    stdout().execute(EnableFocusChange)?;
     enable_raw_mode()?;
     std::panic::set_hook(Box::new(|panic_info| {
         let _ = reset_terminal();
@@ -65,46 +79,101 @@ pub(crate) fn run_ui<T: TuiApp>(app: T) -> R<()> {
 }
 
 fn reset_terminal() -> R<()> {
+    stdout().execute(DisableFocusChange)?;
+    stdout().execute(DisableMouseCapture)?;
     stdout().execute(LeaveAlternateScreen)?;
     disable_raw_mode()?;
     Ok(())
 }
 
-fn main_loop<T: TuiApp>(mut app: T, termination_signal_received: Arc<AtomicBool>) -> R<()> {
+pub(crate) const TICK_LENGTH: Duration = Duration::from_millis(1000);
+
+const IDLE_AFTER: Duration = Duration::from_secs(60);
+
+fn tick_length(
+    since_last_input: Duration,
+    focused: bool,
+    idle_tick_length: Option<Duration>,
+) -> Duration {
+    match idle_tick_length {
+        Some(idle_tick_length) if !focused || since_last_input >= IDLE_AFTER => idle_tick_length,
+        _ => TICK_LENGTH,
+    }
+}
+
+// Waiting for input never takes less than this, so the loop doesn't spin.
+const MIN_POLL_TIMEOUT: Duration = Duration::from_millis(50);
+
+// How long to wait for input before the next tick. When ticking and
+// redrawing take longer than a tick (e.g. for huge process trees), input
+// still gets at least half as much time as they took.
+fn poll_timeout(tick_length: Duration, since_last_tick: Duration, tick_cost: Duration) -> Duration {
+    tick_length
+        .saturating_sub(since_last_tick)
+        .max(MIN_POLL_TIMEOUT)
+        .max(tick_cost / 2)
+}
+
+fn main_loop<T: TuiApp>(app: &mut T, termination_signal_received: Arc<AtomicBool>) -> R<()> {
     let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
     terminal.clear()?;
-    let tick_length = Duration::from_millis(1000);
     let mut last_tick = Instant::now();
-    app.tick();
-    redraw(&mut terminal, &mut app)?;
+    let mut last_input = Instant::now();
+    let mut focused = true;
+    if app.tick()? == UpdateResult::Exit {
+        return Ok(());
+    }
+    redraw(&mut terminal, app)?;
+    let mut tick_cost = last_tick.elapsed();
     loop {
         if termination_signal_received.load(Ordering::Relaxed) {
             break;
         }
-        let has_event = event::poll(
-            tick_length
-                .checked_sub(last_tick.elapsed())
-                .unwrap_or_default(),
-        )?;
+        let tick_length = tick_length(last_input.elapsed(), focused, app.idle_tick_length());
+        let has_event = event::poll(poll_timeout(tick_length, last_tick.elapsed(), tick_cost))?;
         if has_event {
             let event = event::read()?;
-            if let event::Event::Key(key) = event {
-                if key.kind == KeyEventKind::Press {
-                    match app.update(key)? {
-                        UpdateResult::Continue => {}
-                        UpdateResult::Exit => break,
-                    }
-                }
+            match event {
+                Event::FocusLost => focused = false,
+                Event::FocusGained => focused = true,
+                _ => {}
+            }
+            last_input = Instant::now();
+            match handle_event(&mut terminal, app, event)? {
+                UpdateResult::Continue => {}
+                UpdateResult::Exit => break,
             }
         } else {
-            app.tick();
             last_tick = Instant::now();
+            if app.tick()? == UpdateResult::Exit {
+                break;
+            }
+            redraw(&mut terminal, app)?;
+            tick_cost = last_tick.elapsed();
         }
-        redraw(&mut terminal, &mut app)?;
     }
     Ok(())
 }
 
+fn handle_event<B: Backend, T: TuiApp>(
+    terminal: &mut Terminal<B>,
+    app: &mut T,
+    event: Event,
+) -> R<UpdateResult> {
+    match event {
+        Event::Key(key) if key.kind == KeyEventKind::Press => {
+            if let UpdateResult::Exit = app.update(key)? {
+                return Ok(UpdateResult::Exit);
+            }
+        }
+        Event::Mouse(mouse) => app.mouse(mouse),
+        Event::Resize(_, _) => terminal.autoresize()?,
+        _ => {}
+    }
+    redraw(terminal, app)?;
+    Ok(UpdateResult::Continue)
+}
+
 fn setup_signal_handlers() -> R<Arc<AtomicBool>> {
     use signal_hook::consts::{SIGINT, SIGTERM};
     use signal_hook::flag::register;
@@ -114,9 +183,86 @@ fn setup_signal_handlers() -> R<Arc<AtomicBool>> {
     Ok(result)
 }
 
-fn redraw<T: TuiApp>(terminal: &mut Terminal<CrosstermBackend<Stdout>>, app: &mut T) -> R<()> {
+fn redraw<B: Backend, T: TuiApp>(terminal: &mut Terminal<B>, app: &mut T) -> R<()> {
     terminal.draw(|frame| {
         frame.render_stateful_widget(&mut AppWrapper(PhantomData), frame.area(), app);
     })?;
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::process::{Process, ProcessWatcher};
+    use crate::treetop_app::TreetopApp;
+    use crate::Args;
+    use clap::Parser;
+    use ratatui::backend::TestBackend;
+
+    #[test]
+    fn ticks_slow_down_after_the_idle_threshold() {
+        let idle = Some(Duration::from_secs(10));
+        assert_eq!(tick_length(Duration::from_secs(1), true, idle), TICK_LENGTH);
+        assert_eq!(tick_length(IDLE_AFTER, true, idle), Duration::from_secs(10));
+        assert_eq!(
+            tick_length(Duration::from_secs(1), false, idle),
+            Duration::from_secs(10)
+        );
+        assert_eq!(tick_length(IDLE_AFTER, false, None), TICK_LENGTH);
+    }
+
+    #[test]
+    fn poll_timeout_waits_for_the_rest_of_the_tick() {
+        assert_eq!(
+            poll_timeout(
+                Duration::from_millis(1000),
+                Duration::from_millis(300),
+                Duration::from_millis(10)
+            ),
+            Duration::from_millis(700)
+        );
+    }
+
+    #[test]
+    fn poll_timeout_is_clamped_to_a_minimum() {
+        assert_eq!(
+            poll_timeout(
+                Duration::from_millis(1000),
+                Duration::from_millis(1500),
+                Duration::from_millis(10)
+            ),
+            MIN_POLL_TIMEOUT
+        );
+    }
+
+    #[test]
+    fn slow_ticks_leave_time_for_input() {
+        assert_eq!(
+            poll_timeout(
+                Duration::from_millis(1000),
+                Duration::from_millis(3000),
+                Duration::from_millis(3000)
+            ),
+            Duration::from_millis(1500)
+        );
+    }
+
+    #[test]
+    fn resize_events_redraw_at_small_sizes() -> R<()> {
+        let mut app = TreetopApp::new(
+            ProcessWatcher::fake(vec![
+                Process::fake(1, 0.0, None),
+                Process::fake(2, 0.0, Some(1)),
+            ]),
+            &Args::parse_from(["treetop"]).options(),
+        )?;
+        app.tick()?;
+        let mut terminal = Terminal::new(TestBackend::new(80, 10))?;
+        for (width, height) in [(40, 5), (10, 3), (5, 2), (1, 1), (0, 0), (80, 10)] {
+            terminal.backend_mut().resize(width, height);
+            handle_event(&mut terminal, &mut app, Event::Resize(width, height))?;
+            assert_eq!(terminal.get_frame().area(), Rect::new(0, 0, width, height));
+        }
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
 #[cfg(test)]
 pub(crate) mod test {
+    use std::path::{Path, PathBuf};
+
     pub(crate) fn render_number(n: usize) -> &'static str {
         match n {
             1 => "one",
@@ -15,4 +17,32 @@ pub(crate) mod test {
     pub(crate) fn underline(s: &str) -> String {
         format!("{}\u{35f}", s)
     }
+
+    // A fresh directory per test (and test process), removed again when
+    // dropped, even if the test fails.
+    pub(crate) struct TempDir(PathBuf);
+
+    impl TempDir {
+        pub(crate) fn new(test: &str) -> std::io::Result<TempDir> {
+            let path =
+                std::env::temp_dir().join(format!("treetop-{}-{}", test, std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path)?;
+            Ok(TempDir(path))
+        }
+
+        pub(crate) fn path(&self) -> &Path {
+            &self.0
+        }
+
+        pub(crate) fn join(&self, file: &str) -> PathBuf {
+            self.0.join(file)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
 }